@@ -1,8 +1,8 @@
 extern crate image;
-extern crate yaml_rust;
+extern crate yaml_rust2;
 use std::{env, fs};
 use std::path::PathBuf;
-use yaml_rust::YamlLoader;
+use yaml_rust2::YamlLoader;
 use path_absolutize::Absolutize;
 use std::collections::HashSet;
 
@@ -84,7 +84,7 @@ fn build_embedded_config() {
             let sample_file_resolved = sample_file_resolved.to_str().unwrap();
 
             generated_source.push_str("        (");
-            generated_source.push_str(&rust_string(&sample_file));
+            generated_source.push_str(&rust_string(sample_file));
             generated_source.push_str(", include_bytes!(");
             generated_source.push_str(&rust_string(sample_file_resolved));
             generated_source.push_str(")),\n");
@@ -98,9 +98,9 @@ fn build_embedded_config() {
     generated_source.push_str("pub fn embedded_config() -> Option<&'static str> {\n");
     generated_source.push_str("Some(\n");
     generated_source.push_str(&rust_string(&config_str));
-    generated_source.push_str("\n");
+    generated_source.push('\n');
     generated_source.push_str(")\n");
-    generated_source.push_str("}");
+    generated_source.push('}');
 
     fs::write(&out_file, &generated_source).expect("failed to write embedded_config.rs");
 }
@@ -0,0 +1,436 @@
+use crate::config::AudioConfig;
+use crate::error::SoundThreadError;
+use crate::name_match::name_matches;
+use crate::mixer::{MixSource, MIX_CHANNELS, MIX_SAMPLE_RATE};
+use rodio::cpal::traits::HostTrait;
+use rodio::{cpal, Device, DeviceTrait, OutputStream, OutputStreamHandle, Source, StreamError};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{RecvTimeoutError, Sender};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the mix is consumed by the outputs that do not play on a sound device
+const CONSUME_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often the devices are listed while the mix is played on the default device, to notice
+/// when the preferred device has returned
+const PREFERRED_DEVICE_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// See AudioOutput::check()
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AudioOutputStatus {
+    Playing,
+    /// The mix is no longer being played, for example because the sound device has been
+    /// disconnected
+    Lost,
+    /// The mix is being played on another device, because the preferred one had been
+    /// disconnected. The preferred device is available again.
+    PreferredDeviceAvailable,
+}
+
+/// Plays the mix of all banks. Implemented by SystemAudioOutput, NullAudioOutput,
+/// CaptureAudioOutput and OfflineAudioOutput. Playback stops when the AudioOutput is dropped.
+pub trait AudioOutput {
+    /// Start playing the mix. This is called when the SoundThread starts, and with a new mix each
+    /// time check() has returned something other than Playing.
+    fn play(&mut self, source: MixSource) -> Result<(), SoundThreadError>;
+
+    /// Called regularly by the SoundThread, to notice when the mix has to be played again
+    fn check(&mut self) -> AudioOutputStatus {
+        AudioOutputStatus::Playing
+    }
+
+    /// The name of the sound device that the mix is played on, if any
+    fn device_name(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// The name of each sound output device of the system, along with true for the default device
+pub fn output_devices() -> Result<Vec<(String, bool)>, SoundThreadError> {
+    let host = cpal::default_host();
+    let default_name = host.default_output_device().and_then(|device| device.name().ok());
+
+    Ok(host.output_devices()?
+        .filter_map(|device| device.name().ok())
+        .map(|name| {
+            let is_default = default_name.as_deref() == Some(name.as_str());
+            (name, is_default)
+        })
+        .collect())
+}
+
+/// Returns the first sound output device of which the name matches the pattern, see
+/// `name_matches`
+fn find_output_device(pattern: &str) -> Result<Option<Device>, SoundThreadError> {
+    Ok(cpal::default_host()
+        .output_devices()?
+        .find(|device| device.name().is_ok_and(|name| name_matches(pattern, &name))))
+}
+
+/// Counts the samples that are taken from a source, to notice when a sound device has stopped
+/// playing it
+struct CountedSource<S> {
+    source: S,
+    count: Arc<AtomicUsize>,
+}
+
+impl<S: Source<Item = f32>> Iterator for CountedSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.source.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.source.size_hint()
+    }
+}
+
+impl<S: Source<Item = f32>> Source for CountedSource<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+}
+
+/// Looks for the preferred device on a separate thread, because listing the devices can take long
+/// enough to delay the SoundThread
+struct PreferredDeviceWatcher {
+    available: Arc<AtomicBool>,
+    // dropping the sender stops the thread
+    stop_sender: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PreferredDeviceWatcher {
+    fn new(pattern: String) -> Self {
+        let available = Arc::new(AtomicBool::new(false));
+        let thread_available = Arc::clone(&available);
+        let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+
+        let handle = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stop_receiver.recv_timeout(PREFERRED_DEVICE_CHECK_INTERVAL) {
+                if matches!(find_output_device(&pattern), Ok(Some(_))) {
+                    thread_available.store(true, Ordering::Relaxed);
+                    return;
+                }
+            }
+        });
+
+        Self { available, stop_sender: Some(stop_sender), handle: Some(handle) }
+    }
+
+    fn is_available(&self) -> bool {
+        self.available.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for PreferredDeviceWatcher {
+    fn drop(&mut self) {
+        self.stop_sender = None;
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                eprintln!("The thread which looks for the preferred audio output device has panicked");
+            }
+        }
+    }
+}
+
+/// Plays the mix on a sound output device of the system. If the device is disconnected, the mix
+/// is played on the default device until it returns.
+pub struct SystemAudioOutput {
+    /// A name pattern, the default device is used if it is None
+    device: Option<String>,
+    fallback_to_default: bool,
+    /// A name pattern of the device that the mix should be played on: `device`, or the name of the
+    /// default device when the mix was played for the first time. None until then.
+    preferred: Option<String>,
+    /// The name of the device that the mix is played on
+    playing_on: Option<String>,
+    /// The amount of samples that have been played, on any device
+    played: Arc<AtomicUsize>,
+    /// The value of `played` during the previous check()
+    played_at_check: usize,
+    /// Set while the mix is not played on the preferred device
+    preferred_watcher: Option<PreferredDeviceWatcher>,
+    // if the stream is dropped, the mix is no longer played
+    stream: Option<OutputStream>,
+}
+
+impl SystemAudioOutput {
+    pub fn new(audio_config: &AudioConfig) -> Self {
+        Self {
+            device: audio_config.device.clone(),
+            fallback_to_default: audio_config.fallback_to_default,
+            preferred: None,
+            playing_on: None,
+            played: Arc::new(AtomicUsize::new(0)),
+            played_at_check: 0,
+            preferred_watcher: None,
+            stream: None,
+        }
+    }
+
+    /// Returns true if the mix is not played on the preferred device
+    fn is_on_fallback(&self) -> bool {
+        match (&self.preferred, &self.playing_on) {
+            (Some(preferred), Some(playing_on)) => !name_matches(preferred, playing_on),
+            _ => false,
+        }
+    }
+
+    /// Opens the preferred device, or the default device if the preferred device is not available.
+    /// When the mix is played for the first time, a `device` that is not available is an error
+    /// unless `fallback_to_default` is set.
+    fn open(&mut self) -> Result<(OutputStream, OutputStreamHandle), SoundThreadError> {
+        let starting = self.preferred.is_none();
+
+        if let Some(pattern) = self.preferred.clone().or_else(|| self.device.clone()) {
+            if let Some(device) = find_output_device(&pattern)? {
+                let name = device.name().unwrap_or_default();
+                let stream = OutputStream::try_from_device(&device)?;
+                println!("Playing on the audio output device \"{}\"", name);
+                self.preferred = Some(pattern);
+                self.playing_on = Some(name);
+                return Ok(stream);
+            }
+
+            if starting && !self.fallback_to_default {
+                let available: Vec<String> = output_devices()?.into_iter().map(|(name, _)| name).collect();
+                return Err(SoundThreadError::AudioDeviceNotFound {
+                    pattern,
+                    available: available.join(", "),
+                });
+            }
+
+            eprintln!("No audio output device matches \"{}\", playing on the default device instead", pattern);
+        }
+
+        let device = cpal::default_host().default_output_device().ok_or(StreamError::NoDevice)?;
+        let name = device.name().unwrap_or_default();
+        let stream = OutputStream::try_from_device(&device)?;
+        println!("Playing on the default audio output device \"{}\"", name);
+        if self.preferred.is_none() {
+            self.preferred = Some(self.device.clone().unwrap_or_else(|| name.clone()));
+        }
+        self.playing_on = Some(name);
+        Ok(stream)
+    }
+}
+
+impl AudioOutput for SystemAudioOutput {
+    fn play(&mut self, source: MixSource) -> Result<(), SoundThreadError> {
+        // close the previous device first, some devices can only be opened once
+        self.stream = None;
+        self.playing_on = None;
+        self.preferred_watcher = None;
+
+        let (stream, stream_handle) = self.open()?;
+        stream_handle.play_raw(CountedSource { source, count: Arc::clone(&self.played) })?;
+        self.stream = Some(stream);
+
+        if self.is_on_fallback() {
+            let preferred = self.preferred.clone().unwrap_or_default();
+            self.preferred_watcher = Some(PreferredDeviceWatcher::new(preferred));
+        }
+        Ok(())
+    }
+
+    fn check(&mut self) -> AudioOutputStatus {
+        // rodio does not report errors of the stream, but a stream that has stopped playing no
+        // longer takes samples from the mix
+        let played = self.played.load(Ordering::Relaxed);
+        let stalled = played == self.played_at_check;
+        self.played_at_check = played;
+
+        if self.stream.is_none() || stalled {
+            return AudioOutputStatus::Lost;
+        }
+
+        if self.preferred_watcher.as_ref().is_some_and(PreferredDeviceWatcher::is_available) {
+            return AudioOutputStatus::PreferredDeviceAvailable;
+        }
+
+        AudioOutputStatus::Playing
+    }
+
+    fn device_name(&self) -> Option<&str> {
+        self.playing_on.as_deref()
+    }
+}
+
+/// Consumes a MixSource on a separate thread, at the same pace as a sound device would, so that
+/// samples finish playing at the expected time
+struct RealTimeConsumer {
+    stopped: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl RealTimeConsumer {
+    fn new(mut source: MixSource, mut consume: impl FnMut(f32) + Send + 'static) -> Self {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = Arc::clone(&stopped);
+
+        let handle = thread::spawn(move || {
+            let started_at = Instant::now();
+            let mut consumed: u64 = 0;
+
+            while !thread_stopped.load(Ordering::Relaxed) {
+                let frames = started_at.elapsed().as_micros() * u128::from(MIX_SAMPLE_RATE) / 1_000_000;
+                let samples = frames as u64 * u64::from(MIX_CHANNELS);
+                for sample in source.by_ref().take((samples - consumed) as usize) {
+                    consume(sample);
+                }
+                consumed = samples;
+                thread::sleep(CONSUME_INTERVAL);
+            }
+        });
+
+        Self { stopped, handle: Some(handle) }
+    }
+}
+
+impl Drop for RealTimeConsumer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                eprintln!("The thread which consumes the mix has panicked");
+            }
+        }
+    }
+}
+
+/// Discards the mix, for when no sound should be played at all (for example on a computer without
+/// a sound card)
+#[derive(Default)]
+pub struct NullAudioOutput {
+    consumer: Option<RealTimeConsumer>,
+}
+
+impl NullAudioOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AudioOutput for NullAudioOutput {
+    fn play(&mut self, source: MixSource) -> Result<(), SoundThreadError> {
+        self.consumer = Some(RealTimeConsumer::new(source, |_| {}));
+        Ok(())
+    }
+}
+
+/// Keeps everything that is played in memory, with MIX_CHANNELS interleaved channels at
+/// MIX_SAMPLE_RATE. A clone refers to the same samples, so that a test can inspect what the
+/// SoundThread has played.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct CaptureAudioOutput {
+    samples: Arc<Mutex<Vec<f32>>>,
+    consumer: Option<Arc<RealTimeConsumer>>,
+}
+
+#[cfg(test)]
+impl CaptureAudioOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The samples that have been played so far
+    pub fn samples(&self) -> Vec<f32> {
+        self.samples.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl AudioOutput for CaptureAudioOutput {
+    fn play(&mut self, source: MixSource) -> Result<(), SoundThreadError> {
+        let samples = Arc::clone(&self.samples);
+        let consumer = RealTimeConsumer::new(source, move |sample| samples.lock().unwrap().push(sample));
+        self.consumer = Some(Arc::new(consumer));
+        Ok(())
+    }
+}
+
+/// Does not play the mix by itself, the samples are taken using render() instead. Time does not
+/// pass on its own, which makes it possible to render faster than real time. A clone refers to the
+/// same mix.
+#[derive(Clone, Default)]
+pub struct OfflineAudioOutput {
+    source: Arc<Mutex<Option<MixSource>>>,
+}
+
+impl OfflineAudioOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the next `sample_count` samples of the mix. Nothing is returned if play() has not
+    /// been called yet.
+    pub fn render(&self, sample_count: usize) -> Vec<f32> {
+        match self.source.lock().unwrap().as_mut() {
+            Some(source) => source.by_ref().take(sample_count).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl AudioOutput for OfflineAudioOutput {
+    fn play(&mut self, source: MixSource) -> Result<(), SoundThreadError> {
+        *self.source.lock().unwrap() = Some(source);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+    use rodio::buffer::SamplesBuffer;
+    use crate::audio_output::{AudioOutput, CaptureAudioOutput, OfflineAudioOutput};
+    use crate::mixer::{mixer, MIX_CHANNELS, MIX_SAMPLE_RATE};
+    use pretty_assertions::{assert_eq};
+
+    #[test]
+    fn offline_output_renders_on_request() {
+        let (controller, source, _) = mixer();
+        let output = OfflineAudioOutput::new();
+        assert_eq!(output.render(2), vec![]);
+
+        output.clone().play(source).unwrap();
+        controller.add(SamplesBuffer::new(MIX_CHANNELS, MIX_SAMPLE_RATE, vec![0.5f32, -0.5]));
+        assert_eq!(output.render(4), vec![0.5, -0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn capture_output_keeps_what_has_been_played() {
+        let (controller, source, _) = mixer();
+        controller.add(SamplesBuffer::new(MIX_CHANNELS, MIX_SAMPLE_RATE, vec![0.25f32, 0.75]));
+
+        let capture = CaptureAudioOutput::new();
+        let mut output = capture.clone();
+        output.play(source).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        drop(output);
+
+        let samples = capture.samples();
+        assert!(samples.len() >= 4);
+        assert_eq!(&samples[..4], &[0.25, 0.75, 0.0, 0.0]);
+    }
+}
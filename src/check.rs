@@ -0,0 +1,192 @@
+use crate::config::Config;
+use crate::error::{ConfigError, LocatedConfigWarning, SampleLoadError};
+use crate::sample_loader::SampleLoader;
+use std::path::{Path, PathBuf};
+
+/// The config and all of its samples are valid
+pub const EXIT_OK: i32 = 0;
+/// The config file could not be read or is not valid
+pub const EXIT_CONFIG_INVALID: i32 = 1;
+/// The config is valid, but one or more samples could not be read or decoded
+pub const EXIT_SAMPLES_INVALID: i32 = 2;
+
+/// The result of attempting to load and decode a single sample
+pub struct SampleCheck {
+    pub bank_id: String,
+    pub sample_id: String,
+    pub file: PathBuf,
+    pub error: Option<SampleLoadError>,
+}
+
+/// The result of validating a config, without opening any audio device or window.
+pub struct CheckReport {
+    pub config_error: Option<ConfigError>,
+    pub warnings: Vec<LocatedConfigWarning>,
+    pub bank_count: usize,
+    pub switch_count: usize,
+    pub samples: Vec<SampleCheck>,
+}
+
+impl CheckReport {
+    fn from_config_error(config_error: ConfigError) -> Self {
+        CheckReport {
+            config_error: Some(config_error),
+            warnings: Vec::new(),
+            bank_count: 0,
+            switch_count: 0,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn failed_sample_count(&self) -> usize {
+        self.samples.iter().filter(|sample| sample.error.is_some()).count()
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        if self.config_error.is_some() {
+            EXIT_CONFIG_INVALID
+        }
+        else if self.failed_sample_count() > 0 {
+            EXIT_SAMPLES_INVALID
+        }
+        else {
+            EXIT_OK
+        }
+    }
+
+    pub fn print(&self) {
+        if let Some(config_error) = &self.config_error {
+            println!("The configuration is not valid: {}", config_error);
+            return;
+        }
+
+        println!("Found {} banks and {} switches", self.bank_count, self.switch_count);
+
+        for warning in &self.warnings {
+            println!("Warning: {}", warning);
+        }
+
+        for sample in &self.samples {
+            let status = match &sample.error {
+                None => String::from("OK"),
+                Some(err) => format!("FAILED: {}", err),
+            };
+            println!("  bank \"{}\", sample \"{}\" ({}): {}", sample.bank_id, sample.sample_id, sample.file.to_str().unwrap(), status);
+        }
+
+        let failed = self.failed_sample_count();
+        if failed > 0 {
+            println!("{} of {} samples could not be loaded", failed, self.samples.len());
+        }
+        else {
+            println!("The configuration is valid, all {} samples have been loaded", self.samples.len());
+        }
+    }
+}
+
+/// Attempt to load and decode every sample in the config. Unlike SampleLoader::load_banks(), this
+/// does not stop at the first sample that fails.
+pub fn check_config(config: &Config, sample_loader: &mut dyn SampleLoader) -> CheckReport {
+    let mut samples = Vec::new();
+
+    for bank_config in &config.banks {
+        for sample_config in &bank_config.samples {
+            let error = sample_loader.load_sample(sample_config).err();
+
+            samples.push(SampleCheck {
+                bank_id: bank_config.id.clone(),
+                sample_id: sample_config.id.clone(),
+                file: sample_config.file.clone(),
+                error,
+            });
+        }
+    }
+
+    CheckReport {
+        config_error: None,
+        warnings: config.warnings.clone(),
+        bank_count: config.banks.len(),
+        switch_count: config.switches.len(),
+        samples,
+    }
+}
+
+pub fn check_config_file(config_path: &Path, sample_loader: &mut dyn SampleLoader) -> CheckReport {
+    match Config::from_file(config_path) {
+        Ok(config) => check_config(&config, sample_loader),
+        Err(err) => CheckReport::from_config_error(err),
+    }
+}
+
+pub fn check_config_string(yaml_string: &str, sample_loader: &mut dyn SampleLoader) -> CheckReport {
+    match Config::from_string(yaml_string, None) {
+        Ok(config) => check_config(&config, sample_loader),
+        Err(err) => CheckReport::from_config_error(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::check::{check_config_file, EXIT_CONFIG_INVALID, EXIT_OK, EXIT_SAMPLES_INVALID};
+    use crate::error::SampleLoadError;
+    use crate::sample_loader::DiskSampleLoader;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn example_config_path() -> PathBuf {
+        [env!("CARGO_MANIFEST_DIR"), "example", "config.yaml"].iter().collect()
+    }
+
+    #[test]
+    fn check_example_config() {
+        let report = check_config_file(&example_config_path(), &mut DiskSampleLoader::new());
+        assert!(report.config_error.is_none());
+        assert_eq!(report.samples.len(), 12);
+        assert_eq!(report.failed_sample_count(), 0);
+        assert_eq!(report.exit_code(), EXIT_OK);
+    }
+
+    #[test]
+    fn check_missing_config_file() {
+        let config_path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "example", "does-not-exist.yaml"].iter().collect();
+        let report = check_config_file(&config_path, &mut DiskSampleLoader::new());
+        assert!(report.config_error.is_some());
+        assert_eq!(report.exit_code(), EXIT_CONFIG_INVALID);
+    }
+
+    #[test]
+    fn check_config_with_missing_samples() {
+        let config_source = r###"
+banks:
+  - id: bankA
+    samples:
+      - id: missing1
+        file: missing1.flac
+      - id: present
+        file: F#3.flac
+      - id: missing2
+        file: missing2.flac
+switches: []
+"###;
+        let mut config_path = std::env::temp_dir();
+        config_path.push(format!("microswitch-check-test-{}", std::process::id()));
+        fs::create_dir_all(&config_path).unwrap();
+        fs::copy(
+            [env!("CARGO_MANIFEST_DIR"), "example", "samples", "F#3.flac"].iter().collect::<PathBuf>(),
+            config_path.join("F#3.flac"),
+        ).unwrap();
+        config_path.push("config.yaml");
+        fs::write(&config_path, config_source).unwrap();
+
+        let report = check_config_file(&config_path, &mut DiskSampleLoader::new());
+        fs::remove_dir_all(config_path.parent().unwrap()).unwrap();
+
+        // every sample should have been attempted, not just the ones before the first failure
+        assert_eq!(report.samples.len(), 3);
+        assert_eq!(report.failed_sample_count(), 2);
+        assert!(matches!(report.samples[0].error, Some(SampleLoadError::IO { .. })));
+        assert!(report.samples[1].error.is_none());
+        assert!(matches!(report.samples[2].error, Some(SampleLoadError::IO { .. })));
+        assert_eq!(report.exit_code(), EXIT_SAMPLES_INVALID);
+    }
+}
@@ -1,10 +1,17 @@
-use crate::error::ConfigError;
-use gilrs::Button;
-use iced::keyboard::KeyCode;
+use crate::gesture::{Gesture, GestureTiming};
+use crate::input_filter::InputFilterSettings;
+use crate::error::{ConfigError, ConfigWarning, LocatedConfigError, LocatedConfigWarning};
+use crate::name_match::name_matches;
+use crate::yaml_location::{Location, YamlLocations};
+use gilrs::{Axis, Button};
+use iced::keyboard::{KeyCode, Modifiers};
+use iced::mouse;
 use serde::{Deserialize};
 use std::collections::HashMap;
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use std::{fs};
 
 const KEYBOARD_BUTTON_MAPPING: [(&str, KeyCode); 136] = [
@@ -168,6 +175,36 @@ const GAMEPAD_BUTTON_MAPPING: [(&str, Button); 19] = [
     ("DPadRight", Button::DPadRight),
 ];
 
+// The back and forward buttons use the X11 button numbers, see normalize_mouse_button()
+const MOUSE_BUTTON_MAPPING: [(&str, mouse::Button); 5] = [
+    ("Left", mouse::Button::Left),
+    ("Right", mouse::Button::Right),
+    ("Middle", mouse::Button::Middle),
+    ("Back", mouse::Button::Other(8)),
+    ("Forward", mouse::Button::Other(9)),
+];
+
+/// The back and forward buttons are reported with different numbers depending on the platform,
+/// these are translated to the numbers used in MOUSE_BUTTON_MAPPING
+fn normalize_mouse_button(button: mouse::Button) -> mouse::Button {
+    match button {
+        #[cfg(target_os = "windows")]
+        mouse::Button::Other(1) => mouse::Button::Other(8),
+        #[cfg(target_os = "windows")]
+        mouse::Button::Other(2) => mouse::Button::Other(9),
+        #[cfg(target_os = "macos")]
+        mouse::Button::Other(3) => mouse::Button::Other(8),
+        #[cfg(target_os = "macos")]
+        mouse::Button::Other(4) => mouse::Button::Other(9),
+        // Wayland reports the evdev codes BTN_SIDE and BTN_EXTRA
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        mouse::Button::Other(0x113) => mouse::Button::Other(8),
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        mouse::Button::Other(0x114) => mouse::Button::Other(9),
+        button => button,
+    }
+}
+
 fn make_keyboard_button_map() -> HashMap<&'static str, KeyCode> {
     KEYBOARD_BUTTON_MAPPING.iter().cloned().collect()
 }
@@ -188,6 +225,104 @@ fn make_valid_gamepad_button_vec() -> Vec<&'static str>{
         .collect()
 }
 
+const GAMEPAD_AXIS_MAPPING: [(&str, Axis); 8] = [
+    ("LeftStickX", Axis::LeftStickX),
+    ("LeftStickY", Axis::LeftStickY),
+    ("LeftZ", Axis::LeftZ),
+    ("RightStickX", Axis::RightStickX),
+    ("RightStickY", Axis::RightStickY),
+    ("RightZ", Axis::RightZ),
+    ("DPadX", Axis::DPadX),
+    ("DPadY", Axis::DPadY),
+];
+
+fn make_gamepad_axis_map() -> HashMap<&'static str, Axis> {
+    GAMEPAD_AXIS_MAPPING.iter().cloned().collect()
+}
+
+fn make_valid_gamepad_axis_vec() -> Vec<&'static str>{
+    GAMEPAD_AXIS_MAPPING.iter()
+        .map(|x| x.0)
+        .collect()
+}
+
+const KEYBOARD_MODIFIER_MAPPING: [(&str, Modifiers); 4] = [
+    ("Ctrl", Modifiers::CTRL),
+    ("Shift", Modifiers::SHIFT),
+    ("Alt", Modifiers::ALT),
+    ("Logo", Modifiers::LOGO),
+];
+
+fn make_valid_keyboard_modifier_vec() -> Vec<&'static str>{
+    KEYBOARD_MODIFIER_MAPPING.iter()
+        .map(|x| x.0)
+        .collect()
+}
+
+/// The modifier that is active while the given key is held down, if it is a modifier key
+fn modifier_of_key(key_code: KeyCode) -> Modifiers {
+    match key_code {
+        KeyCode::LControl | KeyCode::RControl => Modifiers::CTRL,
+        KeyCode::LShift | KeyCode::RShift => Modifiers::SHIFT,
+        KeyCode::LAlt | KeyCode::RAlt => Modifiers::ALT,
+        KeyCode::LWin | KeyCode::RWin => Modifiers::LOGO,
+        _ => Modifiers::empty(),
+    }
+}
+
+/// A keyboard key, which only matches if exactly these modifiers are held down
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub key_code: KeyCode,
+    pub modifiers: Modifiers,
+}
+
+impl KeyBinding {
+    /// Parse a key such as "A" or "Ctrl+Shift+A"
+    fn parse(key: &str, mapping: &HashMap<&'static str, KeyCode>) -> Result<KeyBinding, ConfigError> {
+        let mut parts: Vec<&str> = key.split('+').map(str::trim).collect();
+        let key_name = parts.pop().unwrap_or_default();
+        let mut modifiers = Modifiers::empty();
+
+        for modifier_name in parts {
+            match KEYBOARD_MODIFIER_MAPPING.iter().find(|x| x.0 == modifier_name) {
+                Some((_, modifier)) => modifiers |= *modifier,
+                None => return Err(ConfigError::UnknownKeyboardModifier {
+                    modifier: modifier_name.to_string(),
+                    allowed_values: make_valid_keyboard_modifier_vec().join(", "),
+                }),
+            }
+        }
+
+        match mapping.get(key_name) {
+            Some(key_code) => Ok(KeyBinding { key_code: *key_code, modifiers }),
+            None => Err(ConfigError::UnknownKeyboardButton {
+                button: key_name.to_string(),
+                allowed_values: make_valid_keyboard_button_vec().join(", "),
+            }),
+        }
+    }
+
+    /// The binding that matches a key press reported by iced. Pressing a modifier key (e.g.
+    /// LShift) also activates that modifier, which is not part of the binding.
+    pub fn from_key_press(key_code: KeyCode, modifiers: Modifiers) -> KeyBinding {
+        KeyBinding {
+            key_code,
+            modifiers: modifiers - modifier_of_key(key_code),
+        }
+    }
+}
+
+fn make_mouse_button_map() -> HashMap<&'static str, mouse::Button> {
+    MOUSE_BUTTON_MAPPING.iter().cloned().collect()
+}
+
+fn make_valid_mouse_button_vec() -> Vec<&'static str>{
+    MOUSE_BUTTON_MAPPING.iter()
+        .map(|x| x.0)
+        .collect()
+}
+
 // SampleId and BankId are used during config parsing, they are a human readable reference to
 // specific banks/samples. They are translated to BankRef and SampleRef, which enable config structs
 // to quickly look up other config structs by index.
@@ -215,17 +350,82 @@ pub struct SwitchRef {
     pub switch_index: usize,
 }
 
+/// A volume as a linear factor (`0.5`), or in decibels (`"-6dB"`)
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum VolumeConfig {
+    Linear(f32),
+    Decibels(String),
+}
+
+impl VolumeConfig {
+    /// Returns the linear factor, or None if the volume is not valid
+    pub fn linear(&self) -> Option<f32> {
+        let linear = match self {
+            VolumeConfig::Linear(linear) => *linear,
+            VolumeConfig::Decibels(text) => {
+                let text = text.trim();
+                let decibels = text.strip_suffix("dB").or_else(|| text.strip_suffix("db"))?;
+                let decibels: f32 = decibels.trim().parse().ok()?;
+                10f32.powf(decibels / 20.0)
+            },
+        };
+
+        (linear.is_finite() && linear >= 0.0).then_some(linear)
+    }
+}
+
+impl fmt::Display for VolumeConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VolumeConfig::Linear(linear) => write!(f, "{}", linear),
+            VolumeConfig::Decibels(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// The volume and pan that a sample is played with
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Gain {
+    /// A linear factor
+    pub volume: f32,
+    /// From -1.0 (left) to 1.0 (right)
+    pub pan: f32,
+}
+
+impl Default for Gain {
+    fn default() -> Self {
+        Gain { volume: 1.0, pan: 0.0 }
+    }
+}
+
+/// The volume and pan of everything that is played
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MasterConfig {
+    pub volume: Option<VolumeConfig>,
+    /// From -1.0 (left) to 1.0 (right)
+    pub pan: Option<f32>,
+}
+
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct SampleConfig {
     pub id: SampleId,
     pub file: PathBuf,
+    pub volume: Option<VolumeConfig>,
+    /// From -1.0 (left) to 1.0 (right)
+    pub pan: Option<f32>,
 
     // Cached //
 
     #[serde(skip)]
     pub bank_sample_ref: BankSampleRef,
 
+    /// The combination of the volume and pan of this sample, its bank and `master`
+    #[serde(skip)]
+    pub gain: Gain,
+
     /// None if the config was embedded, Some if the config is from disk
     #[serde(skip)]
     pub file_resolved: Option<PathBuf>,
@@ -238,6 +438,10 @@ pub struct BankConfig {
     /// If true, do not mute other samples when playing a sample in this bank
     #[serde(default)]
     pub poly: bool,
+    /// Applies to every sample in this bank, along with the volume of the sample itself
+    pub volume: Option<VolumeConfig>,
+    /// From -1.0 (left) to 1.0 (right), added to the pan of each sample
+    pub pan: Option<f32>,
     #[serde(default)]
     pub samples: Vec<SampleConfig>,
 
@@ -257,8 +461,19 @@ impl BankConfig {
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
 pub struct Gamepad {
+    /// Select a device by the id assigned when it was connected. This id changes when devices are
+    /// reconnected in a different order.
     pub device_id: Option<usize>,
 
+    /// Select devices by their SDL compatible UUID
+    pub uuid: Option<String>,
+
+    /// Select devices by name, `*` matches any sequence of characters
+    pub name: Option<String>,
+
+    /// Select only the n-th (starting at 0) connected device which matches `name`
+    pub index: Option<usize>,
+
     /// Must match an identifier present in GAMEPAD_BUTTON_MAPPING
     pub button: String,
 
@@ -266,6 +481,256 @@ pub struct Gamepad {
 
     #[serde(skip)]
     pub gilrs_button: Button,
+
+    /// same as `device_id`, `uuid`, `name` and `index` combined
+    #[serde(skip)]
+    pub selector: GamepadSelector,
+}
+
+/// Determines which gamepad devices a binding applies to. Except for `Any`, these are matched
+/// against the connected devices by the GamepadThread.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum GamepadSelector {
+    #[default]
+    Any,
+    DeviceId(usize),
+    /// Lowercase hexadecimal without dashes
+    Uuid(String),
+    Name { pattern: String, index: Option<usize> },
+}
+
+impl fmt::Display for GamepadSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GamepadSelector::Any => write!(f, "any device"),
+            GamepadSelector::DeviceId(device_id) => write!(f, "device {}", device_id),
+            GamepadSelector::Uuid(uuid) => write!(f, "device {}", uuid),
+            GamepadSelector::Name { pattern, index: None } => write!(f, "devices named \"{}\"", pattern),
+            GamepadSelector::Name { pattern, index: Some(index) } => write!(f, "device #{} named \"{}\"", index, pattern),
+        }
+    }
+}
+
+/// Combine the fields that select a gamepad device into a GamepadSelector. On error, the path of
+/// the offending field (relative to the gamepad) is returned along with the error.
+fn resolve_gamepad_selector(
+    device_id: Option<usize>,
+    uuid: &Option<String>,
+    name: &Option<String>,
+    index: Option<usize>,
+) -> Result<GamepadSelector, (&'static str, ConfigError)> {
+    let selector_count = [device_id.is_some(), uuid.is_some(), name.is_some() || index.is_some()]
+        .iter()
+        .filter(|is_set| **is_set)
+        .count();
+
+    if selector_count > 1 {
+        return Err(("", ConfigError::AmbiguousGamepadSelector));
+    }
+
+    if let Some(device_id) = device_id {
+        return Ok(GamepadSelector::DeviceId(device_id));
+    }
+
+    if let Some(uuid) = uuid {
+        let normalized: String = uuid.chars().filter(|c| *c != '-').collect::<String>().to_lowercase();
+        if normalized.len() != 32 || !normalized.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err((".uuid", ConfigError::InvalidGamepadUuid { uuid: uuid.clone() }));
+        }
+        return Ok(GamepadSelector::Uuid(normalized));
+    }
+
+    if name.is_some() || index.is_some() {
+        return Ok(GamepadSelector::Name {
+            pattern: name.clone().unwrap_or_else(|| String::from("*")),
+            index,
+        });
+    }
+
+    Ok(GamepadSelector::Any)
+}
+
+impl Gamepad {
+    fn resolve_selector(&self) -> Result<GamepadSelector, (&'static str, ConfigError)> {
+        resolve_gamepad_selector(self.device_id, &self.uuid, &self.name, self.index)
+    }
+}
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum GamepadAxisDirection {
+    Positive,
+    Negative,
+}
+
+/// Treat an analog gamepad axis as a button. The axis is pressed once its value crosses the
+/// threshold, and released once it returns below `threshold - hysteresis`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct GamepadAxis {
+    /// Select a device, see `Gamepad`
+    pub device_id: Option<usize>,
+    pub uuid: Option<String>,
+    pub name: Option<String>,
+    pub index: Option<usize>,
+
+    /// Must match an identifier present in GAMEPAD_AXIS_MAPPING
+    pub axis: String,
+
+    /// Between 0 and 1
+    #[serde(default = "default_axis_threshold")]
+    pub threshold: f32,
+
+    #[serde(default = "default_axis_direction")]
+    pub direction: GamepadAxisDirection,
+
+    /// Prevents a noisy axis from repeatedly triggering the switch while it hovers around the
+    /// threshold
+    #[serde(default = "default_axis_hysteresis")]
+    pub hysteresis: f32,
+
+    // Cached //
+
+    #[serde(skip, default = "default_gilrs_axis")]
+    pub gilrs_axis: Axis,
+
+    #[serde(skip)]
+    pub selector: GamepadSelector,
+}
+
+fn default_axis_threshold() -> f32 { 0.5 }
+fn default_axis_direction() -> GamepadAxisDirection { GamepadAxisDirection::Positive }
+fn default_axis_hysteresis() -> f32 { 0.1 }
+fn default_gilrs_axis() -> Axis { Axis::Unknown }
+
+impl GamepadAxis {
+    fn resolve_selector(&self) -> Result<GamepadSelector, (&'static str, ConfigError)> {
+        resolve_gamepad_selector(self.device_id, &self.uuid, &self.name, self.index)
+    }
+
+    /// Determine whether the axis should be considered as held down, given its new value
+    pub fn is_held(&self, was_held: bool, value: f32) -> bool {
+        let value = match self.direction {
+            GamepadAxisDirection::Positive => value,
+            GamepadAxisDirection::Negative => -value,
+        };
+
+        if was_held {
+            value > self.threshold - self.hysteresis
+        } else {
+            value >= self.threshold
+        }
+    }
+}
+
+/// A note or a controller of a MIDI device
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MidiControl {
+    Note(u8),
+    Cc(u8),
+}
+
+/// Trigger a switch using a MIDI note or control change (CC) message
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct MidiTrigger {
+    /// The name of the input port, in which `*` matches anything. Any port if not set.
+    pub port: Option<String>,
+    /// Between 1 and 16, any channel if not set
+    pub channel: Option<u8>,
+    /// Pressed by a note on message, and released by a note off message
+    pub note: Option<u8>,
+    /// Pressed once the value of this controller reaches `threshold`, and released when it drops
+    /// below it
+    pub cc: Option<u8>,
+    #[serde(default = "default_midi_cc_threshold")]
+    pub threshold: u8,
+
+    // Cached //
+
+    #[serde(skip, default = "default_midi_control")]
+    pub control: MidiControl,
+}
+
+fn default_midi_cc_threshold() -> u8 { 64 }
+fn default_midi_control() -> MidiControl { MidiControl::Note(0) }
+
+fn resolve_midi_control(note: Option<u8>, cc: Option<u8>) -> Result<MidiControl, (&'static str, ConfigError)> {
+    let control = match (note, cc) {
+        (Some(note), None) => MidiControl::Note(note),
+        (None, Some(cc)) => MidiControl::Cc(cc),
+        _ => return Err(("", ConfigError::AmbiguousMidiControl)),
+    };
+
+    match control {
+        MidiControl::Note(number) if number > 127 => Err((".note", ConfigError::InvalidMidiNumber { number })),
+        MidiControl::Cc(number) if number > 127 => Err((".cc", ConfigError::InvalidMidiNumber { number })),
+        _ => Ok(control),
+    }
+}
+
+impl MidiTrigger {
+    fn resolve_control(&self) -> Result<MidiControl, (&'static str, ConfigError)> {
+        resolve_midi_control(self.note, self.cc)
+    }
+
+    /// Determine whether the trigger should be considered as held down, given the velocity of a
+    /// note message or the value of a control change message
+    pub fn is_held(&self, value: u8) -> bool {
+        match self.control {
+            MidiControl::Note(_) => value > 0,
+            MidiControl::Cc(_) => value >= self.threshold,
+        }
+    }
+}
+
+/// Send a MIDI note or control change (CC) message, for example to a synthesizer
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct MidiOut {
+    /// The name of the output port, in which `*` matches anything. The first port if not set.
+    pub port: Option<String>,
+    /// Between 1 and 16
+    #[serde(default = "default_midi_out_channel")]
+    pub channel: u8,
+    /// A note on message is sent right away, the note off message when the switch is released
+    /// or after `durationMs`
+    pub note: Option<u8>,
+    #[serde(default = "default_midi_out_velocity")]
+    pub velocity: u8,
+    /// Stop the note after this many milliseconds, instead of when the switch is released
+    pub duration_ms: Option<u64>,
+    /// Send a single control change message for this controller
+    pub cc: Option<u8>,
+    #[serde(default = "default_midi_out_value")]
+    pub value: u8,
+
+    // Cached //
+
+    #[serde(skip, default = "default_midi_control")]
+    pub control: MidiControl,
+}
+
+fn default_midi_out_channel() -> u8 { 1 }
+fn default_midi_out_velocity() -> u8 { 100 }
+fn default_midi_out_value() -> u8 { 127 }
+
+/// Identifies one of the MIDI triggers of a switch
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MidiTriggerRef {
+    pub switch_ref: SwitchRef,
+    /// index in SwitchConfig::midi_fields()
+    pub trigger_index: usize,
+}
+
+/// Identifies one of the gamepad axis triggers of a switch
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct GamepadAxisRef {
+    pub switch_ref: SwitchRef,
+    /// index in SwitchConfig::gamepad_axis_fields()
+    pub axis_index: usize,
 }
 
 #[derive(Deserialize, Debug, Clone, PartialEq)]
@@ -313,6 +778,55 @@ pub struct SwitchPlayStep {
     pub bank_ref: BankRef,
 }
 
+/// Actions performed when a switch is released, see `SwitchConfig::on_release`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct SwitchActions {
+    #[serde(default)]
+    pub stop_sounds: bool,
+    pub play: Option<SwitchPlay>,
+    pub play_random: Option<SwitchPlayRandom>,
+    pub play_step: Option<SwitchPlayStep>,
+    pub midi_out: Option<MidiOut>,
+    pub scan: Option<ScanAction>,
+}
+
+impl SwitchActions {
+    pub fn view(&self) -> SwitchActionsView<'_> {
+        SwitchActionsView {
+            stop_sounds: self.stop_sounds,
+            play: self.play.as_ref(),
+            play_random: self.play_random.as_ref(),
+            play_step: self.play_step.as_ref(),
+            midi_out: self.midi_out.as_ref(),
+            scan: self.scan,
+        }
+    }
+}
+
+/// Borrowed form of the actions of a switch, either those performed on press (specified directly
+/// on the SwitchConfig) or on release (SwitchConfig::on_release)
+#[derive(Debug, Copy, Clone)]
+pub struct SwitchActionsView<'a> {
+    pub stop_sounds: bool,
+    pub play: Option<&'a SwitchPlay>,
+    pub play_random: Option<&'a SwitchPlayRandom>,
+    pub play_step: Option<&'a SwitchPlayStep>,
+    pub midi_out: Option<&'a MidiOut>,
+    pub scan: Option<ScanAction>,
+}
+
+/// Controls the scanning highlight in the window, see `Config::scanning`
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ScanAction {
+    /// Activate the highlighted button, or the highlighted row when scanning rows
+    Select,
+    /// Move the highlight to the next button or row
+    Step,
+}
+
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
@@ -321,10 +835,35 @@ pub struct SwitchConfig {
 
     /// the title in the gui
     pub title: String,
-    /// trigger based on a keyboard key
+    /// trigger based on a keyboard key, optionally combined with modifiers (e.g. "Ctrl+Shift+A")
     pub key: Option<String>,
+    /// trigger based on any of these keyboard keys (in addition to `key`)
+    #[serde(default)]
+    pub keys: Vec<String>,
     /// trigger based on a gamepad button
     pub gamepad: Option<Gamepad>,
+    /// trigger based on any of these gamepad buttons (in addition to `gamepad`)
+    #[serde(default)]
+    pub gamepads: Vec<Gamepad>,
+    /// trigger based on an analog gamepad axis crossing a threshold
+    pub gamepad_axis: Option<GamepadAxis>,
+    /// trigger based on any of these gamepad axes (in addition to `gamepadAxis`)
+    #[serde(default)]
+    pub gamepad_axes: Vec<GamepadAxis>,
+    /// trigger based on a mouse button, must match an identifier present in MOUSE_BUTTON_MAPPING
+    pub mouse: Option<String>,
+    /// trigger based on any of these mouse buttons (in addition to `mouse`)
+    #[serde(default)]
+    pub mouse_buttons: Vec<String>,
+    /// trigger based on a MIDI note or controller
+    pub midi: Option<MidiTrigger>,
+    /// trigger based on any of these MIDI notes or controllers (in addition to `midi`)
+    #[serde(default)]
+    pub midi_messages: Vec<MidiTrigger>,
+    /// If true, other switches with this option may use the same keyboard key, gamepad button or
+    /// mouse button. All of these switches are then triggered together.
+    #[serde(default)]
+    pub shared_binding: bool,
 
     // Actions //
 
@@ -341,127 +880,610 @@ pub struct SwitchConfig {
     // Play a sample, relative in position to the sample previously played in a bank
     pub play_step: Option<SwitchPlayStep>,
 
-    // Cached //
-
-    #[serde(skip)]
-    pub switch_ref: SwitchRef,
+    /// Send a MIDI message
+    pub midi_out: Option<MidiOut>,
 
-    /// same as `key` but translated to a KeyCode
-    #[serde(skip)]
-    pub key_code: Option<KeyCode>,
-}
+    /// Control the scanning highlight in the window
+    pub scan: Option<ScanAction>,
 
-struct ConfigIdLookup {
-    /// bank.id => BankRef
-    bank_id_lookup: HashMap<String, BankRef>,
+    /// Actions to perform when the switch is released
+    pub on_release: Option<SwitchActions>,
 
-    /// sample.id => BankRef
-    sample_id_lookup: HashMap<String, HashMap<String, BankSampleRef>>,
-}
+    /// If true, the samples played by pressing this switch are stopped when it is released
+    #[serde(default)]
+    pub hold_to_play: bool,
 
-impl ConfigIdLookup {
-    fn new(banks: &Vec<BankConfig>) -> Self {
-        let bank_id_lookup = banks
-            .iter()
-            .map(|bank| (bank.id.clone(), bank.bank_ref))
-            .collect();
+    /// The duration of the fade out in milliseconds, when a sample is stopped by `holdToPlay`
+    #[serde(default)]
+    pub fade_out_ms: u64,
 
-        let sample_id_lookup = banks
-            .iter()
-            .map(|bank| {
-                let samples = bank.samples
-                    .iter()
-                    .map(|sample| (sample.id.clone(), sample.bank_sample_ref))
-                    .collect();
+    // Gestures //
 
-                (bank.id.clone(), samples)
-            })
-            .collect();
+    /// Actions to perform when the switch is released before `longPressMs`, and it is not pressed
+    /// again within `doublePressMs`
+    pub short_press: Option<SwitchActions>,
 
-        ConfigIdLookup { bank_id_lookup, sample_id_lookup }
-    }
+    /// Actions to perform when the switch is held down for `longPressMs`
+    pub long_press: Option<SwitchActions>,
 
-    fn bank_id_to_ref(&self, bank_id: &str) -> Result<BankRef, ConfigError> {
-        match self.bank_id_lookup.get(bank_id) {
-            Some(bank_ref) => Ok(*bank_ref),
-            None => Err(ConfigError::UnknownBankId {
-                bank: bank_id.to_string(),
-            }),
-        }
-    }
+    #[serde(default = "default_long_press_ms")]
+    pub long_press_ms: u64,
 
-    fn sample_id_to_ref(&self, bank_id: &str, sample_id: &str) -> Result<BankSampleRef, ConfigError> {
-        let result = self.sample_id_lookup
-            .get(bank_id)
-            .map(|map| map.get(sample_id));
+    /// Actions to perform when the switch is pressed twice within `doublePressMs`
+    pub double_press: Option<SwitchActions>,
 
-        match result {
-            None => Err(ConfigError::UnknownBankId {
-                bank: bank_id.to_string(),
-            }),
-            Some(None) => Err(ConfigError::UnknownSampleId {
-                bank: bank_id.to_string(),
-                sample: sample_id.to_string(),
-            }),
-            Some(Some(bank_sample_ref)) => Ok(*bank_sample_ref),
-        }
-    }
-}
+    #[serde(default = "default_double_press_ms")]
+    pub double_press_ms: u64,
 
-#[derive(Deserialize, Debug, Clone, PartialEq)]
-#[serde(deny_unknown_fields)]
-pub struct Config {
-    pub banks: Vec<BankConfig>,
-    pub switches: Vec<SwitchConfig>,
+    /// Overrides the global `inputFilter` for this switch
+    #[serde(default)]
+    pub input_filter: InputFilterConfig,
 
     // Cached //
 
-    /// The path that all other paths are relative to
-    /// None if the config was embedded, Some if the config is from disk
     #[serde(skip)]
-    pub resolve_path: Option<PathBuf>,
+    pub switch_ref: SwitchRef,
 
+    /// same as `key` and `keys` but translated to a KeyBinding
     #[serde(skip)]
-    // keyboard key code => switch config reference
-    keyboard_key_to_switch_lookup_table: HashMap<KeyCode, SwitchRef>,
+    pub key_bindings: Vec<KeyBinding>,
 
+    /// same as `mouse` and `mouseButtons` but translated to a mouse::Button
     #[serde(skip)]
-    // device id => gamepad button => switch config reference
-    gamepad_button_to_switch_lookup_table: HashMap<Option<usize>, HashMap<Button, SwitchRef>>,
+    pub iced_mouse_buttons: Vec<mouse::Button>,
+}
 
-    #[serde(skip)]
-    // for each switch that has a SwitchConfig.play (SwitchPlay) configuration, map the sample that it specifies to the switch
-    sample_to_switch_play: HashMap<BankSampleRef, Vec<SwitchRef>>,
+/// Filters out unintended presses. Each field that is not set on a switch, is taken from the
+/// global `inputFilter`.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct InputFilterConfig {
+    /// Ignore a press that follows the previous release within this many milliseconds
+    pub debounce_ms: Option<u64>,
+    /// Only accept a press once the switch has been held down for this many milliseconds
+    pub min_hold_ms: Option<u64>,
+    /// Ignore a press that follows the previous accepted press within this many milliseconds
+    pub cooldown_ms: Option<u64>,
+}
 
-    #[serde(skip)]
-    empty_switch_ref_vec: Vec<SwitchRef>, // serde will init this to Default::default() which will be an empty vec
+#[derive(Deserialize, Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ScanMode {
+    /// The highlight moves by itself every `intervalMs`
+    #[default]
+    Auto,
+    /// The highlight only moves by a switch with `scan: step`
+    Step,
 }
 
-impl Config {
-    pub fn from_string(yaml_string: &str, resolve_path: Option<PathBuf>) -> Result<Config, ConfigError> {
-        let mut config: Config = serde_yaml::from_str(yaml_string)?;
-        config.resolve_path = resolve_path;
+/// Lets a single switch (with `scan: select`) activate any button in the window, by highlighting
+/// the buttons one after another
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanningConfig {
+    #[serde(default)]
+    pub mode: ScanMode,
+    /// How long each button or row is highlighted in the `auto` mode
+    #[serde(default = "default_scan_interval_ms")]
+    pub interval_ms: u64,
+    /// The buttons are laid out in this many columns. With more than one column, a row is
+    /// selected first and then a button within that row.
+    #[serde(default = "default_scan_columns")]
+    pub columns: usize,
+}
+
+/// Control μSwitch with Open Sound Control (OSC) messages over UDP, and send an OSC message for
+/// every event, for example to a lighting or video console
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct OscConfig {
+    /// The address to receive messages on, e.g. "127.0.0.1:9000". Nothing is received if not set.
+    pub listen: Option<String>,
+    /// The address to send events to, e.g. "127.0.0.1:9001". Nothing is sent if not set.
+    pub send: Option<String>,
+    /// The start of every OSC address that is sent or received
+    #[serde(default = "default_osc_prefix")]
+    pub prefix: String,
+
+    // Cached //
+
+    /// same as `listen` but parsed
+    #[serde(skip)]
+    pub listen_addr: Option<SocketAddr>,
+
+    /// same as `send` but parsed
+    #[serde(skip)]
+    pub send_addr: Option<SocketAddr>,
+}
+
+/// A web server which lets other devices, such as a tablet, press switches and follow what is
+/// being played
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpConfig {
+    /// The address to listen on. Only this computer can connect to the default address, use
+    /// "0.0.0.0:8080" to accept connections from other devices.
+    #[serde(default = "default_http_listen")]
+    pub listen: String,
+
+    // Cached //
+
+    /// same as `listen` but parsed
+    #[serde(skip, default = "default_http_listen_addr")]
+    pub listen_addr: SocketAddr,
+}
+
+/// Write every switch press and every played sample to a file, so that a practice session can be
+/// listened back to later using `microswitch replay`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionLogConfig {
+    /// A new file is created in this directory each time μSwitch starts
+    pub directory: PathBuf,
+
+    // Cached //
+
+    /// Same as `directory`, but relative to the directory the config file is in. Relative to the
+    /// working directory if the config was embedded.
+    #[serde(skip)]
+    pub directory_resolved: PathBuf,
+}
+
+/// Where the sound is played
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioConfig {
+    #[serde(default)]
+    pub output: AudioOutputKind,
+    /// A pattern that has to match the whole name of the sound output device, in which `*`
+    /// matches anything (e.g. `*USB*`). The default device is used if it is None
+    pub device: Option<String>,
+    /// Use the default device if `device` is not found, instead of refusing to start
+    #[serde(default)]
+    pub fallback_to_default: bool,
+}
+
+#[derive(Deserialize, Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AudioOutputKind {
+    /// The sound output device of the system
+    #[default]
+    System,
+    /// Nothing is played, for example on a computer without a sound card. The switches and other
+    /// outputs (such as MIDI) keep working.
+    #[serde(rename = "none")]
+    Null,
+}
+
+/// Where the recordings of everything that is played are written
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingConfig {
+    /// Relative to the directory the config file is in
+    #[serde(default = "default_recording_directory")]
+    pub directory: PathBuf,
+}
+
+fn default_recording_directory() -> PathBuf { PathBuf::from("recordings") }
+
+fn default_scan_interval_ms() -> u64 { 1000 }
+fn default_scan_columns() -> usize { 1 }
+fn default_ignore_gui_clicks() -> bool { true }
+fn default_osc_prefix() -> String { String::from("/microswitch") }
+fn default_http_listen() -> String { String::from("127.0.0.1:8080") }
+fn default_http_listen_addr() -> SocketAddr { SocketAddr::from(([127, 0, 0, 1], 8080)) }
+
+fn default_long_press_ms() -> u64 { 800 }
+fn default_double_press_ms() -> u64 { 400 }
 
-        config.resolve_refs()?;
+impl SwitchConfig {
+    /// Returns None if no gestures have been configured for this switch
+    pub fn gesture_timing(&self) -> Option<GestureTiming> {
+        if self.short_press.is_none() && self.long_press.is_none() && self.double_press.is_none() {
+            return None;
+        }
+
+        Some(GestureTiming {
+            long_press: self.long_press.as_ref().map(|_| Duration::from_millis(self.long_press_ms)),
+            double_press: self.double_press.as_ref().map(|_| Duration::from_millis(self.double_press_ms)),
+        })
+    }
+
+    /// The actions performed for a gesture detected by GestureDetector
+    pub fn gesture_actions(&self, gesture: Gesture) -> Option<SwitchActionsView<'_>> {
+        let actions = match gesture {
+            Gesture::Short => &self.short_press,
+            Gesture::Long => &self.long_press,
+            Gesture::Double => &self.double_press,
+        };
+        actions.as_ref().map(SwitchActions::view)
+    }
+
+    /// The actions performed when this switch is pressed
+    pub fn press_actions(&self) -> SwitchActionsView<'_> {
+        SwitchActionsView {
+            stop_sounds: self.stop_sounds,
+            play: self.play.as_ref(),
+            play_random: self.play_random.as_ref(),
+            play_step: self.play_step.as_ref(),
+            midi_out: self.midi_out.as_ref(),
+            scan: self.scan,
+        }
+    }
+
+    /// The fields of the actions that are specified directly on this switch
+    fn press_action_fields(&self) -> Vec<&'static str> {
+        let actions = self.press_actions();
+        [
+            ("stopSounds", actions.stop_sounds),
+            ("play", actions.play.is_some()),
+            ("playRandom", actions.play_random.is_some()),
+            ("playStep", actions.play_step.is_some()),
+            ("midiOut", actions.midi_out.is_some()),
+            ("scan", actions.scan.is_some()),
+        ]
+            .into_iter()
+            .filter(|(_, is_set)| *is_set)
+            .map(|(field, _)| field)
+            .collect()
+    }
+
+    /// True if this switch controls the scanning highlight, such a switch is skipped while
+    /// scanning
+    pub fn has_scan_action(&self) -> bool {
+        self.scan.is_some() || [&self.on_release, &self.short_press, &self.long_press, &self.double_press]
+            .into_iter()
+            .flatten()
+            .any(|actions| actions.scan.is_some())
+    }
+
+    /// The actions performed when this switch is released
+    pub fn release_actions(&self) -> Option<SwitchActionsView<'_>> {
+        self.on_release.as_ref().map(SwitchActions::view)
+    }
+
+    /// All keyboard keys that trigger this switch, along with the path of their field relative to
+    /// the switch
+    fn key_fields(&self) -> Vec<(String, &String)> {
+        self.key.iter()
+            .map(|key| (String::from("key"), key))
+            .chain(self.keys.iter().enumerate().map(|(index, key)| (format!("keys[{}]", index), key)))
+            .collect()
+    }
+
+    /// All gamepad buttons that trigger this switch, along with the path of their field relative
+    /// to the switch
+    fn gamepad_fields(&self) -> Vec<(String, &Gamepad)> {
+        self.gamepad.iter()
+            .map(|gamepad| (String::from("gamepad"), gamepad))
+            .chain(self.gamepads.iter().enumerate().map(|(index, gamepad)| (format!("gamepads[{}]", index), gamepad)))
+            .collect()
+    }
+
+    fn gamepad_fields_mut(&mut self) -> Vec<(String, &mut Gamepad)> {
+        self.gamepad.iter_mut()
+            .map(|gamepad| (String::from("gamepad"), gamepad))
+            .chain(self.gamepads.iter_mut().enumerate().map(|(index, gamepad)| (format!("gamepads[{}]", index), gamepad)))
+            .collect()
+    }
+
+    /// All gamepad axes that trigger this switch, along with the path of their field relative to
+    /// the switch. GamepadAxisRef::axis_index refers to the index in this list.
+    pub fn gamepad_axis_fields(&self) -> Vec<(String, &GamepadAxis)> {
+        self.gamepad_axis.iter()
+            .map(|axis| (String::from("gamepadAxis"), axis))
+            .chain(self.gamepad_axes.iter().enumerate().map(|(index, axis)| (format!("gamepadAxes[{}]", index), axis)))
+            .collect()
+    }
+
+    fn gamepad_axis_fields_mut(&mut self) -> Vec<(String, &mut GamepadAxis)> {
+        self.gamepad_axis.iter_mut()
+            .map(|axis| (String::from("gamepadAxis"), axis))
+            .chain(self.gamepad_axes.iter_mut().enumerate().map(|(index, axis)| (format!("gamepadAxes[{}]", index), axis)))
+            .collect()
+    }
+
+    /// All MIDI notes and controllers that trigger this switch, along with the path of their field
+    /// relative to the switch. MidiTriggerRef::trigger_index refers to the index in this list.
+    pub fn midi_fields(&self) -> Vec<(String, &MidiTrigger)> {
+        self.midi.iter()
+            .map(|trigger| (String::from("midi"), trigger))
+            .chain(self.midi_messages.iter().enumerate().map(|(index, trigger)| (format!("midiMessages[{}]", index), trigger)))
+            .collect()
+    }
+
+    fn midi_fields_mut(&mut self) -> Vec<(String, &mut MidiTrigger)> {
+        self.midi.iter_mut()
+            .map(|trigger| (String::from("midi"), trigger))
+            .chain(self.midi_messages.iter_mut().enumerate().map(|(index, trigger)| (format!("midiMessages[{}]", index), trigger)))
+            .collect()
+    }
+
+    /// All mouse buttons that trigger this switch, along with the path of their field relative to
+    /// the switch
+    fn mouse_fields(&self) -> Vec<(String, &String)> {
+        self.mouse.iter()
+            .map(|button| (String::from("mouse"), button))
+            .chain(self.mouse_buttons.iter().enumerate().map(|(index, button)| (format!("mouseButtons[{}]", index), button)))
+            .collect()
+    }
+}
+
+/// Anything that can trigger a switch, used to detect conflicting bindings
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Binding {
+    Key(KeyBinding),
+    GamepadButton(GamepadSelector, Button),
+    MouseButton(mouse::Button),
+    /// A MIDI note or controller, on a port and channel or any of them
+    Midi(Option<String>, MidiControl, Option<u8>),
+}
+
+impl Binding {
+    /// A MIDI trigger without a port or a channel conflicts with the same control on every port
+    /// or channel. Two different port patterns are assumed to select different ports.
+    fn conflicts_with(&self, other: &Binding) -> bool {
+        match (self, other) {
+            (Binding::Midi(port, control, channel), Binding::Midi(other_port, other_control, other_channel)) => {
+                control == other_control
+                    && (port.is_none() || other_port.is_none() || port == other_port)
+                    && (channel.is_none() || other_channel.is_none() || channel == other_channel)
+            },
+            _ => self == other,
+        }
+    }
+}
+
+/// Insert a switch into a list of a lookup table, a switch which is bound multiple times to the
+/// same trigger is only inserted once.
+fn push_switch_ref(list: &mut Vec<SwitchRef>, switch_ref: SwitchRef) {
+    if !list.contains(&switch_ref) {
+        list.push(switch_ref);
+    }
+}
+
+struct ConfigIdLookup {
+    /// bank.id => BankRef
+    bank_id_lookup: HashMap<String, BankRef>,
+
+    /// sample.id => BankRef
+    sample_id_lookup: HashMap<String, HashMap<String, BankSampleRef>>,
+}
+
+impl ConfigIdLookup {
+    fn new(banks: &[BankConfig]) -> Self {
+        let bank_id_lookup = banks
+            .iter()
+            .map(|bank| (bank.id.clone(), bank.bank_ref))
+            .collect();
+
+        let sample_id_lookup = banks
+            .iter()
+            .map(|bank| {
+                let samples = bank.samples
+                    .iter()
+                    .map(|sample| (sample.id.clone(), sample.bank_sample_ref))
+                    .collect();
+
+                (bank.id.clone(), samples)
+            })
+            .collect();
+
+        ConfigIdLookup { bank_id_lookup, sample_id_lookup }
+    }
+
+    fn bank_id_to_ref(&self, bank_id: &str) -> Result<BankRef, ConfigError> {
+        match self.bank_id_lookup.get(bank_id) {
+            Some(bank_ref) => Ok(*bank_ref),
+            None => Err(ConfigError::UnknownBankId {
+                bank: bank_id.to_string(),
+            }),
+        }
+    }
+
+    fn sample_id_to_ref(&self, bank_id: &str, sample_id: &str) -> Result<BankSampleRef, ConfigError> {
+        let result = self.sample_id_lookup
+            .get(bank_id)
+            .map(|map| map.get(sample_id));
+
+        match result {
+            None => Err(ConfigError::UnknownBankId {
+                bank: bank_id.to_string(),
+            }),
+            Some(None) => Err(ConfigError::UnknownSampleId {
+                bank: bank_id.to_string(),
+                sample: sample_id.to_string(),
+            }),
+            Some(Some(bank_sample_ref)) => Ok(*bank_sample_ref),
+        }
+    }
+}
+
+/// Collects the problems found while resolving a config, so that all of them can be reported at
+/// once instead of only the first one.
+#[derive(Default)]
+struct ConfigErrorCollector {
+    /// (path of the offending field, error)
+    errors: Vec<(String, ConfigError)>,
+    /// (path of the offending field, warning)
+    warnings: Vec<(String, ConfigWarning)>,
+}
+
+impl ConfigErrorCollector {
+    fn push(&mut self, path: String, error: ConfigError) {
+        self.errors.push((path, error));
+    }
+
+    fn push_warning(&mut self, path: String, warning: ConfigWarning) {
+        self.warnings.push((path, warning));
+    }
+
+    /// Returns ConfigError::Invalid if any problem has been collected, otherwise the warnings are
+    /// returned. The yaml source is used to look up the line and column of each problem.
+    fn into_result(self, yaml_string: &str) -> Result<Vec<LocatedConfigWarning>, ConfigError> {
+        if self.errors.is_empty() && self.warnings.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let locations = YamlLocations::parse(yaml_string);
+
+        // report in the same order as the config file, problems without a location go last
+        let sort_key = |location: &Option<Location>| {
+            location.map_or((usize::MAX, usize::MAX), |location| (location.line, location.column))
+        };
+
+        if self.errors.is_empty() {
+            let mut warnings: Vec<LocatedConfigWarning> = self.warnings
+                .into_iter()
+                .map(|(path, warning)| LocatedConfigWarning {
+                    location: locations.get(&path),
+                    path,
+                    warning,
+                })
+                .collect();
+
+            warnings.sort_by_key(|warning| sort_key(&warning.location));
+            return Ok(warnings);
+        }
+
+        let mut errors: Vec<LocatedConfigError> = self.errors
+            .into_iter()
+            .map(|(path, source)| LocatedConfigError {
+                location: locations.get(&path),
+                path,
+                source,
+            })
+            .collect();
+
+        errors.sort_by_key(|error| sort_key(&error.location));
+        Err(ConfigError::Invalid { errors })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub banks: Vec<BankConfig>,
+    pub switches: Vec<SwitchConfig>,
+
+    /// Filters out unintended presses of all switches
+    #[serde(default, rename = "inputFilter")]
+    pub input_filter: InputFilterConfig,
+
+    /// Highlight the buttons in the window one after another, so that a single switch can
+    /// activate any of them
+    pub scanning: Option<ScanningConfig>,
+
+    /// If true, mouse button triggers ignore clicks on the buttons in the window
+    #[serde(default = "default_ignore_gui_clicks", rename = "ignoreGuiClicks")]
+    pub ignore_gui_clicks: bool,
+
+    /// Receive and send OSC messages over UDP
+    pub osc: Option<OscConfig>,
+
+    /// Serve a remote control over HTTP and WebSocket
+    pub http: Option<HttpConfig>,
+
+    /// Record every session to a file
+    #[serde(rename = "sessionLog")]
+    pub session_log: Option<SessionLogConfig>,
+
+    /// Show a button in the window to record everything that is played to a WAV file
+    pub recording: Option<RecordingConfig>,
+
+    /// Where the sound is played, this is only applied after a restart
+    #[serde(default)]
+    pub audio: AudioConfig,
+
+    /// The volume and pan of everything that is played
+    #[serde(default)]
+    pub master: MasterConfig,
+
+    // Cached //
+
+    /// The path that all other paths are relative to
+    /// None if the config was embedded, Some if the config is from disk
+    #[serde(skip)]
+    pub resolve_path: Option<PathBuf>,
+
+    /// Problems found in the config which do not prevent it from being used
+    #[serde(skip)]
+    pub warnings: Vec<LocatedConfigWarning>,
+
+    #[serde(skip)]
+    // keyboard key code and modifiers => switch config references
+    keyboard_key_to_switch_lookup_table: HashMap<KeyBinding, Vec<SwitchRef>>,
+
+    #[serde(skip)]
+    // gamepad device selector => gamepad button => switch config references
+    gamepad_button_to_switch_lookup_table: HashMap<GamepadSelector, HashMap<Button, Vec<SwitchRef>>>,
+
+    #[serde(skip)]
+    // gamepad device selector => gamepad axis => axis triggers
+    gamepad_axis_lookup_table: HashMap<GamepadSelector, HashMap<Axis, Vec<GamepadAxisRef>>>,
+
+    #[serde(skip)]
+    // mouse button => switch config references
+    mouse_button_to_switch_lookup_table: HashMap<mouse::Button, Vec<SwitchRef>>,
+
+    #[serde(skip)]
+    // MIDI note or controller => MIDI triggers
+    midi_lookup_table: HashMap<MidiControl, Vec<MidiTriggerRef>>,
+
+    #[serde(skip)]
+    // for each switch that has a SwitchConfig.play (SwitchPlay) configuration, map the sample that it specifies to the switch
+    sample_to_switch_play: HashMap<BankSampleRef, Vec<SwitchRef>>,
+
+    #[serde(skip)]
+    empty_switch_ref_vec: Vec<SwitchRef>, // serde will init this to Default::default() which will be an empty vec
+}
+
+impl Config {
+    pub fn from_string(yaml_string: &str, resolve_path: Option<PathBuf>) -> Result<Config, ConfigError> {
+        let mut config: Config = serde_yaml::from_str(yaml_string)?;
+        config.resolve_path = resolve_path;
+
+        let mut errors = ConfigErrorCollector::default();
+        config.resolve_refs(&mut errors);
         config.resolve_bank_paths();
-        config.resolve_gamepad_button_mappings()?;
-        config.resolve_keyboard_key_to_switch_lookup_table()?;
+        config.resolve_gains(&mut errors);
+        config.resolve_gamepad_button_mappings(&mut errors);
+        config.resolve_gamepad_axis_mappings(&mut errors);
+        config.resolve_midi_triggers(&mut errors);
+        config.resolve_keyboard_key_to_switch_lookup_table(&mut errors);
+        config.resolve_mouse_button_to_switch_lookup_table(&mut errors);
+        config.lint_bindings(&mut errors);
+        config.lint_scanning(&mut errors);
+        config.lint_gestures(&mut errors);
+        config.resolve_osc(&mut errors);
+        config.resolve_http(&mut errors);
+        config.warnings = errors.into_result(yaml_string)?;
+
         config.resolve_gamepad_button_to_switch_lookup_table();
+        config.resolve_gamepad_axis_lookup_table();
+        config.resolve_midi_lookup_table();
         config.resolve_sample_to_switch_play_lookup_table();
 
         Ok(config)
     }
 
     pub fn from_file(path: &Path) -> Result<Config, ConfigError> {
-        let content = fs::read_to_string(&path)?;
+        let content = fs::read_to_string(path)?;
 
         // all paths defined in the config file, are relative to the directory the config file is in
         let mut resolve_path = path.to_path_buf();
         resolve_path.pop();
-        Ok(Config::from_string(&content, Some(resolve_path))?)
+        Config::from_string(&content, Some(resolve_path))
     }
 
-    fn resolve_refs(&mut self) -> Result<(), ConfigError> {
+    fn resolve_refs(&mut self, errors: &mut ConfigErrorCollector) {
         for (bank_index, bank_config) in &mut self.banks.iter_mut().enumerate() {
             bank_config.bank_ref.bank_index = bank_index;
 
@@ -475,113 +1497,545 @@ impl Config {
             switch_config.switch_ref.switch_index = switch_index;
         }
 
-        let lookup = ConfigIdLookup::new(&self.banks);
+        let lookup = ConfigIdLookup::new(&self.banks);
+
+        for (switch_index, switch_config) in &mut self.switches.iter_mut().enumerate() {
+            let path = format!("switches[{}]", switch_index);
+
+            Self::resolve_action_refs(
+                &lookup,
+                &path,
+                switch_config.play.as_mut(),
+                switch_config.play_random.as_mut(),
+                switch_config.play_step.as_mut(),
+                switch_config.midi_out.as_mut(),
+                errors,
+            );
+
+            let nested_actions = [
+                ("onRelease", switch_config.on_release.as_mut()),
+                ("shortPress", switch_config.short_press.as_mut()),
+                ("longPress", switch_config.long_press.as_mut()),
+                ("doublePress", switch_config.double_press.as_mut()),
+            ];
+
+            for (field, actions) in nested_actions {
+                if let Some(actions) = actions {
+                    Self::resolve_action_refs(
+                        &lookup,
+                        &format!("{}.{}", path, field),
+                        actions.play.as_mut(),
+                        actions.play_random.as_mut(),
+                        actions.play_step.as_mut(),
+                        actions.midi_out.as_mut(),
+                        errors,
+                    );
+                }
+            }
+        }
+    }
+
+    fn resolve_action_refs(
+        lookup: &ConfigIdLookup,
+        path: &str,
+        play: Option<&mut SwitchPlay>,
+        play_random: Option<&mut SwitchPlayRandom>,
+        play_step: Option<&mut SwitchPlayStep>,
+        midi_out: Option<&mut MidiOut>,
+        errors: &mut ConfigErrorCollector,
+    ) {
+        if let Some(play) = play {
+            match lookup.sample_id_to_ref(&play.bank, &play.sample) {
+                Ok(bank_sample_ref) => play.bank_sample_ref = bank_sample_ref,
+                Err(err @ ConfigError::UnknownSampleId { .. }) => errors.push(format!("{}.play.sample", path), err),
+                Err(err) => errors.push(format!("{}.play.bank", path), err),
+            }
+        }
+
+        if let Some(play) = play_random {
+            match lookup.bank_id_to_ref(&play.bank) {
+                Ok(bank_ref) => play.bank_ref = bank_ref,
+                Err(err) => errors.push(format!("{}.playRandom.bank", path), err),
+            }
+        }
+
+        if let Some(play) = play_step {
+            match lookup.bank_id_to_ref(&play.bank) {
+                Ok(bank_ref) => play.bank_ref = bank_ref,
+                Err(err) => errors.push(format!("{}.playStep.bank", path), err),
+            }
+        }
+
+        if let Some(midi_out) = midi_out {
+            match resolve_midi_control(midi_out.note, midi_out.cc) {
+                Ok(control) => midi_out.control = control,
+                Err((sub_field, err)) => errors.push(format!("{}.midiOut{}", path, sub_field), err),
+            }
+
+            if !(1..=16).contains(&midi_out.channel) {
+                errors.push(format!("{}.midiOut.channel", path), ConfigError::InvalidMidiChannel { channel: midi_out.channel });
+            }
+
+            for (field, value) in [("velocity", midi_out.velocity), ("value", midi_out.value)] {
+                if value > 127 {
+                    errors.push(format!("{}.midiOut.{}", path, field), ConfigError::InvalidMidiValue { value });
+                }
+            }
+        }
+    }
+
+    fn resolve_bank_paths(&mut self) {
+        if let Some(session_log) = &mut self.session_log {
+            session_log.directory_resolved = match &self.resolve_path {
+                Some(resolve_path) => resolve_path.join(&session_log.directory),
+                None => session_log.directory.clone(),
+            };
+        }
+
+        let resolve_path = &self.resolve_path;
+        if resolve_path.is_none() {
+            return;
+        }
+        let resolve_path = resolve_path.as_ref().unwrap();
+
+        for bank_config in &mut self.banks {
+            for sample in &mut bank_config.samples {
+                let mut resolved_file = PathBuf::from(resolve_path);
+                resolved_file.push(&sample.file);
+                sample.file_resolved = Some(resolved_file);
+            }
+        }
+    }
+
+    /// Returns the volume and pan of a `volume` and `pan` field, or the defaults if they have not
+    /// been set. `path` is the path of the object that contains both fields.
+    fn resolve_gain(volume: &Option<VolumeConfig>, pan: Option<f32>, path: &str, errors: &mut ConfigErrorCollector) -> Gain {
+        let mut gain = Gain::default();
+
+        if let Some(volume) = volume {
+            match volume.linear() {
+                Some(linear) => gain.volume = linear,
+                None => errors.push(format!("{}volume", path), ConfigError::InvalidVolume { volume: volume.to_string() }),
+            }
+        }
+
+        if let Some(pan) = pan {
+            if (-1.0..=1.0).contains(&pan) {
+                gain.pan = pan;
+            } else {
+                errors.push(format!("{}pan", path), ConfigError::InvalidPan { pan });
+            }
+        }
+
+        gain
+    }
+
+    fn resolve_gains(&mut self, errors: &mut ConfigErrorCollector) {
+        let master = Self::resolve_gain(&self.master.volume, self.master.pan, "master.", errors);
+
+        for (bank_index, bank_config) in self.banks.iter_mut().enumerate() {
+            let path = format!("banks[{}].", bank_index);
+            let bank = Self::resolve_gain(&bank_config.volume, bank_config.pan, &path, errors);
+
+            for (sample_index, sample_config) in bank_config.samples.iter_mut().enumerate() {
+                let path = format!("{}samples[{}].", path, sample_index);
+                let sample = Self::resolve_gain(&sample_config.volume, sample_config.pan, &path, errors);
+
+                sample_config.gain = Gain {
+                    volume: master.volume * bank.volume * sample.volume,
+                    pan: (master.pan + bank.pan + sample.pan).clamp(-1.0, 1.0),
+                };
+            }
+        }
+    }
+
+    fn resolve_gamepad_button_mappings(&mut self, errors: &mut ConfigErrorCollector) {
+        let mapping = make_gamepad_button_map();
+        for (switch_index, switch_config) in self.switches.iter_mut().enumerate() {
+            for (field, gamepad) in switch_config.gamepad_fields_mut() {
+                match mapping.get(&gamepad.button.as_str()) {
+                    Some(gilrs_button) => {
+                        gamepad.gilrs_button = *gilrs_button;
+                    },
+                    None => {
+                        errors.push(format!("switches[{}].{}.button", switch_index, field), ConfigError::UnknownGamepadButton {
+                            button: String::from(&gamepad.button),
+                            allowed_values: make_valid_gamepad_button_vec().join(", "),
+                        });
+                    },
+                }
+
+                match gamepad.resolve_selector() {
+                    Ok(selector) => {
+                        gamepad.selector = selector;
+                    },
+                    Err((sub_field, error)) => {
+                        errors.push(format!("switches[{}].{}{}", switch_index, field, sub_field), error);
+                    },
+                }
+            }
+        }
+    }
+
+    fn resolve_gamepad_axis_mappings(&mut self, errors: &mut ConfigErrorCollector) {
+        let mapping = make_gamepad_axis_map();
+        for (switch_index, switch_config) in self.switches.iter_mut().enumerate() {
+            for (field, axis) in switch_config.gamepad_axis_fields_mut() {
+                match mapping.get(&axis.axis.as_str()) {
+                    Some(gilrs_axis) => {
+                        axis.gilrs_axis = *gilrs_axis;
+                    },
+                    None => {
+                        errors.push(format!("switches[{}].{}.axis", switch_index, field), ConfigError::UnknownGamepadAxis {
+                            axis: String::from(&axis.axis),
+                            allowed_values: make_valid_gamepad_axis_vec().join(", "),
+                        });
+                    },
+                }
+
+                if !(axis.threshold > 0.0 && axis.threshold <= 1.0) {
+                    errors.push(format!("switches[{}].{}.threshold", switch_index, field), ConfigError::InvalidGamepadAxisThreshold {
+                        threshold: axis.threshold,
+                    });
+                }
+
+                if !(axis.hysteresis >= 0.0 && axis.hysteresis < axis.threshold) {
+                    errors.push(format!("switches[{}].{}.hysteresis", switch_index, field), ConfigError::InvalidGamepadAxisHysteresis {
+                        hysteresis: axis.hysteresis,
+                    });
+                }
+
+                match axis.resolve_selector() {
+                    Ok(selector) => {
+                        axis.selector = selector;
+                    },
+                    Err((sub_field, error)) => {
+                        errors.push(format!("switches[{}].{}{}", switch_index, field, sub_field), error);
+                    },
+                }
+            }
+        }
+    }
+
+    fn resolve_midi_triggers(&mut self, errors: &mut ConfigErrorCollector) {
+        for (switch_index, switch_config) in self.switches.iter_mut().enumerate() {
+            for (field, trigger) in switch_config.midi_fields_mut() {
+                match trigger.resolve_control() {
+                    Ok(control) => {
+                        trigger.control = control;
+                    },
+                    Err((sub_field, error)) => {
+                        errors.push(format!("switches[{}].{}{}", switch_index, field, sub_field), error);
+                    },
+                }
+
+                if let Some(channel) = trigger.channel {
+                    if !(1..=16).contains(&channel) {
+                        errors.push(format!("switches[{}].{}.channel", switch_index, field), ConfigError::InvalidMidiChannel { channel });
+                    }
+                }
+
+                if !(1..=127).contains(&trigger.threshold) {
+                    errors.push(format!("switches[{}].{}.threshold", switch_index, field), ConfigError::InvalidMidiThreshold {
+                        threshold: trigger.threshold,
+                    });
+                }
+            }
+        }
+    }
+
+    fn resolve_keyboard_key_to_switch_lookup_table(&mut self, errors: &mut ConfigErrorCollector) {
+        let mapping = make_keyboard_button_map();
+        let mut lookup_table = HashMap::new();
+
+        for (switch_index, switch_config) in self.switches.iter_mut().enumerate() {
+            let mut key_bindings = Vec::new();
+
+            for (field, key) in switch_config.key_fields() {
+                match KeyBinding::parse(key, &mapping) {
+                    Ok(key_binding) => {
+                        key_bindings.push(key_binding);
+                    },
+                    Err(error) => {
+                        errors.push(format!("switches[{}].{}", switch_index, field), error);
+                    },
+                }
+            }
+
+            switch_config.key_bindings = key_bindings;
+        }
+
+        for switch_config in self.switches.iter() {
+            for key_binding in &switch_config.key_bindings {
+                let list = lookup_table.entry(*key_binding).or_insert_with(Vec::new);
+                push_switch_ref(list, switch_config.switch_ref);
+            }
+        }
+
+        self.keyboard_key_to_switch_lookup_table = lookup_table;
+    }
+
+    fn resolve_mouse_button_to_switch_lookup_table(&mut self, errors: &mut ConfigErrorCollector) {
+        let mapping = make_mouse_button_map();
+        let mut lookup_table = HashMap::new();
+
+        for (switch_index, switch_config) in self.switches.iter_mut().enumerate() {
+            let mut iced_mouse_buttons = Vec::new();
+
+            for (field, button) in switch_config.mouse_fields() {
+                match mapping.get(button.as_str()) {
+                    Some(iced_mouse_button) => {
+                        iced_mouse_buttons.push(*iced_mouse_button);
+                    },
+                    None => {
+                        errors.push(format!("switches[{}].{}", switch_index, field), ConfigError::UnknownMouseButton {
+                            button: button.to_string(),
+                            allowed_values: make_valid_mouse_button_vec().join(", "),
+                        });
+                    },
+                }
+            }
+
+            switch_config.iced_mouse_buttons = iced_mouse_buttons;
+        }
+
+        for switch_config in self.switches.iter() {
+            for iced_mouse_button in &switch_config.iced_mouse_buttons {
+                let list = lookup_table.entry(*iced_mouse_button).or_insert_with(Vec::new);
+                push_switch_ref(list, switch_config.switch_ref);
+            }
+        }
+
+        self.mouse_button_to_switch_lookup_table = lookup_table;
+    }
+
+    /// All triggers of a switch as (path of the field, human readable description, binding).
+    /// Triggers which could not be resolved are skipped, they have already been reported.
+    fn switch_bindings(switch_config: &SwitchConfig) -> Vec<(String, String, Binding)> {
+        let switch_index = switch_config.switch_ref.switch_index;
+        let key_mapping = make_keyboard_button_map();
+        let gamepad_mapping = make_gamepad_button_map();
+        let mouse_mapping = make_mouse_button_map();
+        let mut bindings = Vec::new();
+
+        for (field, key) in switch_config.key_fields() {
+            if let Ok(key_binding) = KeyBinding::parse(key, &key_mapping) {
+                bindings.push((
+                    format!("switches[{}].{}", switch_index, field),
+                    format!("keyboard key \"{}\"", key),
+                    Binding::Key(key_binding),
+                ));
+            }
+        }
+
+        for (field, gamepad) in switch_config.gamepad_fields() {
+            // an unknown button has already been reported, its gilrs_button is meaningless
+            if gamepad_mapping.contains_key(gamepad.button.as_str()) {
+                bindings.push((
+                    format!("switches[{}].{}", switch_index, field),
+                    format!("gamepad button \"{}\" on {}", gamepad.button, gamepad.selector),
+                    Binding::GamepadButton(gamepad.selector.clone(), gamepad.gilrs_button),
+                ));
+            }
+        }
+
+        for (field, button) in switch_config.mouse_fields() {
+            if let Some(iced_mouse_button) = mouse_mapping.get(button.as_str()) {
+                bindings.push((
+                    format!("switches[{}].{}", switch_index, field),
+                    format!("mouse button \"{}\"", button),
+                    Binding::MouseButton(*iced_mouse_button),
+                ));
+            }
+        }
+
+        for (field, trigger) in switch_config.midi_fields() {
+            // an invalid trigger has already been reported, its control is meaningless
+            if let Ok(control) = trigger.resolve_control() {
+                let control_description = match control {
+                    MidiControl::Note(note) => format!("MIDI note {}", note),
+                    MidiControl::Cc(cc) => format!("MIDI controller {}", cc),
+                };
+                let channel_description = match trigger.channel {
+                    Some(channel) => format!("channel {}", channel),
+                    None => String::from("any channel"),
+                };
+                let port_description = match &trigger.port {
+                    Some(port) => format!(" of port \"{}\"", port),
+                    None => String::new(),
+                };
+
+                bindings.push((
+                    format!("switches[{}].{}", switch_index, field),
+                    format!("{} on {}{}", control_description, channel_description, port_description),
+                    Binding::Midi(trigger.port.clone(), control, trigger.channel),
+                ));
+            }
+        }
+
+        bindings
+    }
+
+    /// Detect switches which are triggered by the same keyboard key, gamepad button, mouse button
+    /// or MIDI message. This is reported as an error, unless all of these switches explicitly allow it
+    /// using `sharedBinding`. A gamepad button bound to a specific device, which takes priority
+    /// over the same button bound to any device, is reported as a warning.
+    fn lint_bindings(&self, errors: &mut ConfigErrorCollector) {
+        // the most recent binding is looked up first
+        let mut bindings_seen: Vec<(Binding, SwitchRef)> = Vec::new();
+
+        for switch_config in &self.switches {
+            let switch_ref = switch_config.switch_ref;
+
+            for (path, description, binding) in Self::switch_bindings(switch_config) {
+                let conflict = bindings_seen
+                    .iter()
+                    .rev()
+                    .filter(|(seen, _)| binding.conflicts_with(seen))
+                    .map(|&(_, other)| other)
+                    .find(|&other| other != switch_ref && !self.is_shared_binding(switch_ref, other));
+
+                match conflict {
+                    Some(other) => {
+                        errors.push(path, ConfigError::ConflictingBinding {
+                            binding: description,
+                            other_switch: self.switch(other).title.clone(),
+                        });
+                    },
+                    None => {
+                        bindings_seen.push((binding, switch_ref));
+                    },
+                }
+            }
+        }
+
+        let gamepad_mapping = make_gamepad_button_map();
+        for switch_config in &self.switches {
+            for (field, gamepad) in switch_config.gamepad_fields() {
+                if gamepad.selector == GamepadSelector::Any || !gamepad_mapping.contains_key(gamepad.button.as_str()) {
+                    continue;
+                }
+
+                let any_device = Binding::GamepadButton(GamepadSelector::Any, gamepad.gilrs_button);
+                match bindings_seen.iter().rev().find(|(seen, _)| *seen == any_device) {
+                    Some(&(_, other)) if other != switch_config.switch_ref => {
+                        errors.push_warning(format!("switches[{}].{}", switch_config.switch_ref.switch_index, field), ConfigWarning::ShadowedGamepadBinding {
+                            button: gamepad.button.clone(),
+                            device: gamepad.selector.to_string(),
+                            other_switch: self.switch(other).title.clone(),
+                        });
+                    },
+                    _ => {},
+                }
+            }
+        }
+    }
+
+    fn lint_scanning(&self, errors: &mut ConfigErrorCollector) {
+        if let Some(scanning) = &self.scanning {
+            if scanning.mode == ScanMode::Auto && scanning.interval_ms == 0 {
+                errors.push(String::from("scanning.intervalMs"), ConfigError::InvalidScanInterval);
+            }
 
-        for switch_config in &mut self.switches.iter_mut() {
-            if let Some(play) = &mut switch_config.play.as_mut() {
-                play.bank_sample_ref = lookup.sample_id_to_ref(&play.bank, &play.sample)?;
+            if scanning.columns == 0 {
+                errors.push(String::from("scanning.columns"), ConfigError::InvalidScanColumns);
             }
+        }
+    }
 
-            if let Some(play) = &mut switch_config.play_random.as_mut() {
-                play.bank_ref = lookup.bank_id_to_ref(&play.bank)?;
+    /// Actions specified directly on a switch are performed on every press, so they would be
+    /// performed in addition to the actions of every gesture
+    fn lint_gestures(&self, errors: &mut ConfigErrorCollector) {
+        for switch_config in &self.switches {
+            if switch_config.gesture_timing().is_none() {
+                continue;
             }
 
-            if let Some(play) = &mut switch_config.play_step.as_mut() {
-                play.bank_ref = lookup.bank_id_to_ref(&play.bank)?;
+            for field in switch_config.press_action_fields() {
+                errors.push(
+                    format!("switches[{}].{}", switch_config.switch_ref.switch_index, field),
+                    ConfigError::PressActionWithGestures { action: String::from(field) },
+                );
             }
         }
-
-        Ok(())
     }
 
-    fn resolve_bank_paths(&mut self) {
-        let resolve_path = &self.resolve_path;
-        if resolve_path.is_none() {
+    fn resolve_osc(&mut self, errors: &mut ConfigErrorCollector) {
+        let Some(osc) = &mut self.osc else {
             return;
-        }
-        let resolve_path = resolve_path.as_ref().unwrap();
+        };
 
-        for bank_config in &mut self.banks {
-            for sample in &mut bank_config.samples {
-                let mut resolved_file = PathBuf::from(resolve_path);
-                resolved_file.push(&sample.file);
-                sample.file_resolved = Some(resolved_file);
+        let mut parse = |field: &str, address: &Option<String>| -> Option<SocketAddr> {
+            let address = address.as_ref()?;
+            match address.parse() {
+                Ok(socket_addr) => Some(socket_addr),
+                Err(_) => {
+                    errors.push(format!("osc.{}", field), ConfigError::InvalidSocketAddress { address: address.clone() });
+                    None
+                },
             }
+        };
+
+        osc.listen_addr = parse("listen", &osc.listen);
+        osc.send_addr = parse("send", &osc.send);
+
+        if !osc.prefix.starts_with('/') {
+            errors.push(String::from("osc.prefix"), ConfigError::InvalidOscPrefix { prefix: osc.prefix.clone() });
         }
     }
 
-    fn resolve_gamepad_button_mappings(&mut self) -> Result<(), ConfigError> {
-        let mapping = make_gamepad_button_map();
-        for switch_config in &mut self.switches {
-            if let Some(gamepad) = &mut switch_config.gamepad {
-                let gilrs_button = match mapping.get(&gamepad.button.as_str()) {
-                    None => {
-                        return Err(ConfigError::UnknownGamepadButton {
-                            button: String::from(&gamepad.button),
-                            allowed_values: make_valid_gamepad_button_vec().join(", "),
-                        });
-                    },
-                    Some(v) => v,
-                };
-                gamepad.gilrs_button = *gilrs_button;
+    fn resolve_http(&mut self, errors: &mut ConfigErrorCollector) {
+        if let Some(http) = &mut self.http {
+            match http.listen.parse() {
+                Ok(listen_addr) => http.listen_addr = listen_addr,
+                Err(_) => errors.push(String::from("http.listen"), ConfigError::InvalidSocketAddress { address: http.listen.clone() }),
             }
         }
+    }
 
-        Ok(())
+    fn is_shared_binding(&self, a: SwitchRef, b: SwitchRef) -> bool {
+        self.switch(a).shared_binding && self.switch(b).shared_binding
     }
 
-    fn resolve_keyboard_key_to_switch_lookup_table(&mut self) -> Result<(), ConfigError> {
-        let mapping = make_keyboard_button_map();
+    fn resolve_gamepad_button_to_switch_lookup_table(&mut self) {
         let mut lookup_table = HashMap::new();
 
-        for switch_config in (&mut self.switches).into_iter() {
-            if let Some(key) = &switch_config.key {
-                match mapping.get(key.as_str()) {
-                    Some(key_code) => {
-                        switch_config.key_code = Some(*key_code);
-                    },
-                    None => {
-                        return Err(ConfigError::UnknownKeyboardButton {
-                            button: key.to_string(),
-                            allowed_values: make_valid_keyboard_button_vec().join(", "),
-                        });
-                    },
-                }
+        for switch_config in &self.switches {
+            for (_field, gamepad_config) in switch_config.gamepad_fields() {
+                let map = lookup_table.entry(gamepad_config.selector.clone()).or_insert_with(HashMap::new);
+                let list = map.entry(gamepad_config.gilrs_button).or_insert_with(Vec::new);
+                push_switch_ref(list, switch_config.switch_ref);
             }
         }
 
-        for switch_config in (&self.switches).into_iter() {
-            if let Some(key_code) = &switch_config.key_code {
-                lookup_table.insert(*key_code, switch_config.switch_ref);
+        self.gamepad_button_to_switch_lookup_table = lookup_table;
+    }
+
+    fn resolve_gamepad_axis_lookup_table(&mut self) {
+        let mut lookup_table = HashMap::new();
+
+        for switch_config in &self.switches {
+            for (axis_index, (_field, axis_config)) in switch_config.gamepad_axis_fields().into_iter().enumerate() {
+                let map = lookup_table.entry(axis_config.selector.clone()).or_insert_with(HashMap::new);
+                let list = map.entry(axis_config.gilrs_axis).or_insert_with(Vec::new);
+                list.push(GamepadAxisRef { switch_ref: switch_config.switch_ref, axis_index });
             }
         }
 
-        self.keyboard_key_to_switch_lookup_table = lookup_table;
-        Ok(())
+        self.gamepad_axis_lookup_table = lookup_table;
     }
 
-    fn resolve_gamepad_button_to_switch_lookup_table(&mut self) {
-        let gamepad_configs = (&self.switches)
-            .into_iter()
-            .filter_map(|switch_config| {
-                match &switch_config.gamepad {
-                    Some(gamepad) => Some((gamepad, switch_config.switch_ref)),
-                    None => None,
-                }
-            });
-
+    fn resolve_midi_lookup_table(&mut self) {
         let mut lookup_table = HashMap::new();
 
-        for (gamepad_config, switch_ref) in gamepad_configs {
-            if !lookup_table.contains_key(&gamepad_config.device_id) {
-                lookup_table.insert(gamepad_config.device_id, HashMap::new());
+        for switch_config in &self.switches {
+            for (trigger_index, (_field, trigger)) in switch_config.midi_fields().into_iter().enumerate() {
+                let list = lookup_table.entry(trigger.control).or_insert_with(Vec::new);
+                list.push(MidiTriggerRef { switch_ref: switch_config.switch_ref, trigger_index });
             }
-
-            let map = lookup_table.get_mut(&gamepad_config.device_id).unwrap();
-            map.insert(gamepad_config.gilrs_button, switch_ref);
         }
 
-        self.gamepad_button_to_switch_lookup_table = lookup_table;
+        self.midi_lookup_table = lookup_table;
     }
 
     fn resolve_sample_to_switch_play_lookup_table(&mut self) {
@@ -589,7 +2043,7 @@ impl Config {
 
         for switch in &self.switches {
             if let Some(play) = &switch.play {
-                let list = lookup_table.entry(play.bank_sample_ref).or_insert_with(|| Vec::new());
+                let list = lookup_table.entry(play.bank_sample_ref).or_insert_with(Vec::new);
                 list.push(switch.switch_ref);
             }
         }
@@ -597,35 +2051,134 @@ impl Config {
         self.sample_to_switch_play = lookup_table;
     }
 
-    pub fn find_switch_for_keyboard_key(&self, key: KeyCode) -> Option<&SwitchConfig> {
-        match self.keyboard_key_to_switch_lookup_table.get(&key) {
-            Some(switch_ref) => Some(&self.switches[switch_ref.switch_index]),
-            None => None,
+    pub fn print_warnings(&self) {
+        for warning in &self.warnings {
+            println!("Warning: {}", warning);
         }
     }
 
-    pub fn find_switch_for_gamepad_button(&self, device_id: usize, button: Button) -> Option<&SwitchConfig> {
-        // first try to find a switch configured for a specific gamepad device
-        let switch_ref = match self.gamepad_button_to_switch_lookup_table.get(&Some(device_id)) {
-            Some(map) => map.get(&button),
-            None => None,
+    /// The input filters of a switch, combined with the global input filters
+    pub fn input_filter_settings(&self, switch_ref: SwitchRef) -> InputFilterSettings {
+        let switch_filter = &self.switch(switch_ref).input_filter;
+        let global_filter = &self.input_filter;
+        let millis = |switch_value: Option<u64>, global_value: Option<u64>| {
+            Duration::from_millis(switch_value.or(global_value).unwrap_or(0))
         };
 
-        // Then try to find a switch configured for all gamepad devices
-        let switch_ref = match switch_ref {
-            Some(v) => Some(v),
-            None => {
-                match self.gamepad_button_to_switch_lookup_table.get(&None) {
-                    Some(map) => map.get(&button),
-                    None => None,
+        InputFilterSettings {
+            debounce: millis(switch_filter.debounce_ms, global_filter.debounce_ms),
+            min_hold: millis(switch_filter.min_hold_ms, global_filter.min_hold_ms),
+            cooldown: millis(switch_filter.cooldown_ms, global_filter.cooldown_ms),
+        }
+    }
+
+    /// Returns all switches that should be triggered by the given keyboard key, while exactly the
+    /// given modifiers are held down
+    pub fn find_switches_for_keyboard_key(&self, key_code: KeyCode, modifiers: Modifiers) -> &Vec<SwitchRef> {
+        match self.keyboard_key_to_switch_lookup_table.get(&KeyBinding::from_key_press(key_code, modifiers)) {
+            Some(list) => list,
+            None => &self.empty_switch_ref_vec,
+        }
+    }
+
+    /// Returns all switches that should be triggered by the given mouse button
+    pub fn find_switches_for_mouse_button(&self, button: mouse::Button) -> &Vec<SwitchRef> {
+        match self.mouse_button_to_switch_lookup_table.get(&normalize_mouse_button(button)) {
+            Some(list) => list,
+            None => &self.empty_switch_ref_vec,
+        }
+    }
+
+    /// Returns all switches that should be triggered by the given gamepad button, on a device which
+    /// matches the given selectors (see GamepadThread)
+    pub fn find_switches_for_gamepad_button(&self, selectors: &[GamepadSelector], button: Button) -> Vec<SwitchRef> {
+        // first try to find switches configured for a specific gamepad device
+        let mut list = Vec::new();
+        for selector in selectors {
+            if let Some(switch_refs) = self.gamepad_button_to_switch_lookup_table.get(selector).and_then(|map| map.get(&button)) {
+                for &switch_ref in switch_refs {
+                    push_switch_ref(&mut list, switch_ref);
                 }
             }
-        };
+        }
+
+        // Then try to find switches configured for all gamepad devices
+        if list.is_empty() {
+            if let Some(switch_refs) = self.gamepad_button_to_switch_lookup_table.get(&GamepadSelector::Any).and_then(|map| map.get(&button)) {
+                list.clone_from(switch_refs);
+            }
+        }
+
+        list
+    }
+
+    /// Returns all axis triggers for the given gamepad axis, on a device which matches the given
+    /// selectors. Unlike buttons, triggers for any device are always included, because each
+    /// trigger may use a different threshold and direction.
+    pub fn find_gamepad_axis_triggers(&self, selectors: &[GamepadSelector], axis: Axis) -> Vec<GamepadAxisRef> {
+        let mut list = Vec::new();
+        for selector in selectors.iter().chain(std::iter::once(&GamepadSelector::Any)) {
+            if let Some(axis_refs) = self.gamepad_axis_lookup_table.get(selector).and_then(|map| map.get(&axis)) {
+                for axis_ref in axis_refs {
+                    if !list.contains(axis_ref) {
+                        list.push(*axis_ref);
+                    }
+                }
+            }
+        }
+        list
+    }
+
+    pub fn gamepad_axis(&self, axis_ref: GamepadAxisRef) -> &GamepadAxis {
+        self.switch(axis_ref.switch_ref).gamepad_axis_fields()[axis_ref.axis_index].1
+    }
+
+    /// Returns the MIDI triggers for the given note or controller, on the given port and channel
+    /// (1 to 16)
+    pub fn find_midi_triggers(&self, port: &str, control: MidiControl, channel: u8) -> Vec<MidiTriggerRef> {
+        match self.midi_lookup_table.get(&control) {
+            Some(trigger_refs) => trigger_refs
+                .iter()
+                .filter(|trigger_ref| {
+                    let trigger = self.midi_trigger(**trigger_ref);
+                    trigger.channel.map_or(true, |trigger_channel| trigger_channel == channel)
+                        && trigger.port.as_ref().map_or(true, |pattern| name_matches(pattern, port))
+                })
+                .copied()
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn midi_trigger(&self, trigger_ref: MidiTriggerRef) -> &MidiTrigger {
+        self.switch(trigger_ref.switch_ref).midi_fields()[trigger_ref.trigger_index].1
+    }
+
+    /// The port patterns of all MIDI triggers, `*` for a trigger without a port. Empty if no
+    /// switch is triggered by MIDI, in which case no port has to be connected.
+    pub fn midi_port_patterns(&self) -> Vec<&str> {
+        let mut patterns: Vec<&str> = Vec::new();
+        for switch_config in &self.switches {
+            for (_field, trigger) in switch_config.midi_fields() {
+                let pattern = trigger.port.as_deref().unwrap_or("*");
+                if !patterns.contains(&pattern) {
+                    patterns.push(pattern);
+                }
+            }
+        }
+        patterns
+    }
 
-        match switch_ref {
-            Some(switch_ref) => Some(&self.switches[switch_ref.switch_index]),
-            None => None,
+    /// All selectors used by gamepad bindings that must be matched against the connected devices
+    pub fn gamepad_selectors(&self) -> Vec<&GamepadSelector> {
+        let mut selectors: Vec<&GamepadSelector> = Vec::new();
+        let keys = self.gamepad_button_to_switch_lookup_table.keys().chain(self.gamepad_axis_lookup_table.keys());
+        for selector in keys {
+            if *selector != GamepadSelector::Any && !selectors.contains(&selector) {
+                selectors.push(selector);
+            }
         }
+        selectors
     }
 
     pub fn find_switch_play_for_sample(&self, bank_sample_ref: BankSampleRef) -> &Vec<SwitchRef> {
@@ -652,16 +2205,32 @@ impl Config {
         let sample = bank.sample(bank_sample_ref.sample);
         (bank, sample)
     }
+
+    /// The directory that recordings are written to, also if `recording` has not been configured
+    pub fn recording_directory(&self) -> PathBuf {
+        let directory = self.recording.as_ref()
+            .map_or_else(default_recording_directory, |recording| recording.directory.clone());
+
+        match &self.resolve_path {
+            Some(resolve_path) => resolve_path.join(directory),
+            None => directory,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::config::{Config, BankConfig, BankRef, SampleConfig, SampleRef, BankSampleRef, SwitchConfig, SwitchRef, SwitchPlay, SwitchPlayRandom, SwitchPlayStep, Gamepad};
+    use crate::config::{Config, BankConfig, BankRef, SampleConfig, SampleRef, BankSampleRef, SwitchConfig, SwitchRef, SwitchPlay, SwitchPlayRandom, SwitchPlayStep, Gamepad, GamepadSelector, KeyBinding, InputFilterConfig, ScanAction, ScanMode, ScanningConfig, AudioConfig, AudioOutputKind, MasterConfig, Gain};
     use std::path::{PathBuf};
-    use gilrs::Button;
-    use iced::keyboard::KeyCode;
+    use gilrs::{Axis, Button};
+    use iced::keyboard::{KeyCode, Modifiers};
+    use iced::mouse;
     use pretty_assertions::{assert_eq};
-    use crate::error::ConfigError;
+    use crate::error::{ConfigError, ConfigWarning, LocatedConfigWarning};
+    use crate::yaml_location::Location;
+    use crate::gesture::{Gesture, GestureTiming};
+    use crate::input_filter::InputFilterSettings;
+    use std::time::Duration;
 
     fn test_path(extra_parts: &[&str]) -> PathBuf {
         let path_sep = std::path::MAIN_SEPARATOR.to_string();
@@ -736,42 +2305,56 @@ switches:
                 BankConfig {
                     id: "bankA".to_string(),
                     poly: true,
+                    volume: None,
+                    pan: None,
                     samples: vec![
                         SampleConfig {
                             id: "foo1".to_string(),
                             file: PathBuf::from("foo1.mp3"),
+                            volume: None,
+                            pan: None,
                             bank_sample_ref: BankSampleRef {
                                 bank: BankRef { bank_index: 0 },
                                 sample: SampleRef { sample_index: 0 },
                             },
                             file_resolved: Some(test_path(&["foo1.mp3"])),
+                            gain: Gain::default(),
                         },
                         SampleConfig {
                             id: "foo2".to_string(),
                             file: PathBuf::from("foo2.wav"),
+                            volume: None,
+                            pan: None,
                             bank_sample_ref: BankSampleRef {
                                 bank: BankRef { bank_index: 0 },
                                 sample: SampleRef { sample_index: 1 },
                             },
                             file_resolved: Some(test_path(&["foo2.wav"])),
+                            gain: Gain::default(),
                         },
                         SampleConfig {
                             id: "foo3".to_string(),
                             file: PathBuf::from("foo3.ogg"),
+                            volume: None,
+                            pan: None,
                             bank_sample_ref: BankSampleRef {
                                 bank: BankRef { bank_index: 0 },
                                 sample: SampleRef { sample_index: 2 },
                             },
                             file_resolved: Some(test_path(&["foo3.ogg"])),
+                            gain: Gain::default(),
                         },
                         SampleConfig {
                             id: "foo4".to_string(),
                             file: PathBuf::from("foo4.flac"),
+                            volume: None,
+                            pan: None,
                             bank_sample_ref: BankSampleRef {
                                 bank: BankRef { bank_index: 0 },
                                 sample: SampleRef { sample_index: 3 },
                             },
                             file_resolved: Some(test_path(&["foo4.flac"])),
+                            gain: Gain::default(),
                         },
                     ],
                     bank_ref: BankRef { bank_index: 0 },
@@ -779,15 +2362,20 @@ switches:
                 BankConfig {
                     id: "bankB".to_string(),
                     poly: false,
+                    volume: None,
+                    pan: None,
                     samples: vec![
                         SampleConfig {
                             id: "foo1".to_string(),
                             file: PathBuf::from("foo1-bankB.mp3"),
+                            volume: None,
+                            pan: None,
                             bank_sample_ref: BankSampleRef {
                                 bank: BankRef { bank_index: 1 },
                                 sample: SampleRef { sample_index: 0 },
                             },
                             file_resolved: Some(test_path(&["foo1-bankB.mp3"])),
+                            gain: Gain::default(),
                         },
                     ],
                     bank_ref: BankRef { bank_index: 1 },
@@ -797,7 +2385,16 @@ switches:
                 SwitchConfig {
                     title: "play option".to_string(),
                     key: None,
+                    keys: vec![],
                     gamepad: None,
+                    gamepads: vec![],
+                    gamepad_axis: None,
+                    gamepad_axes: vec![],
+                    mouse: None,
+                    mouse_buttons: vec![],
+                    midi: None,
+                    midi_messages: vec![],
+                    shared_binding: false,
                     stop_sounds: false,
                     play: Some(SwitchPlay {
                         bank: "bankB".to_string(),
@@ -809,13 +2406,34 @@ switches:
                     }),
                     play_random: None,
                     play_step: None,
+                    midi_out: None,
+                    scan: None,
+                    on_release: None,
+                    hold_to_play: false,
+                    fade_out_ms: 0,
+                    short_press: None,
+                    long_press: None,
+                    long_press_ms: 800,
+                    double_press: None,
+                    double_press_ms: 400,
+                    input_filter: InputFilterConfig::default(),
                     switch_ref: SwitchRef { switch_index: 0 },
-                    key_code: None
+                    key_bindings: vec![],
+                    iced_mouse_buttons: vec![],
                 },
                 SwitchConfig {
                     title: "playRandom option".to_string(),
                     key: None,
+                    keys: vec![],
                     gamepad: None,
+                    gamepads: vec![],
+                    gamepad_axis: None,
+                    gamepad_axes: vec![],
+                    mouse: None,
+                    mouse_buttons: vec![],
+                    midi: None,
+                    midi_messages: vec![],
+                    shared_binding: false,
                     stop_sounds: false,
                     play: None,
                     play_random: Some(
@@ -825,13 +2443,34 @@ switches:
                         },
                     ),
                     play_step: None,
+                    midi_out: None,
+                    scan: None,
+                    on_release: None,
+                    hold_to_play: false,
+                    fade_out_ms: 0,
+                    short_press: None,
+                    long_press: None,
+                    long_press_ms: 800,
+                    double_press: None,
+                    double_press_ms: 400,
+                    input_filter: InputFilterConfig::default(),
                     switch_ref: SwitchRef { switch_index: 1 },
-                    key_code: None,
+                    key_bindings: vec![],
+                    iced_mouse_buttons: vec![],
                 },
                 SwitchConfig {
                     title: "playStep option".to_string(),
                     key: None,
+                    keys: vec![],
                     gamepad: None,
+                    gamepads: vec![],
+                    gamepad_axis: None,
+                    gamepad_axes: vec![],
+                    mouse: None,
+                    mouse_buttons: vec![],
+                    midi: None,
+                    midi_messages: vec![],
+                    shared_binding: false,
                     stop_sounds: false,
                     play: None,
                     play_random: None,
@@ -842,80 +2481,214 @@ switches:
                             bank_ref: BankRef { bank_index: 0 },
                         },
                     ),
+                    midi_out: None,
+                    scan: None,
+                    on_release: None,
+                    hold_to_play: false,
+                    fade_out_ms: 0,
+                    short_press: None,
+                    long_press: None,
+                    long_press_ms: 800,
+                    double_press: None,
+                    double_press_ms: 400,
+                    input_filter: InputFilterConfig::default(),
                     switch_ref: SwitchRef { switch_index: 2 },
-                    key_code: None,
+                    key_bindings: vec![],
+                    iced_mouse_buttons: vec![],
                 },
                 SwitchConfig {
                     title: "stopSounds option".to_string(),
                     key: None,
+                    keys: vec![],
                     gamepad: None,
+                    gamepads: vec![],
+                    gamepad_axis: None,
+                    gamepad_axes: vec![],
+                    mouse: None,
+                    mouse_buttons: vec![],
+                    midi: None,
+                    midi_messages: vec![],
+                    shared_binding: false,
                     stop_sounds: true,
                     play: None,
                     play_random: None,
                     play_step: None,
+                    midi_out: None,
+                    scan: None,
+                    on_release: None,
+                    hold_to_play: false,
+                    fade_out_ms: 0,
+                    short_press: None,
+                    long_press: None,
+                    long_press_ms: 800,
+                    double_press: None,
+                    double_press_ms: 400,
+                    input_filter: InputFilterConfig::default(),
                     switch_ref: SwitchRef { switch_index: 3 },
-                    key_code: None,
+                    key_bindings: vec![],
+                    iced_mouse_buttons: vec![],
                 },
                 SwitchConfig {
                     title: "Only the required fields".to_string(),
                     key: None,
+                    keys: vec![],
                     gamepad: None,
+                    gamepads: vec![],
+                    gamepad_axis: None,
+                    gamepad_axes: vec![],
+                    mouse: None,
+                    mouse_buttons: vec![],
+                    midi: None,
+                    midi_messages: vec![],
+                    shared_binding: false,
                     stop_sounds: false,
                     play: None,
                     play_random: None,
                     play_step: None,
+                    midi_out: None,
+                    scan: None,
+                    on_release: None,
+                    hold_to_play: false,
+                    fade_out_ms: 0,
+                    short_press: None,
+                    long_press: None,
+                    long_press_ms: 800,
+                    double_press: None,
+                    double_press_ms: 400,
+                    input_filter: InputFilterConfig::default(),
                     switch_ref: SwitchRef { switch_index: 4 },
-                    key_code: None,
+                    key_bindings: vec![],
+                    iced_mouse_buttons: vec![],
                 },
                 SwitchConfig {
                     title: "keyboard key".to_string(),
                     key: Some("X".to_string()),
+                    keys: vec![],
                     gamepad: None,
+                    gamepads: vec![],
+                    gamepad_axis: None,
+                    gamepad_axes: vec![],
+                    mouse: None,
+                    mouse_buttons: vec![],
+                    midi: None,
+                    midi_messages: vec![],
+                    shared_binding: false,
                     stop_sounds: false,
                     play: None,
                     play_random: None,
                     play_step: None,
+                    midi_out: None,
+                    scan: None,
+                    on_release: None,
+                    hold_to_play: false,
+                    fade_out_ms: 0,
+                    short_press: None,
+                    long_press: None,
+                    long_press_ms: 800,
+                    double_press: None,
+                    double_press_ms: 400,
+                    input_filter: InputFilterConfig::default(),
                     switch_ref: SwitchRef { switch_index: 5 },
-                    key_code: Some(KeyCode::X),
+                    key_bindings: vec![KeyBinding { key_code: KeyCode::X, modifiers: Modifiers::empty() }],
+                    iced_mouse_buttons: vec![],
                 },
                 SwitchConfig {
                     title: "gamepad button on any device".to_string(),
                     key: None,
+                    keys: vec![],
                     gamepad: Some(
                         Gamepad {
                             device_id: None,
+                            uuid: None,
+                            name: None,
+                            index: None,
                             button: "North".to_string(),
                             gilrs_button: Button::North,
+                            selector: GamepadSelector::Any,
                         },
                     ),
+                    gamepads: vec![],
+                    gamepad_axis: None,
+                    gamepad_axes: vec![],
+                    mouse: None,
+                    mouse_buttons: vec![],
+                    midi: None,
+                    midi_messages: vec![],
+                    shared_binding: false,
                     stop_sounds: false,
                     play: None,
                     play_random: None,
                     play_step: None,
+                    midi_out: None,
+                    scan: None,
+                    on_release: None,
+                    hold_to_play: false,
+                    fade_out_ms: 0,
+                    short_press: None,
+                    long_press: None,
+                    long_press_ms: 800,
+                    double_press: None,
+                    double_press_ms: 400,
+                    input_filter: InputFilterConfig::default(),
                     switch_ref: SwitchRef { switch_index: 6 },
-                    key_code: None,
+                    key_bindings: vec![],
+                    iced_mouse_buttons: vec![],
                 },
                 SwitchConfig {
                     title: "gamepad button, specific device".to_string(),
                     key: None,
+                    keys: vec![],
                     gamepad: Some(
                         Gamepad {
                             device_id: Some(123),
+                            uuid: None,
+                            name: None,
+                            index: None,
                             button: "North".to_string(),
                             gilrs_button: Button::North,
+                            selector: GamepadSelector::DeviceId(123),
                         },
                     ),
+                    gamepads: vec![],
+                    gamepad_axis: None,
+                    gamepad_axes: vec![],
+                    mouse: None,
+                    mouse_buttons: vec![],
+                    midi: None,
+                    midi_messages: vec![],
+                    shared_binding: false,
                     stop_sounds: false,
                     play: None,
                     play_random: None,
                     play_step: None,
+                    midi_out: None,
+                    scan: None,
+                    on_release: None,
+                    hold_to_play: false,
+                    fade_out_ms: 0,
+                    short_press: None,
+                    long_press: None,
+                    long_press_ms: 800,
+                    double_press: None,
+                    double_press_ms: 400,
+                    input_filter: InputFilterConfig::default(),
                     switch_ref: SwitchRef { switch_index: 7 },
-                    key_code: None,
+                    key_bindings: vec![],
+                    iced_mouse_buttons: vec![],
                 },
                 SwitchConfig {
                     title: "another play option, same actions as the first".to_string(),
                     key: None,
+                    keys: vec![],
                     gamepad: None,
+                    gamepads: vec![],
+                    gamepad_axis: None,
+                    gamepad_axes: vec![],
+                    mouse: None,
+                    mouse_buttons: vec![],
+                    midi: None,
+                    midi_messages: vec![],
+                    shared_binding: false,
                     stop_sounds: false,
                     play: Some(SwitchPlay {
                         bank: "bankB".to_string(),
@@ -927,113 +2700,845 @@ switches:
                     }),
                     play_random: None,
                     play_step: None,
+                    midi_out: None,
+                    scan: None,
+                    on_release: None,
+                    hold_to_play: false,
+                    fade_out_ms: 0,
+                    short_press: None,
+                    long_press: None,
+                    long_press_ms: 800,
+                    double_press: None,
+                    double_press_ms: 400,
+                    input_filter: InputFilterConfig::default(),
                     switch_ref: SwitchRef { switch_index: 8 },
-                    key_code: None
+                    key_bindings: vec![],
+                    iced_mouse_buttons: vec![],
                 },
             ],
+            input_filter: InputFilterConfig::default(),
+            scanning: None,
+            ignore_gui_clicks: true,
+            osc: None,
+            http: None,
+            session_log: None,
+            recording: None,
+            audio: AudioConfig::default(),
+            master: MasterConfig::default(),
             resolve_path: Some(test_path(&[])),
 
-            keyboard_key_to_switch_lookup_table: vec![
-                (KeyCode::X, SwitchRef { switch_index: 5 }),
-            ].into_iter().collect(),
+            warnings: vec![
+                LocatedConfigWarning {
+                    path: "switches[7].gamepad".to_string(),
+                    location: Some(Location { line: 52, column: 5 }),
+                    warning: ConfigWarning::ShadowedGamepadBinding {
+                        button: "North".to_string(),
+                        device: "device 123".to_string(),
+                        other_switch: "gamepad button on any device".to_string(),
+                    },
+                },
+            ],
+
+            keyboard_key_to_switch_lookup_table: vec![
+                (KeyBinding { key_code: KeyCode::X, modifiers: Modifiers::empty() }, vec![SwitchRef { switch_index: 5 }]),
+            ].into_iter().collect(),
+
+            gamepad_button_to_switch_lookup_table: vec![
+                (
+                    GamepadSelector::Any,
+                    vec![
+                        (Button::North, vec![SwitchRef { switch_index: 6 }]),
+                    ].into_iter().collect(),
+                ),
+                (
+                    GamepadSelector::DeviceId(123),
+                    vec![
+                        (Button::North, vec![SwitchRef { switch_index: 7 }]),
+                    ].into_iter().collect(),
+                ),
+            ].into_iter().collect(),
+
+            gamepad_axis_lookup_table: vec![].into_iter().collect(),
+
+            mouse_button_to_switch_lookup_table: vec![].into_iter().collect(),
+
+            midi_lookup_table: vec![].into_iter().collect(),
+
+            sample_to_switch_play: vec![
+                (
+                    BankSampleRef {
+                        bank: BankRef { bank_index: 1 },
+                        sample: SampleRef { sample_index: 0 },
+                    },
+                    vec![
+                        SwitchRef { switch_index: 0 },
+                        SwitchRef { switch_index: 8 },
+                    ],
+                )
+            ].into_iter().collect(),
+            empty_switch_ref_vec: vec![],
+        });
+    }
+
+    fn titles(config: &Config, switch_refs: &[SwitchRef]) -> Vec<String> {
+        switch_refs
+            .iter()
+            .map(|switch_ref| config.switch(*switch_ref).title.clone())
+            .collect()
+    }
+
+    #[test]
+    fn find_switches_for_keyboard_key() {
+        let config_source = r###"
+banks: []
+switches:
+  - title: Key a
+    key: A
+
+  - title: Key b
+    key: B
+    sharedBinding: true
+
+  # should be triggered together with the previous switch
+  - title: Key b (shared)
+    key: B
+    sharedBinding: true
+
+  - title: No key
+"###;
+
+        let config = Config::from_string(config_source, Some(test_path(&[]))).unwrap();
+        let find = |key_code| titles(&config, config.find_switches_for_keyboard_key(key_code, Modifiers::empty()));
+        assert!(find(KeyCode::Z).is_empty());
+        assert_eq!(find(KeyCode::A), vec!["Key a"]);
+        assert_eq!(find(KeyCode::B), vec!["Key b", "Key b (shared)"]);
+    }
+
+    #[test]
+    fn find_switches_for_keyboard_key_with_modifiers() {
+        let config_source = r###"
+banks: []
+switches:
+  - title: A
+    key: A
+
+  - title: Ctrl+A
+    key: Ctrl+A
+
+  - title: Ctrl+Shift+A
+    keys: ["Shift+Ctrl+A"]
+
+  - title: Left shift
+    key: LShift
+
+  - title: Ctrl+Plus
+    key: Ctrl + Plus
+"###;
+        let config = Config::from_string(config_source, Some(test_path(&[]))).unwrap();
+        let find = |key_code, modifiers| titles(&config, config.find_switches_for_keyboard_key(key_code, modifiers));
+
+        assert_eq!(find(KeyCode::A, Modifiers::empty()), vec!["A"]);
+        assert_eq!(find(KeyCode::A, Modifiers::CTRL), vec!["Ctrl+A"]);
+        assert_eq!(find(KeyCode::A, Modifiers::CTRL | Modifiers::SHIFT), vec!["Ctrl+Shift+A"]);
+        assert!(find(KeyCode::A, Modifiers::ALT).is_empty());
+        assert!(find(KeyCode::A, Modifiers::CTRL | Modifiers::ALT).is_empty());
+
+        // pressing a modifier key activates its own modifier
+        assert_eq!(find(KeyCode::LShift, Modifiers::SHIFT), vec!["Left shift"]);
+        assert!(find(KeyCode::LShift, Modifiers::SHIFT | Modifiers::CTRL).is_empty());
+
+        assert_eq!(find(KeyCode::Plus, Modifiers::CTRL), vec!["Ctrl+Plus"]);
+    }
+
+    #[test]
+    fn config_with_invalid_keyboard_modifier() {
+        let config_source = r###"
+banks: []
+switches:
+  - title: Invalid modifier
+    key: Hyper+A
+"###;
+        let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "switches[0].key");
+        match &errors[0].source {
+            ConfigError::UnknownKeyboardModifier { modifier, .. } => {
+                assert_eq!(modifier.as_str(), "Hyper");
+            }
+            _ => {
+                panic!("Expected error to be ConfigError::UnknownKeyboardModifier");
+            }
+        }
+    }
+
+    #[test]
+    fn find_switches_for_gamepad_button() {
+        let config_source = r###"
+banks: []
+switches:
+  - title: South, no device filter
+    gamepad:
+      button: South
+    sharedBinding: true
+
+  # should be triggered together with the previous switch
+  - title: South, no device filter (shared)
+    gamepad:
+      button: South
+    sharedBinding: true
+
+  - title: North, device 123
+    gamepad:
+      deviceId: 123
+      button: North
+
+  # Should have less priority because it specifies no device
+  - title: North, no device filter
+    gamepad:
+      button: North
+
+  # The only entry that specifies West
+  - title: West, device 123
+    gamepad:
+      deviceId: 123
+      button: West
+"###;
+        let config = Config::from_string(config_source, Some(test_path(&[]))).unwrap();
+        let find = |device_id, button| titles(&config, &config.find_switches_for_gamepad_button(&[GamepadSelector::DeviceId(device_id)], button));
+
+        assert!(find(10, Button::West).is_empty());
+        assert_eq!(find(123, Button::West), vec!["West, device 123"]);
+
+        assert_eq!(find(123, Button::South), vec!["South, no device filter", "South, no device filter (shared)"]);
+
+        assert_eq!(find(123, Button::North), vec!["North, device 123"]);
+        assert_eq!(find(456, Button::North), vec!["North, no device filter"]);
+
+        // Start button is not specified at all
+        assert!(find(10, Button::Start).is_empty());
+    }
+
+    #[test]
+    fn switch_with_multiple_triggers() {
+        let config_source = r###"
+banks: []
+switches:
+  - title: Many triggers
+    key: A
+    keys: [B, C]
+    gamepad:
+      button: South
+    gamepads:
+      - button: North
+      - deviceId: 123
+        button: West
+    mouse: Left
+    mouseButtons: [Right]
+
+  - title: Middle mouse button
+    mouseButtons: [Middle]
+
+  - title: Back and forward
+    mouseButtons: [Back, Forward]
+"###;
+        let config = Config::from_string(config_source, Some(test_path(&[]))).unwrap();
+        let find_key = |key_code| titles(&config, config.find_switches_for_keyboard_key(key_code, Modifiers::empty()));
+        let find_gamepad = |device_id, button| titles(&config, &config.find_switches_for_gamepad_button(&[GamepadSelector::DeviceId(device_id)], button));
+        let find_mouse = |button| titles(&config, config.find_switches_for_mouse_button(button));
+
+        assert_eq!(find_key(KeyCode::A), vec!["Many triggers"]);
+        assert_eq!(find_key(KeyCode::B), vec!["Many triggers"]);
+        assert_eq!(find_key(KeyCode::C), vec!["Many triggers"]);
+        assert!(find_key(KeyCode::D).is_empty());
+
+        assert_eq!(find_gamepad(1, Button::South), vec!["Many triggers"]);
+        assert_eq!(find_gamepad(1, Button::North), vec!["Many triggers"]);
+        assert_eq!(find_gamepad(123, Button::West), vec!["Many triggers"]);
+        assert!(find_gamepad(1, Button::West).is_empty());
+
+        assert_eq!(find_mouse(mouse::Button::Left), vec!["Many triggers"]);
+        assert_eq!(find_mouse(mouse::Button::Right), vec!["Many triggers"]);
+        assert_eq!(find_mouse(mouse::Button::Middle), vec!["Middle mouse button"]);
+        assert_eq!(find_mouse(mouse::Button::Other(8)), vec!["Back and forward"]);
+        assert_eq!(find_mouse(mouse::Button::Other(9)), vec!["Back and forward"]);
+        assert!(find_mouse(mouse::Button::Other(10)).is_empty());
+    }
+
+    #[test]
+    fn switch_with_invalid_and_conflicting_triggers() {
+        let config_source = r###"
+banks: []
+switches:
+  - title: First
+    keys: [A, NotAKey]
+    mouse: Left
+
+  # Listing the same key twice within a switch is fine
+  - title: Second
+    keys: [B, B]
+    mouseButtons: [Sideways, Left]
+"###;
+        let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+        let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(paths, vec!["switches[0].keys[1]", "switches[1].mouseButtons[0]", "switches[1].mouseButtons[1]"]);
+        assert!(matches!(errors[0].source, ConfigError::UnknownKeyboardButton { .. }));
+        assert!(matches!(errors[1].source, ConfigError::UnknownMouseButton { .. }));
+        assert!(matches!(errors[2].source, ConfigError::ConflictingBinding { .. }));
+    }
+
+    #[test]
+    fn find_switches_for_gamepad_selected_by_name_or_uuid() {
+        let config_source = r###"
+banks: []
+switches:
+  - title: Any Xbox controller
+    gamepad:
+      name: Xbox*
+      button: South
+
+  - title: Second Xbox controller
+    gamepad:
+      name: Xbox*
+      index: 1
+      button: North
+
+  - title: By uuid
+    gamepad:
+      uuid: 03000000-5e04-0000-0a0b-000000000000
+      button: North
+
+  - title: Any device
+    gamepad:
+      button: West
+"###;
+        let config = Config::from_string(config_source, Some(test_path(&[]))).unwrap();
+        let any_xbox = GamepadSelector::Name { pattern: "Xbox*".to_string(), index: None };
+        let second_xbox = GamepadSelector::Name { pattern: "Xbox*".to_string(), index: Some(1) };
+        let uuid = GamepadSelector::Uuid("030000005e0400000a0b000000000000".to_string());
+
+        let mut selectors = config.gamepad_selectors();
+        selectors.sort_by_key(|selector| selector.to_string());
+        assert_eq!(selectors, vec![&second_xbox, &uuid, &any_xbox]);
+
+        let find = |selectors: &[GamepadSelector], button| titles(&config, &config.find_switches_for_gamepad_button(selectors, button));
+        assert_eq!(find(&[any_xbox.clone(), second_xbox.clone()], Button::South), vec!["Any Xbox controller"]);
+        assert_eq!(find(&[any_xbox.clone(), second_xbox.clone()], Button::North), vec!["Second Xbox controller"]);
+        assert!(find(std::slice::from_ref(&any_xbox), Button::North).is_empty());
+        assert_eq!(find(std::slice::from_ref(&uuid), Button::North), vec!["By uuid"]);
+        assert_eq!(find(&[uuid], Button::West), vec!["Any device"]);
+        assert!(find(&[], Button::South).is_empty());
+    }
+
+    #[test]
+    fn config_with_invalid_gamepad_selector() {
+        let config_source = r###"
+banks: []
+switches:
+  - title: Two selectors
+    gamepad:
+      deviceId: 1
+      name: Xbox*
+      button: South
+
+  - title: Invalid uuid
+    gamepads:
+      - uuid: not-a-uuid
+        button: North
+"###;
+        let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+        let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(paths, vec!["switches[0].gamepad", "switches[1].gamepads[0].uuid"]);
+        assert!(matches!(errors[0].source, ConfigError::AmbiguousGamepadSelector));
+        assert!(matches!(errors[1].source, ConfigError::InvalidGamepadUuid { .. }));
+    }
 
-            gamepad_button_to_switch_lookup_table: vec![
-                (
-                    None,
-                    vec![
-                        (Button::North, SwitchRef { switch_index: 6 }),
-                    ].into_iter().collect(),
-                ),
-                (
-                    Some(123),
-                    vec![
-                        (Button::North, SwitchRef { switch_index: 7 }),
-                    ].into_iter().collect(),
-                ),
-            ].into_iter().collect(),
+    #[test]
+    fn gamepad_axis_triggers() {
+        let config_source = r###"
+banks: []
+switches:
+  - title: Puff
+    gamepadAxis:
+      axis: LeftStickY
+      threshold: 0.6
+
+  - title: Sip
+    gamepadAxes:
+      - axis: LeftStickY
+        direction: Negative
+        hysteresis: 0.2
+      - name: Xbox*
+        axis: RightZ
+"###;
+        let config = Config::from_string(config_source, Some(test_path(&[]))).unwrap();
+        let xbox = GamepadSelector::Name { pattern: "Xbox*".to_string(), index: None };
+        let find = |selectors: &[GamepadSelector], axis| -> Vec<String> {
+            config.find_gamepad_axis_triggers(selectors, axis)
+                .into_iter()
+                .map(|axis_ref| format!("{} {}", config.switch(axis_ref.switch_ref).title, config.gamepad_axis(axis_ref).axis))
+                .collect()
+        };
 
-            sample_to_switch_play: vec![
-                (
-                    BankSampleRef {
-                        bank: BankRef { bank_index: 1 },
-                        sample: SampleRef { sample_index: 0 },
-                    },
-                    vec![
-                        SwitchRef { switch_index: 0 },
-                        SwitchRef { switch_index: 8 },
-                    ],
-                )
-            ].into_iter().collect(),
-            empty_switch_ref_vec: vec![],
-        });
+        assert_eq!(find(&[], Axis::LeftStickY), vec!["Puff LeftStickY", "Sip LeftStickY"]);
+        assert!(find(&[], Axis::RightZ).is_empty());
+        assert_eq!(find(std::slice::from_ref(&xbox), Axis::RightZ), vec!["Sip RightZ"]);
+        assert_eq!(config.gamepad_selectors(), vec![&xbox]);
+
+        let puff = config.switches[0].gamepad_axis.as_ref().unwrap();
+        assert!(!puff.is_held(false, 0.59));
+        assert!(puff.is_held(false, 0.6));
+        assert!(puff.is_held(true, 0.51));
+        assert!(!puff.is_held(true, 0.5));
+        assert!(!puff.is_held(false, -1.0));
+
+        let sip = &config.switches[1].gamepad_axes[0];
+        assert!(!sip.is_held(false, 0.8));
+        assert!(sip.is_held(false, -0.5));
+        assert!(sip.is_held(true, -0.31));
+        assert!(!sip.is_held(true, -0.3));
     }
 
     #[test]
-    fn find_switch_for_keyboard_key() {
+    fn config_with_invalid_gamepad_axis() {
         let config_source = r###"
 banks: []
 switches:
-  - title: Key a
-    key: A
+  - title: Invalid axis
+    gamepadAxis:
+      axis: Sideways
+      threshold: 1.5
+      hysteresis: 2
+"###;
+        let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+        let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(paths, vec!["switches[0].gamepadAxis.axis", "switches[0].gamepadAxis.threshold", "switches[0].gamepadAxis.hysteresis"]);
+        assert!(matches!(errors[0].source, ConfigError::UnknownGamepadAxis { .. }));
+        assert!(matches!(errors[1].source, ConfigError::InvalidGamepadAxisThreshold { .. }));
+        assert!(matches!(errors[2].source, ConfigError::InvalidGamepadAxisHysteresis { .. }));
+    }
 
-  # should override the first switch
-  - title: Key a (again)
-    key: A
+    #[test]
+    fn config_with_invalid_midi_triggers() {
+        let config_source = r###"
+banks: []
+switches:
+  - title: Invalid MIDI
+    midi: { channel: 17, note: 128 }
+    midiMessages:
+      - note: 60
+        cc: 1
+      - cc: 1
+        threshold: 0
+
+  - title: Invalid MIDI output
+    midiOut: { channel: 0, cc: 7, value: 200 }
+"###;
+        let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+        let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(paths, vec!["switches[0].midi.channel", "switches[0].midi.note", "switches[0].midiMessages[0]", "switches[0].midiMessages[1].threshold", "switches[1].midiOut.channel", "switches[1].midiOut.value"]);
+        assert!(matches!(errors[0].source, ConfigError::InvalidMidiChannel { channel: 17 }));
+        assert!(matches!(errors[1].source, ConfigError::InvalidMidiNumber { number: 128 }));
+        assert!(matches!(errors[2].source, ConfigError::AmbiguousMidiControl));
+        assert!(matches!(errors[3].source, ConfigError::InvalidMidiThreshold { threshold: 0 }));
+        assert!(matches!(errors[4].source, ConfigError::InvalidMidiChannel { channel: 0 }));
+        assert!(matches!(errors[5].source, ConfigError::InvalidMidiValue { value: 200 }));
+    }
 
-  - title: Key b
-    key: B
+    #[test]
+    fn switch_with_release_actions() {
+        let config_source = r###"
+banks:
+  - id: notes
+    samples:
+      - id: c
+        file: c.flac
+      - id: d
+        file: d.flac
+switches:
+  - title: Hold to play
+    play:
+      bank: notes
+      sample: c
+    holdToPlay: true
+    fadeOutMs: 250
+    onRelease:
+      play:
+        bank: notes
+        sample: d
+
+  - title: Invalid release action
+    onRelease:
+      stopSounds: true
+      playStep:
+        bank: invalid
+        steps: 1
+"###;
+        let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+        let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(paths, vec!["switches[1].onRelease.playStep.bank"]);
+
+        let config_source = config_source.replace("bank: invalid", "bank: notes");
+        let config = Config::from_string(&config_source, Some(test_path(&[]))).unwrap();
+
+        let hold = &config.switches[0];
+        assert!(hold.hold_to_play);
+        assert_eq!(hold.fade_out_ms, 250);
+        assert_eq!(hold.press_actions().play.unwrap().bank_sample_ref.sample, SampleRef { sample_index: 0 });
+        let release_actions = hold.release_actions().unwrap();
+        assert!(!release_actions.stop_sounds);
+        assert_eq!(release_actions.play.unwrap().bank_sample_ref.sample, SampleRef { sample_index: 1 });
+
+        let invalid = &config.switches[1];
+        assert!(invalid.press_actions().play.is_none());
+        let release_actions = invalid.release_actions().unwrap();
+        assert!(release_actions.stop_sounds);
+        assert_eq!(release_actions.play_step.unwrap().bank_ref, BankRef { bank_index: 0 });
+    }
 
-  - title: No key
+    #[test]
+    fn switch_with_gestures() {
+        let config_source = r###"
+banks:
+  - id: notes
+    samples:
+      - id: c
+        file: c.flac
+switches:
+  - title: No gestures
+    play:
+      bank: notes
+      sample: c
+
+  - title: Gestures
+    shortPress:
+      play:
+        bank: notes
+        sample: c
+    longPress:
+      stopSounds: true
+    longPressMs: 1000
+    doublePress:
+      playRandom:
+        bank: invalid
+"###;
+        let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+        let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(paths, vec!["switches[1].doublePress.playRandom.bank"]);
+
+        let config_source = config_source.replace("bank: invalid", "bank: notes");
+        let config = Config::from_string(&config_source, Some(test_path(&[]))).unwrap();
+
+        assert_eq!(config.switches[0].gesture_timing(), None);
+        assert_eq!(config.switches[1].gesture_timing(), Some(GestureTiming {
+            long_press: Some(Duration::from_millis(1000)),
+            double_press: Some(Duration::from_millis(400)),
+        }));
+
+        let gestures = &config.switches[1];
+        assert!(gestures.gesture_actions(Gesture::Short).unwrap().play.is_some());
+        assert!(gestures.gesture_actions(Gesture::Long).unwrap().stop_sounds);
+        assert!(gestures.gesture_actions(Gesture::Double).unwrap().play_random.is_some());
+        assert!(config.switches[0].gesture_actions(Gesture::Short).is_none());
+    }
+
+    #[test]
+    fn switch_with_gestures_and_press_actions() {
+        let config_source = r###"
+banks:
+  - id: notes
+    samples:
+      - id: c
+        file: c.flac
+switches:
+  - title: Both
+    stopSounds: true
+    play:
+      bank: notes
+      sample: c
+    longPress:
+      stopSounds: true
 "###;
+        let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+        let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(paths, vec!["switches[0].stopSounds", "switches[0].play"]);
+    }
+
+    #[test]
+    fn input_filter_settings() {
+        let config_source = r###"
+banks: []
+inputFilter:
+  debounceMs: 30
+  cooldownMs: 250
+switches:
+  - title: Global filters
 
+  - title: Overridden filters
+    inputFilter:
+      minHoldMs: 100
+      cooldownMs: 0
+"###;
         let config = Config::from_string(config_source, Some(test_path(&[]))).unwrap();
-        assert!(config.find_switch_for_keyboard_key(KeyCode::Z).is_none());
-        assert_eq!(config.find_switch_for_keyboard_key(KeyCode::A).unwrap().title, "Key a (again)");
-        assert_eq!(config.find_switch_for_keyboard_key(KeyCode::B).unwrap().title, "Key b");
+        let ms = Duration::from_millis;
+
+        assert_eq!(config.input_filter_settings(SwitchRef { switch_index: 0 }), InputFilterSettings {
+            debounce: ms(30),
+            min_hold: ms(0),
+            cooldown: ms(250),
+        });
+        assert_eq!(config.input_filter_settings(SwitchRef { switch_index: 1 }), InputFilterSettings {
+            debounce: ms(30),
+            min_hold: ms(100),
+            cooldown: ms(0),
+        });
     }
 
     #[test]
-    fn find_switch_for_gamepad_button() {
+    fn scanning() {
         let config_source = r###"
 banks: []
+scanning:
+  mode: step
+  columns: 3
 switches:
-  - title: South, no device filter
-    gamepad:
-      button: South
+  - title: Select
+    key: Space
+    scan: select
 
-  # should override the first switch
-  - title: South, no device filter (again)
-    gamepad:
-      button: South
+  - title: Step
+    key: Enter
+    shortPress:
+      scan: step
 
-  - title: North, device 123
-    gamepad:
-      deviceId: 123
-      button: North
+  - title: Scanned
+"###;
+        let config = Config::from_string(config_source, Some(test_path(&[]))).unwrap();
+        assert_eq!(config.scanning, Some(ScanningConfig { mode: ScanMode::Step, interval_ms: 1000, columns: 3 }));
 
-  # Should have less priority because it specifies no device
-  - title: North, no device filter
-    gamepad:
-      button: North
+        let scan_actions: Vec<bool> = config.switches.iter().map(SwitchConfig::has_scan_action).collect();
+        assert_eq!(scan_actions, vec![true, true, false]);
+        assert_eq!(config.switches[0].press_actions().scan, Some(ScanAction::Select));
+        assert_eq!(config.switches[1].gesture_actions(Gesture::Short).unwrap().scan, Some(ScanAction::Step));
+    }
 
-  # The only entry that specifies West
-  - title: West, device 123
-    gamepad:
-      deviceId: 123
-      button: West
+    #[test]
+    fn config_with_invalid_scanning() {
+        let config_source = r###"
+banks: []
+scanning:
+  intervalMs: 0
+  columns: 0
+switches: []
+"###;
+        let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+        let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(paths, vec!["scanning.intervalMs", "scanning.columns"]);
+        assert!(matches!(errors[0].source, ConfigError::InvalidScanInterval));
+        assert!(matches!(errors[1].source, ConfigError::InvalidScanColumns));
+    }
+
+    #[test]
+    fn osc() {
+        let config_source = r###"
+banks: []
+switches: []
+osc:
+  listen: "0.0.0.0:9000"
 "###;
         let config = Config::from_string(config_source, Some(test_path(&[]))).unwrap();
+        let osc = config.osc.unwrap();
+        assert_eq!(osc.listen_addr, Some("0.0.0.0:9000".parse().unwrap()));
+        assert_eq!(osc.send_addr, None);
+        assert_eq!(osc.prefix, "/microswitch");
+    }
+
+    #[test]
+    fn http() {
+        let config_source = r###"
+banks: []
+switches: []
+http: {}
+"###;
+        let config = Config::from_string(config_source, Some(test_path(&[]))).unwrap();
+        assert_eq!(config.http.unwrap().listen_addr, "127.0.0.1:8080".parse().unwrap());
+
+        let config_source = r###"
+banks: []
+switches: []
+http:
+  listen: "0.0.0.0:80"
+"###;
+        let config = Config::from_string(config_source, Some(test_path(&[]))).unwrap();
+        assert_eq!(config.http.unwrap().listen_addr, "0.0.0.0:80".parse().unwrap());
+
+        let config_source = r###"
+banks: []
+switches: []
+http:
+  listen: "0.0.0.0"
+"###;
+        let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+        assert_eq!(errors[0].path, "http.listen");
+        assert!(matches!(errors[0].source, ConfigError::InvalidSocketAddress { .. }));
+    }
+
+    #[test]
+    fn session_log() {
+        let config_source = r###"
+banks: []
+switches: []
+sessionLog:
+  directory: sessions
+"###;
+        let config = Config::from_string(config_source, Some(test_path(&[]))).unwrap();
+        assert_eq!(config.session_log.unwrap().directory_resolved, test_path(&["sessions"]));
 
-        assert!(config.find_switch_for_gamepad_button(10, Button::West).is_none());
-        assert_eq!(config.find_switch_for_gamepad_button(123, Button::West).unwrap().title, "West, device 123");
+        let config = Config::from_string(config_source, None).unwrap();
+        assert_eq!(config.session_log.unwrap().directory_resolved, PathBuf::from("sessions"));
+    }
 
-        assert_eq!(config.find_switch_for_gamepad_button(123, Button::South).unwrap().title, "South, no device filter (again)");
+    #[test]
+    fn recording() {
+        let config = Config::from_string("banks: []\nswitches: []\n", Some(test_path(&[]))).unwrap();
+        assert_eq!(config.recording, None);
+        assert_eq!(config.recording_directory(), test_path(&["recordings"]));
 
-        assert_eq!(config.find_switch_for_gamepad_button(123, Button::North).unwrap().title, "North, device 123");
-        assert_eq!(config.find_switch_for_gamepad_button(456, Button::North).unwrap().title, "North, no device filter");
+        let config = Config::from_string("banks: []\nswitches: []\nrecording: { directory: concert }\n", Some(test_path(&[]))).unwrap();
+        assert_eq!(config.recording_directory(), test_path(&["concert"]));
 
-        // Start button is not specified at all
-        assert!(config.find_switch_for_gamepad_button(10, Button::Start).is_none());
+        let config = Config::from_string("banks: []\nswitches: []\nrecording: {}\n", None).unwrap();
+        assert_eq!(config.recording_directory(), PathBuf::from("recordings"));
+    }
+
+    #[test]
+    fn volume_and_pan() {
+        let config_source = r###"
+master:
+  volume: 0.5
+banks:
+  - id: drums
+    volume: -6dB
+    pan: -0.5
+    samples:
+      - id: kick
+        file: kick.wav
+      - id: snare
+        file: snare.wav
+        volume: 2
+        pan: -0.75
+switches: []
+"###;
+        let config = Config::from_string(config_source, None).unwrap();
+        let kick = config.banks[0].samples[0].gain;
+        let snare = config.banks[0].samples[1].gain;
+        assert!((kick.volume - 0.5 * 0.501).abs() < 0.001);
+        assert_eq!(kick.pan, -0.5);
+        assert!((snare.volume - 0.5 * 0.501 * 2.0).abs() < 0.001);
+        assert_eq!(snare.pan, -1.0);
+
+        let config = Config::from_string("banks: [{ id: drums, samples: [{ id: kick, file: kick.wav }] }]\nswitches: []\n", None).unwrap();
+        assert_eq!(config.banks[0].samples[0].gain, Gain::default());
+
+        let config_source = r###"
+master:
+  pan: 2
+banks:
+  - id: drums
+    volume: loud
+    samples:
+      - id: kick
+        file: kick.wav
+        volume: -1
+switches: []
+"###;
+        let error = Config::from_string(config_source, None).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+        let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(paths, vec!["master.pan", "banks[0].volume", "banks[0].samples[0].volume"]);
+        assert!(matches!(errors[0].source, ConfigError::InvalidPan { .. }));
+        assert!(matches!(errors[1].source, ConfigError::InvalidVolume { .. }));
+    }
+
+    #[test]
+    fn audio() {
+        let config = Config::from_string("banks: []\nswitches: []\n", None).unwrap();
+        assert_eq!(config.audio.output, AudioOutputKind::System);
+
+        let config = Config::from_string("banks: []\nswitches: []\naudio: { output: none }\n", None).unwrap();
+        assert_eq!(config.audio.output, AudioOutputKind::Null);
+
+        assert!(Config::from_string("banks: []\nswitches: []\naudio: { output: speakers }\n", None).is_err());
+
+        let config = Config::from_string("banks: []\nswitches: []\naudio: { device: \"Focusrite*\", fallbackToDefault: true }\n", None).unwrap();
+        assert_eq!(config.audio, AudioConfig {
+            output: AudioOutputKind::System,
+            device: Some(String::from("Focusrite*")),
+            fallback_to_default: true,
+        });
+    }
+
+    #[test]
+    fn config_with_invalid_osc() {
+        let config_source = r###"
+banks: []
+switches: []
+osc:
+  listen: "localhost"
+  send: "127.0.0.1:9001"
+  prefix: microswitch
+"###;
+        let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+        let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(paths, vec!["osc.listen", "osc.prefix"]);
+        assert!(matches!(errors[0].source, ConfigError::InvalidSocketAddress { .. }));
+        assert!(matches!(errors[1].source, ConfigError::InvalidOscPrefix { .. }));
     }
 
     #[test]
@@ -1063,7 +3568,13 @@ switches:
       bank: invalid
 "###;
         let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
-        match error {
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "switches[0].playRandom.bank");
+        match &errors[0].source {
             ConfigError::UnknownBankId { bank } => {
                 assert_eq!(bank.as_str(), "invalid");
             }
@@ -1089,7 +3600,13 @@ switches:
       sample: invalid
 "###;
         let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
-        match error {
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "switches[0].play.sample");
+        match &errors[0].source {
             ConfigError::UnknownSampleId { bank, sample } => {
                 assert_eq!(bank.as_str(), "mybank");
                 assert_eq!(sample.as_str(), "invalid");
@@ -1099,4 +3616,159 @@ switches:
             }
         }
     }
+
+    #[test]
+    fn config_with_multiple_errors() {
+        let config_source = r###"
+banks:
+  - id: mybank
+    samples:
+      - id: mysample
+        file: foo.mp3
+
+switches:
+  - title: invalid key
+    key: NotAKey
+  - title: valid
+    key: A
+    play:
+      bank: mybank
+      sample: mysample
+  - title: invalid sample and gamepad button
+    gamepad:
+      button: NotAButton
+    play:
+      bank: mybank
+      sample: invalid
+  - title: invalid bank
+    playStep:
+      bank: invalid
+      steps: 1
+"###;
+        let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+
+        let summary: Vec<(&str, Option<Location>)> = errors
+            .iter()
+            .map(|error| (error.path.as_str(), error.location))
+            .collect();
+        let l = |line, column| Some(Location { line, column });
+
+        assert_eq!(summary, vec![
+            ("switches[0].key", l(10, 5)),
+            ("switches[2].gamepad.button", l(18, 7)),
+            ("switches[2].play.sample", l(21, 7)),
+            ("switches[3].playStep.bank", l(24, 7)),
+        ]);
+
+        let error_message = format!("{}", ConfigError::Invalid { errors });
+        assert!(error_message.starts_with("Found 4 problem(s) in the configuration:"));
+        assert!(error_message.contains("switches[0].key (line 10, column 5): Unknown keyboard button \"NotAKey\""));
+    }
+
+    #[test]
+    fn config_with_conflicting_bindings() {
+        let config_source = r###"
+banks: []
+switches:
+  - title: first
+    key: A
+    gamepad:
+      button: South
+  - title: same key
+    key: A
+    # only one of the switches allows sharing
+    sharedBinding: true
+  - title: same gamepad button
+    gamepad:
+      button: South
+  - title: same gamepad button, but a specific device
+    gamepad:
+      deviceId: 1
+      button: South
+  - title: same gamepad button and the same specific device
+    gamepad:
+      deviceId: 1
+      button: South
+"###;
+        let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+
+        let messages: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(messages, vec![
+            "switches[1].key (line 9, column 5): The keyboard key \"A\" is already used by switch \"first\". Set `sharedBinding: true` on both switches to trigger them together".to_string(),
+            "switches[2].gamepad (line 13, column 5): The gamepad button \"South\" on any device is already used by switch \"first\". Set `sharedBinding: true` on both switches to trigger them together".to_string(),
+            "switches[4].gamepad (line 20, column 5): The gamepad button \"South\" on device 1 is already used by switch \"same gamepad button, but a specific device\". Set `sharedBinding: true` on both switches to trigger them together".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn unknown_gamepad_buttons_do_not_conflict() {
+        let config_source = r###"
+banks: []
+switches:
+  - title: first
+    gamepad:
+      button: Sout
+  - title: second
+    gamepad:
+      button: Nrth
+"###;
+        let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+
+        let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(paths, vec!["switches[0].gamepad.button", "switches[1].gamepad.button"]);
+    }
+
+    #[test]
+    fn config_with_conflicting_midi_bindings() {
+        let config_source = r###"
+banks: []
+switches:
+  - title: first
+    midi:
+      note: 60
+      channel: 1
+  - title: other channel
+    midi:
+      note: 60
+      channel: 2
+  - title: any channel
+    midi:
+      note: 60
+  - title: controller
+    midiMessages:
+      - cc: 60
+      - cc: 64
+  - title: same controller on a channel
+    midi:
+      cc: 64
+      channel: 3
+  - title: first port
+    midi: { note: 10, port: "A*" }
+  - title: second port
+    midi: { note: 10, port: "B*" }
+"###;
+        let error = Config::from_string(config_source, Some(test_path(&[]))).unwrap_err();
+        let errors = match error {
+            ConfigError::Invalid { errors } => errors,
+            _ => panic!("Expected error to be ConfigError::Invalid"),
+        };
+
+        let messages: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(messages, vec![
+            "switches[2].midi (line 13, column 5): The MIDI note 60 on any channel is already used by switch \"first\". Set `sharedBinding: true` on both switches to trigger them together".to_string(),
+            "switches[4].midi (line 20, column 5): The MIDI controller 64 on channel 3 is already used by switch \"controller\". Set `sharedBinding: true` on both switches to trigger them together".to_string(),
+        ]);
+    }
 }
@@ -0,0 +1,213 @@
+use crate::config::Config;
+use crate::error::{readable_thread_panic_error, ConfigWatchThreadError};
+use crate::sound_thread::SoundThreadRpc;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often the config file and the sample files are checked for modifications
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug)]
+enum ConfigWatchOperation {
+    Stop,
+}
+
+/// The modification time of every watched file. None if the file could not be read (e.g. it has
+/// been deleted), so that it will be seen as a change when the file appears again.
+type FileSnapshot = HashMap<PathBuf, Option<SystemTime>>;
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// All files that should trigger a reload if they are modified: the config file itself and every
+/// sample file that it references.
+fn watched_files(config_path: &Path, config: &Config) -> Vec<PathBuf> {
+    let mut files = vec![config_path.to_path_buf()];
+
+    for bank_config in &config.banks {
+        for sample_config in &bank_config.samples {
+            if let Some(file_resolved) = &sample_config.file_resolved {
+                files.push(file_resolved.clone());
+            }
+        }
+    }
+
+    files
+}
+
+fn take_snapshot(files: &[PathBuf]) -> FileSnapshot {
+    files
+        .iter()
+        .map(|path| (path.clone(), modified_time(path)))
+        .collect()
+}
+
+struct ConfigWatchThreadBody {
+    config_path: PathBuf,
+    sound_thread_rpc: SoundThreadRpc,
+    operation_receiver: Receiver<ConfigWatchOperation>,
+    snapshot: FileSnapshot,
+}
+
+impl ConfigWatchThreadBody {
+    fn new(
+        config_path: PathBuf,
+        config: &Config,
+        sound_thread_rpc: SoundThreadRpc,
+        operation_receiver: Receiver<ConfigWatchOperation>,
+    ) -> Self {
+        let snapshot = take_snapshot(&watched_files(&config_path, config));
+        Self { config_path, sound_thread_rpc, operation_receiver, snapshot }
+    }
+
+    fn has_changes(&self) -> bool {
+        self.snapshot
+            .iter()
+            .any(|(path, modified)| modified_time(path) != *modified)
+    }
+
+    fn reload(&mut self) -> Result<(), ConfigWatchThreadError> {
+        println!("Config or sample file has been modified, reloading {}", self.config_path.to_str().unwrap());
+
+        match Config::from_file(&self.config_path) {
+            Ok(config) => {
+                config.print_warnings();
+                let snapshot = take_snapshot(&watched_files(&self.config_path, &config));
+
+                if let Err(err) = self.sound_thread_rpc.reload_config(config) {
+                    eprintln!("Error sending reload_config to sound thread {}", err);
+                    return Err(ConfigWatchThreadError::SendSoundThread);
+                }
+
+                // Only remember the new modification times once the new config has been handed
+                // to the SoundThread, so that a reload which could not be sent is tried again. If
+                // the SoundThread then fails to load the samples, it keeps the current config and
+                // the reload is not tried again until a file is modified.
+                self.snapshot = snapshot;
+            },
+            Err(err) => {
+                eprintln!("The modified configuration is not valid, keeping the current configuration: {}", err);
+
+                // Only update the modification time of files we already know about, so that we
+                // do not keep trying to reload the same invalid config.
+                let files: Vec<PathBuf> = self.snapshot.keys().cloned().collect();
+                self.snapshot = take_snapshot(&files);
+            },
+        }
+
+        Ok(())
+    }
+
+    fn thread_body(mut self) -> Result<(), ConfigWatchThreadError> {
+        loop {
+            match self.operation_receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(received) => {
+                    match received {
+                        ConfigWatchOperation::Stop => {
+                            return Ok(());
+                        }
+                    }
+                },
+                Err(RecvTimeoutError::Timeout) => {
+                    if self.has_changes() {
+                        if let Err(err) = self.reload() {
+                            // The SoundThread is gone, which happens during shutdown
+                            eprintln!("Stopping ConfigWatchThread: {}", err);
+                            return Ok(());
+                        }
+                    }
+                },
+                Err(err @ RecvTimeoutError::Disconnected) => {
+                    return Err(ConfigWatchThreadError::from(err));
+                },
+            };
+        }
+    }
+}
+
+/// The ConfigWatchThread periodically checks if the config file, or any of the sample files it
+/// references, has been modified. If so, the config is parsed again and handed over to the
+/// SoundThread, which will reload the samples and notify the GUI. If the new config is not valid,
+/// the current config is kept.
+pub struct ConfigWatchThread {
+    operation_sender: Sender<ConfigWatchOperation>,
+    handle: JoinHandle<()>,
+}
+
+impl ConfigWatchThread {
+    pub fn new(config_path: &Path, config: &Config, sound_thread_rpc: SoundThreadRpc) -> Self {
+        let config_path = config_path.to_path_buf();
+        let (operation_sender, operation_receiver) = mpsc::channel();
+
+        // take the initial snapshot before returning, so that modifications made while the
+        // thread is starting up are not missed
+        let body = ConfigWatchThreadBody::new(config_path, config, sound_thread_rpc, operation_receiver);
+
+        let handle = thread::spawn(move || {
+            body.thread_body()
+                .expect("Error during ConfigWatchThreadBody.thread_body()");
+        });
+
+        Self { operation_sender, handle }
+    }
+
+    pub fn stop(self) -> Result<(), ConfigWatchThreadError> {
+        if let Err(err) = self.operation_sender.send(ConfigWatchOperation::Stop) {
+            eprintln!("Failed to send stop operation to ConfigWatchThread: {}", err);
+            // Still try to join in this case, this will probably give us more error details
+        }
+        let handle = self.handle;
+
+        match handle.join() {
+            Ok(_) => Ok(()),
+            Err(join_error) => {
+                let join_error_str = readable_thread_panic_error(&join_error);
+                Err(ConfigWatchThreadError::JoinPanic {
+                    join_error,
+                    join_error_str,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::config_watch_thread::watched_files;
+    use std::path::PathBuf;
+    use pretty_assertions::assert_eq;
+
+    fn test_path(extra_parts: &[&str]) -> PathBuf {
+        let path_sep = std::path::MAIN_SEPARATOR.to_string();
+        [path_sep.as_str(), "test", "path"].iter().chain(extra_parts.iter()).collect()
+    }
+
+    #[test]
+    fn watched_files_include_config_and_samples() {
+        let config_source = r###"
+banks:
+  - id: bankA
+    samples:
+      - id: foo1
+        file: foo1.mp3
+      - id: foo2
+        file: sub/foo2.wav
+switches: []
+"###;
+        let config = Config::from_string(config_source, Some(test_path(&[]))).unwrap();
+
+        assert_eq!(watched_files(&test_path(&["config.yaml"]), &config), vec![
+            test_path(&["config.yaml"]),
+            test_path(&["foo1.mp3"]),
+            test_path(&["sub/foo2.wav"]),
+        ]);
+    }
+}
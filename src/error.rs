@@ -1,13 +1,15 @@
-use rodio::{StreamError, PlayError};
+use rodio::{DevicesError, StreamError, PlayError};
 use rodio::decoder::DecoderError;
 use std::any::Any;
 use std::io;
-use std::sync::mpsc::{RecvError, SendError, RecvTimeoutError};
+use std::net::SocketAddr;
+use std::sync::mpsc::{RecvError, SendError, RecvTimeoutError, TryRecvError};
 use thiserror::Error;
 use msgbox::IconType;
 use std::path::PathBuf;
 use std::fmt::{Debug, Display};
 use crate::sound_thread::SoundThreadEvent;
+use crate::yaml_location::Location;
 
 #[derive(Error, Debug)]
 pub enum ConfigError {
@@ -23,11 +25,112 @@ pub enum ConfigError {
     #[error("Unknown gamepad button \"{button}\". Allowed values are: {allowed_values}")]
     UnknownGamepadButton { button: String, allowed_values: String },
 
+    #[error("Unknown keyboard modifier \"{modifier}\". Allowed values are: {allowed_values}")]
+    UnknownKeyboardModifier { modifier: String, allowed_values: String },
+
+    #[error("Unknown gamepad axis \"{axis}\". Allowed values are: {allowed_values}")]
+    UnknownGamepadAxis { axis: String, allowed_values: String },
+
+    #[error("The threshold of a gamepad axis must be greater than 0 and at most 1, not {threshold}")]
+    InvalidGamepadAxisThreshold { threshold: f32 },
+
+    #[error("The hysteresis of a gamepad axis must be at least 0 and less than its threshold, not {hysteresis}")]
+    InvalidGamepadAxisHysteresis { hysteresis: f32 },
+
+    #[error("Exactly one of `note` and `cc` must be set")]
+    AmbiguousMidiControl,
+
+    #[error("MIDI note and controller numbers must be between 0 and 127, not {number}")]
+    InvalidMidiNumber { number: u8 },
+
+    #[error("MIDI velocities and controller values must be between 0 and 127, not {value}")]
+    InvalidMidiValue { value: u8 },
+
+    #[error("The MIDI channel must be between 1 and 16, not {channel}")]
+    InvalidMidiChannel { channel: u8 },
+
+    #[error("The threshold of a MIDI controller must be between 1 and 127, not {threshold}")]
+    InvalidMidiThreshold { threshold: u8 },
+
+    #[error("The scanning interval must be greater than 0")]
+    InvalidScanInterval,
+
+    #[error("Scanning requires at least one column")]
+    InvalidScanColumns,
+
+    #[error("`{action}` would be performed on every press in addition to `shortPress`, `longPress` and `doublePress`, move it into one of those instead")]
+    PressActionWithGestures { action: String },
+
+    #[error("Invalid network address \"{address}\", expected an IP address and a port such as \"127.0.0.1:9000\"")]
+    InvalidSocketAddress { address: String },
+
+    #[error("The OSC prefix must start with \"/\", not \"{prefix}\"")]
+    InvalidOscPrefix { prefix: String },
+
+    #[error("Invalid volume \"{volume}\", expected a number of at least 0 (such as 0.5) or decibels (such as \"-6dB\")")]
+    InvalidVolume { volume: String },
+
+    #[error("The pan must be between -1.0 (left) and 1.0 (right), not {pan}")]
+    InvalidPan { pan: f32 },
+
+    #[error("Only one of `deviceId`, `uuid` and `name`/`index` may be used to select a gamepad")]
+    AmbiguousGamepadSelector,
+
+    #[error("Invalid gamepad UUID \"{uuid}\", expected 32 hexadecimal digits")]
+    InvalidGamepadUuid { uuid: String },
+
+    #[error("Unknown mouse button \"{button}\". Allowed values are: {allowed_values}")]
+    UnknownMouseButton { button: String, allowed_values: String },
+
     #[error("Bank with id \"{bank}\" has not been defined")]
     UnknownBankId { bank: String },
 
     #[error("Sample with id \"{sample}\" has not been defined in bank \"{bank}\"")]
     UnknownSampleId { bank: String, sample: String },
+
+    #[error("The {binding} is already used by switch \"{other_switch}\". Set `sharedBinding: true` on both switches to trigger them together")]
+    ConflictingBinding { binding: String, other_switch: String },
+
+    #[error("{}", format_located_config_errors(errors))]
+    Invalid { errors: Vec<LocatedConfigError> },
+}
+
+/// A problem in the config which does not prevent it from being used, but which is probably not
+/// what the user intended.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ConfigWarning {
+    #[error("The gamepad button \"{button}\" on {device} takes priority over the same button on any device, which is used by switch \"{other_switch}\"")]
+    ShadowedGamepadBinding { button: String, device: String, other_switch: String },
+}
+
+/// A ConfigWarning along with the place in the config file that caused it
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("{path}{}: {warning}", location.map(|location| format!(" ({})", location)).unwrap_or_default())]
+pub struct LocatedConfigWarning {
+    /// The path of the offending field, e.g. `switches[4].gamepad`
+    pub path: String,
+    pub location: Option<Location>,
+    pub warning: ConfigWarning,
+}
+
+/// A ConfigError along with the place in the config file that caused it
+#[derive(Error, Debug)]
+#[error("{path}{}: {source}", location.map(|location| format!(" ({})", location)).unwrap_or_default())]
+pub struct LocatedConfigError {
+    /// The path of the offending field, e.g. `switches[4].play.sample`
+    pub path: String,
+    /// None if the config source is not available, or if the path could not be found in it
+    pub location: Option<Location>,
+    pub source: ConfigError,
+}
+
+fn format_located_config_errors(errors: &[LocatedConfigError]) -> String {
+    let mut result = format!("Found {} problem(s) in the configuration:", errors.len());
+    for error in errors {
+        result.push_str("\n  - ");
+        result.push_str(&error.to_string());
+    }
+    result
 }
 
 #[derive(Error, Debug)]
@@ -61,9 +164,18 @@ pub enum SoundThreadError {
     #[error("SoundThread: Failed to open sound stream: {source}")]
     OpenSoundStream { #[from] source: StreamError },
 
+    #[error("SoundThread: Failed to list the audio output devices: {source}")]
+    ListAudioDevices { #[from] source: DevicesError },
+
+    #[error("SoundThread: No audio output device matches \"{pattern}\" (available devices: {available})")]
+    AudioDeviceNotFound { pattern: String, available: String },
+
     #[error("SoundThread: Failed to recv() from channel (sender went away?): {source}")]
     Recv { #[from] source: RecvError },
 
+    #[error("SoundThread: Failed to recv() from channel (sender went away?): {source}")]
+    RecvTimeout { #[from] source: RecvTimeoutError },
+
     #[error("SoundThread: Failed to send() SoundThreadEvent to channel: {source}")]
     SendEvent { #[from] source: SendError<SoundThreadEvent> },
 
@@ -75,6 +187,27 @@ pub enum SoundThreadError {
 
     #[error("SoundThread: Failed to play the sample")]
     PlayError { #[from] source: PlayError },
+
+    #[error("SoundThread: Failed to send a MIDI message: {source}")]
+    MidiOutput { #[from] source: MidiOutputError },
+
+    #[error("SoundThread: {source}")]
+    RecordingThread { #[from] source: RecordingThreadError },
+}
+
+#[derive(Error, Debug)]
+pub enum MidiOutputError {
+    #[error("MIDI output is not available: {message}")]
+    Init { message: String },
+
+    #[error("No MIDI output port matches \"{pattern}\"")]
+    NoPort { pattern: String },
+
+    #[error("Failed to connect to MIDI output port \"{port}\": {message}")]
+    Connect { port: String, message: String },
+
+    #[error("Failed to send MIDI message: {message}")]
+    Send { message: String },
 }
 
 #[derive(Error, Debug)]
@@ -98,6 +231,168 @@ pub enum GamepadThreadError {
     },
 }
 
+#[derive(Error, Debug)]
+pub enum MidiThreadError {
+    #[error("MidiThread: Failed to send message to SoundThread")]
+    SendSoundThread,
+
+    #[error("MidiThread: Failed to recv() (sender went away?): {source}")]
+    Recv { #[from] source: TryRecvError },
+
+    #[error("MidiThread: The thread panicked: {join_error_str}")]
+    JoinPanic {
+        join_error_str: String,
+        join_error: Box<dyn Any + Send + 'static>,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum OscThreadError {
+    #[error("OscThread: Failed to open a UDP socket on {address}: {source}")]
+    Bind { address: SocketAddr, source: io::Error },
+
+    #[error("OscThread: Socket error: {source}")]
+    Socket { #[from] source: io::Error },
+
+    #[error("OscThread: Failed to encode OSC message: {message}")]
+    Encode { message: String },
+
+    #[error("OscThread: Failed to send message to SoundThread")]
+    SendSoundThread,
+
+    #[error("OscThread: Failed to recv() (sender went away?): {source}")]
+    Recv { #[from] source: TryRecvError },
+
+    #[error("OscThread: The thread panicked: {join_error_str}")]
+    JoinPanic {
+        join_error_str: String,
+        join_error: Box<dyn Any + Send + 'static>,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum HttpThreadError {
+    #[error("HttpThread: Failed to listen on {address}: {message}")]
+    Bind { address: SocketAddr, message: String },
+
+    #[error("HttpThread: Failed to respond to a request: {source}")]
+    Respond { #[from] source: io::Error },
+
+    #[error("HttpThread: Failed to send message to SoundThread")]
+    SendSoundThread,
+
+    #[error("HttpThread: Failed to recv() (sender went away?): {source}")]
+    Recv { #[from] source: TryRecvError },
+
+    #[error("HttpThread: The thread panicked: {join_error_str}")]
+    JoinPanic {
+        join_error_str: String,
+        join_error: Box<dyn Any + Send + 'static>,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum RecordingThreadError {
+    #[error("RecordingThread: Failed to create directory ({path}): {source}")]
+    CreateDirectory { path: PathBuf, source: io::Error },
+
+    #[error("RecordingThread: Failed to create recording ({path}): {source}")]
+    Create { path: PathBuf, source: hound::Error },
+
+    #[error("RecordingThread: Failed to write recording: {source}")]
+    Write { #[from] source: hound::Error },
+
+    #[error("RecordingThread: Failed to recv() (sender went away?): {source}")]
+    Recv { #[from] source: TryRecvError },
+
+    #[error("RecordingThread: The thread panicked: {join_error_str}")]
+    JoinPanic {
+        join_error_str: String,
+        join_error: Box<dyn Any + Send + 'static>,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum OfflineRenderError {
+    #[error("Failed to render because the configuration is not valid: {source}")]
+    Config { #[from] source: ConfigError },
+
+    #[error("Failed to render: {source}")]
+    SessionLog { #[from] source: SessionLogError },
+
+    #[error("Failed to render: {source}")]
+    SoundThread { #[from] source: SoundThreadError },
+
+    #[error("Failed to write the rendered file ({path}): {source}")]
+    Write { path: PathBuf, source: hound::Error },
+}
+
+#[derive(Error, Debug)]
+pub enum SessionLogError {
+    #[error("Failed to open session log ({path}): {source}")]
+    Open { path: PathBuf, source: io::Error },
+
+    #[error("Failed to read or write session log: {source}")]
+    IO { #[from] source: io::Error },
+
+    #[error("Invalid session log, line {line}: {source}")]
+    Parse { line: usize, source: serde_json::Error },
+
+    #[error("Failed to serialize session log entry: {source}")]
+    Serialize { source: serde_json::Error },
+}
+
+#[derive(Error, Debug)]
+pub enum SessionRecordThreadError {
+    #[error("SessionRecordThread: {source}")]
+    SessionLog { #[from] source: SessionLogError },
+
+    #[error("SessionRecordThread: Failed to send message to SoundThread")]
+    SendSoundThread,
+
+    #[error("SessionRecordThread: Failed to recv() (sender went away?): {source}")]
+    Recv { #[from] source: TryRecvError },
+
+    #[error("SessionRecordThread: The thread panicked: {join_error_str}")]
+    JoinPanic {
+        join_error_str: String,
+        join_error: Box<dyn Any + Send + 'static>,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum SessionReplayThreadError {
+    #[error("SessionReplayThread: {source}")]
+    SessionLog { #[from] source: SessionLogError },
+
+    #[error("SessionReplayThread: Failed to send message to SoundThread")]
+    SendSoundThread,
+
+    #[error("SessionReplayThread: Failed to recv() (sender went away?): {source}")]
+    Recv { #[from] source: RecvTimeoutError },
+
+    #[error("SessionReplayThread: The thread panicked: {join_error_str}")]
+    JoinPanic {
+        join_error_str: String,
+        join_error: Box<dyn Any + Send + 'static>,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigWatchThreadError {
+    #[error("ConfigWatchThread: Failed to send message to SoundThread")]
+    SendSoundThread,
+
+    #[error("ConfigWatchThread: Failed to recv() (sender went away?): {source}")]
+    Recv { #[from] source: RecvTimeoutError },
+
+    #[error("ConfigWatchThread: The thread panicked: {join_error_str}")]
+    JoinPanic {
+        join_error_str: String,
+        join_error: Box<dyn Any + Send + 'static>,
+    },
+}
+
 // gilrs does not implement Send (on linux) which we need, so copy the error message only, instead
 // of including it as `source`
 impl From<gilrs::Error> for GamepadThreadError {
@@ -113,6 +408,18 @@ pub enum AppRunError {
     #[error("Failed to start application (GamepadThread): {source}")]
     GamepadThread { #[from] source: GamepadThreadError },
 
+    #[error("Failed to start application (OscThread): {source}")]
+    OscThread { #[from] source: OscThreadError },
+
+    #[error("Failed to start application (HttpThread): {source}")]
+    HttpThread { #[from] source: HttpThreadError },
+
+    #[error("Failed to start application (SessionRecordThread): {source}")]
+    SessionRecordThread { #[from] source: SessionRecordThreadError },
+
+    #[error("Failed to start application (SessionReplayThread): {source}")]
+    SessionReplayThread { #[from] source: SessionReplayThreadError },
+
     #[error("Failed to start application (SoundThread): {source}")]
     SoundThread { #[from] source: SoundThreadError },
 
@@ -121,18 +428,21 @@ pub enum AppRunError {
 
     #[error("Failed to start application (iced): {source}")]
     Iced { #[from] source: iced::Error },
+
+    #[error("Missing argument, usage: {usage}")]
+    Usage { usage: &'static str },
 }
 
 pub fn readable_thread_panic_error(error: &Box<dyn Any + Send + 'static>) -> String {
     let mut stringified = String::from("???");
 
     if let Some(s) = error.downcast_ref::<&str>() {
-        stringified = format!("{}", s);
+        stringified = s.to_string();
     }
     else if let Some(s) = error.downcast_ref::<String>() {
-        stringified = format!("{}", s);
+        stringified = s.to_string();
     }
-    let type_id = error.type_id();
+    let type_id = (**error).type_id();
 
     format!("panic from thread: [{:?}]: [{}]", type_id, stringified)
 }
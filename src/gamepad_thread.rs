@@ -1,16 +1,80 @@
-use crate::config::Config;
+use crate::config::{Config, GamepadAxisRef, GamepadSelector, SwitchRef};
 use crate::error::{readable_thread_panic_error, GamepadThreadError};
-use crate::sound_thread::SoundThreadRpc;
-use gilrs::{Gilrs, Event, EventType, Button, GamepadId};
-use std::sync::mpsc::{Sender, RecvTimeoutError, Receiver};
+use crate::name_match::name_matches;
+use crate::sound_thread::{InputSource, SoundThreadRpc};
+use gilrs::{Gilrs, Event, EventType, Axis, Button, GamepadId};
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{Sender, SendError, RecvTimeoutError, Receiver};
 use std::sync::mpsc;
 use std::thread::JoinHandle;
 use std::thread;
 use std::time::Duration;
 
 #[derive(Debug)]
-enum GamepadOperation {
+pub enum GamepadOperation {
     Stop,
+    ReloadConfig {
+        config: Box<Config>,
+    },
+}
+
+/// The properties of a connected gamepad that can be used to select it in the config
+#[derive(Debug, Clone, PartialEq)]
+struct ConnectedGamepad {
+    device_id: usize,
+    name: String,
+    uuid: String,
+}
+
+fn format_uuid(uuid: &[u8; 16]) -> String {
+    uuid.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Determine which selectors apply to each of the connected gamepads (by device id)
+fn match_gamepad_selectors(selectors: &[&GamepadSelector], gamepads: &[ConnectedGamepad]) -> HashMap<usize, Vec<GamepadSelector>> {
+    let mut gamepads: Vec<&ConnectedGamepad> = gamepads.iter().collect();
+    // the ordinal of a device is based on the order in which the devices were connected
+    gamepads.sort_by_key(|gamepad| gamepad.device_id);
+
+    let mut result: HashMap<usize, Vec<GamepadSelector>> = HashMap::new();
+
+    for &selector in selectors {
+        let matching = gamepads
+            .iter()
+            .filter(|gamepad| match selector {
+                GamepadSelector::Any => false,
+                GamepadSelector::DeviceId(device_id) => gamepad.device_id == *device_id,
+                GamepadSelector::Uuid(uuid) => gamepad.uuid == *uuid,
+                GamepadSelector::Name { pattern, index: _ } => name_matches(pattern, &gamepad.name),
+            });
+
+        let matching: Vec<&&ConnectedGamepad> = match selector {
+            GamepadSelector::Name { pattern: _, index: Some(index) } => matching.skip(*index).take(1).collect(),
+            _ => matching.collect(),
+        };
+
+        for gamepad in matching {
+            result.entry(gamepad.device_id).or_default().push(selector.clone());
+        }
+    }
+
+    result
+}
+
+/// A gamepad button or axis which is currently holding down a switch
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum HeldInput {
+    Button(usize, Button),
+    Axis(usize, GamepadAxisRef),
+}
+
+impl HeldInput {
+    fn device_id(&self) -> usize {
+        match self {
+            HeldInput::Button(device_id, _) => *device_id,
+            HeldInput::Axis(device_id, _) => *device_id,
+        }
+    }
 }
 
 struct GamepadThreadBody {
@@ -18,33 +82,47 @@ struct GamepadThreadBody {
     sound_thread_rpc: SoundThreadRpc,
     gilrs: Gilrs,
     operation_receiver: Receiver<GamepadOperation>,
+    /// device id => the selectors from the config that match this device
+    device_selectors: HashMap<usize, Vec<GamepadSelector>>,
+    /// The inputs which are currently holding down each switch. A switch is released once all of
+    /// its inputs have been released.
+    held_inputs: HashMap<SwitchRef, HashSet<HeldInput>>,
 }
 
 impl GamepadThreadBody {
     fn new(config: Config, sound_thread_rpc: SoundThreadRpc, operation_receiver: Receiver<GamepadOperation>) -> Result<Self, GamepadThreadError> {
         let gilrs = Gilrs::new()?;
-        let body = Self { config, sound_thread_rpc, gilrs, operation_receiver };
+        let mut body = Self { config, sound_thread_rpc, gilrs, operation_receiver, device_selectors: HashMap::new(), held_inputs: HashMap::new() };
         body.print_devices();
+        body.resolve_device_selectors();
         Ok(body)
     }
 
     fn print_devices(&self) {
         println!("Connected gamepads:");
         for (id, gamepad) in self.gilrs.gamepads() {
-            println!("  {}: \"{}\" is {:?}", id, gamepad.name(), gamepad.power_info());
+            println!("  {}: \"{}\" (uuid {}) is {:?}", id, gamepad.name(), format_uuid(&gamepad.uuid()), gamepad.power_info());
         }
     }
 
-    fn handle_gamepad_button(&self, gamepad_id: GamepadId, button: Button) -> Result<(), GamepadThreadError> {
-        let device_id: usize = gamepad_id.into();
-        println!("Gamepad {:?} button: {:?}", device_id, button);
+    /// Must be called whenever a device is connected or disconnected, or the config changes
+    fn resolve_device_selectors(&mut self) {
+        let gamepads: Vec<ConnectedGamepad> = self.gilrs.gamepads()
+            .map(|(id, gamepad)| ConnectedGamepad {
+                device_id: id.into(),
+                name: gamepad.name().to_string(),
+                uuid: format_uuid(&gamepad.uuid()),
+            })
+            .collect();
 
-        let switch_config = self.config.find_switch_for_gamepad_button(device_id, button);
+        self.device_selectors = match_gamepad_selectors(&self.config.gamepad_selectors(), &gamepads);
+    }
 
-        if let Some(switch_config) = switch_config {
-            let switch_ref = switch_config.switch_ref;
+    fn press(&mut self, switch_ref: SwitchRef, input: HeldInput) -> Result<(), GamepadThreadError> {
+        let newly_held = self.held_inputs.entry(switch_ref).or_default().insert(input);
 
-            if let Err(err) = self.sound_thread_rpc.switch_pressed(switch_ref) {
+        if newly_held {
+            if let Err(err) = self.sound_thread_rpc.switch_pressed(switch_ref, InputSource::Gamepad) {
                 eprintln!("Error sending switch_pressed {:?} to sound thread {}", switch_ref, err);
                 return Err(GamepadThreadError::SendSoundThread);
             }
@@ -53,6 +131,72 @@ impl GamepadThreadBody {
         Ok(())
     }
 
+    /// Release all held inputs that match the predicate
+    fn release_where(&mut self, predicate: impl Fn(&HeldInput) -> bool) -> Result<(), GamepadThreadError> {
+        let mut released = Vec::new();
+
+        self.held_inputs.retain(|switch_ref, inputs| {
+            let held_before = !inputs.is_empty();
+            inputs.retain(|input| !predicate(input));
+
+            if held_before && inputs.is_empty() {
+                released.push(*switch_ref);
+            }
+            !inputs.is_empty()
+        });
+
+        for switch_ref in released {
+            if let Err(err) = self.sound_thread_rpc.switch_released(switch_ref, InputSource::Gamepad) {
+                eprintln!("Error sending switch_released {:?} to sound thread {}", switch_ref, err);
+                return Err(GamepadThreadError::SendSoundThread);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_gamepad_button(&mut self, gamepad_id: GamepadId, button: Button) -> Result<(), GamepadThreadError> {
+        let device_id: usize = gamepad_id.into();
+        println!("Gamepad {:?} button: {:?}", device_id, button);
+
+        let selectors = self.device_selectors.get(&device_id).map(Vec::as_slice).unwrap_or_default();
+        let switch_refs = self.config.find_switches_for_gamepad_button(selectors, button);
+
+        for switch_ref in switch_refs {
+            self.press(switch_ref, HeldInput::Button(device_id, button))?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_gamepad_button_released(&mut self, gamepad_id: GamepadId, button: Button) -> Result<(), GamepadThreadError> {
+        let device_id: usize = gamepad_id.into();
+        self.release_where(|input| *input == HeldInput::Button(device_id, button))
+    }
+
+    fn handle_gamepad_axis(&mut self, gamepad_id: GamepadId, axis: Axis, value: f32) -> Result<(), GamepadThreadError> {
+        let device_id: usize = gamepad_id.into();
+        let selectors = self.device_selectors.get(&device_id).map(Vec::as_slice).unwrap_or_default();
+
+        for axis_ref in self.config.find_gamepad_axis_triggers(selectors, axis) {
+            let input = HeldInput::Axis(device_id, axis_ref);
+            let was_held = self.held_inputs
+                .get(&axis_ref.switch_ref)
+                .map(|inputs| inputs.contains(&input))
+                .unwrap_or(false);
+            let is_held = self.config.gamepad_axis(axis_ref).is_held(was_held, value);
+
+            if is_held && !was_held {
+                println!("Gamepad {:?} axis {:?} crossed threshold: {}", device_id, axis, value);
+                self.press(axis_ref.switch_ref, input)?;
+            } else if !is_held && was_held {
+                self.release_where(|held_input| *held_input == input)?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn thread_body(mut self) -> Result<(), GamepadThreadError> {
         let sleep_duration = Duration::from_millis(1);
 
@@ -64,6 +208,24 @@ impl GamepadThreadBody {
                             eprintln!("Error while handling gamepad button event {:?}", err);
                         }
                     },
+                    EventType::ButtonReleased(button, _code) => {
+                        if let Err(err) = self.handle_gamepad_button_released(id, button) {
+                            eprintln!("Error while handling gamepad button event {:?}", err);
+                        }
+                    },
+                    EventType::AxisChanged(axis, value, _code) => {
+                        if let Err(err) = self.handle_gamepad_axis(id, axis, value) {
+                            eprintln!("Error while handling gamepad axis event {:?}", err);
+                        }
+                    },
+                    EventType::Connected | EventType::Disconnected => {
+                        println!("Gamepad {} {}", id, if event == EventType::Connected { "connected" } else { "disconnected" });
+                        let device_id: usize = id.into();
+                        if let Err(err) = self.release_where(|input| input.device_id() == device_id) {
+                            eprintln!("Error while releasing the switches of a gamepad {:?}", err);
+                        }
+                        self.resolve_device_selectors();
+                    },
                     _ => {},
                 }
             }
@@ -74,6 +236,12 @@ impl GamepadThreadBody {
                         GamepadOperation::Stop => {
                             return Ok(());
                         }
+                        GamepadOperation::ReloadConfig { config } => {
+                            self.config = *config;
+                            // the SoundThread has already forgotten about held switches
+                            self.held_inputs.clear();
+                            self.resolve_device_selectors();
+                        }
                     }
                 },
                 Err(RecvTimeoutError::Timeout) => {
@@ -123,6 +291,10 @@ impl GamepadThread {
         Ok(gamepad_thread)
     }
 
+    pub fn reload_config(&self, config: &Config) -> Result<(), SendError<GamepadOperation>> {
+        self.operation_sender.send(GamepadOperation::ReloadConfig { config: Box::new(config.clone()) })
+    }
+
     pub fn stop(self) -> Result<(), GamepadThreadError> {
         if let Err(err) = self.operation_sender.send(GamepadOperation::Stop) {
             eprintln!("Failed to send stop operation to SoundThread: {}", err);
@@ -142,3 +314,39 @@ impl GamepadThread {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::GamepadSelector;
+    use crate::gamepad_thread::{ConnectedGamepad, match_gamepad_selectors};
+    use pretty_assertions::{assert_eq};
+
+    #[test]
+    fn selectors_are_matched_against_connected_gamepads() {
+        let gamepad = |device_id, name: &str, uuid: &str| ConnectedGamepad {
+            device_id,
+            name: name.to_string(),
+            uuid: uuid.to_string(),
+        };
+        // device ids depend on the order in which the devices were connected
+        let gamepads = vec![
+            gamepad(4, "Xbox Adaptive Controller", "0300000000000000000000000000000a"),
+            gamepad(1, "Generic USB Joystick", "0300000000000000000000000000000b"),
+            gamepad(2, "Xbox Adaptive Controller", "0300000000000000000000000000000c"),
+        ];
+
+        let by_id = GamepadSelector::DeviceId(1);
+        let by_uuid = GamepadSelector::Uuid("0300000000000000000000000000000a".to_string());
+        let by_name = GamepadSelector::Name { pattern: "xbox*".to_string(), index: None };
+        let first_xbox = GamepadSelector::Name { pattern: "xbox*".to_string(), index: Some(0) };
+        let second_xbox = GamepadSelector::Name { pattern: "xbox*".to_string(), index: Some(1) };
+        let third_xbox = GamepadSelector::Name { pattern: "xbox*".to_string(), index: Some(2) };
+
+        let result = match_gamepad_selectors(&[&by_id, &by_uuid, &by_name, &first_xbox, &second_xbox, &third_xbox], &gamepads);
+
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[&1], vec![by_id.clone()]);
+        assert_eq!(result[&2], vec![by_name.clone(), first_xbox.clone()]);
+        assert_eq!(result[&4], vec![by_uuid.clone(), by_name.clone(), second_xbox.clone()]);
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use crate::config::SwitchRef;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Gesture {
+    Short,
+    Long,
+    Double,
+}
+
+/// Which gestures are configured for a switch
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GestureTiming {
+    pub long_press: Option<Duration>,
+    pub double_press: Option<Duration>,
+}
+
+#[derive(Debug, Default)]
+struct GestureState {
+    /// The first press of a possible double press
+    first_pressed_at: Option<Instant>,
+    /// The switch is currently held down, and a long press occurs at this time
+    long_press_at: Option<Instant>,
+    /// The switch has been released, a short press occurs at this time unless it is pressed again
+    short_press_at: Option<Instant>,
+    /// The current press has already been handled as a long or double press
+    handled: bool,
+}
+
+/// Turns the presses and releases of switches into gestures. Gestures that are detected after
+/// some time has passed (such as a long press) are returned by expire().
+#[derive(Debug, Default)]
+pub struct GestureDetector {
+    states: HashMap<SwitchRef, GestureState>,
+}
+
+impl GestureDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pressed(&mut self, switch_ref: SwitchRef, timing: GestureTiming, now: Instant) -> Option<Gesture> {
+        let state = self.states.entry(switch_ref).or_default();
+
+        if let (Some(first_pressed_at), Some(double_press)) = (state.first_pressed_at, timing.double_press) {
+            if state.short_press_at.is_some() && now.saturating_duration_since(first_pressed_at) <= double_press {
+                *state = GestureState { handled: true, ..GestureState::default() };
+                return Some(Gesture::Double);
+            }
+        }
+
+        // the previous press was waiting for a second press which came too late
+        let previous = state.short_press_at.map(|_| Gesture::Short);
+
+        *state = GestureState {
+            first_pressed_at: Some(now),
+            long_press_at: timing.long_press.map(|long_press| now + long_press),
+            short_press_at: None,
+            handled: false,
+        };
+        previous
+    }
+
+    pub fn released(&mut self, switch_ref: SwitchRef, timing: GestureTiming, now: Instant) -> Option<Gesture> {
+        let state = self.states.entry(switch_ref).or_default();
+        state.long_press_at = None;
+
+        if state.handled || state.first_pressed_at.is_none() {
+            self.states.remove(&switch_ref);
+            return None;
+        }
+
+        // wait for a possible second press
+        if let (Some(first_pressed_at), Some(double_press)) = (state.first_pressed_at, timing.double_press) {
+            let short_press_at = first_pressed_at + double_press;
+            if now < short_press_at {
+                state.short_press_at = Some(short_press_at);
+                return None;
+            }
+        }
+
+        self.states.remove(&switch_ref);
+        Some(Gesture::Short)
+    }
+
+    /// Returns the gestures that have occurred because enough time has passed
+    pub fn expire(&mut self, now: Instant) -> Vec<(SwitchRef, Gesture)> {
+        let mut gestures = Vec::new();
+
+        for (switch_ref, state) in self.states.iter_mut() {
+            if state.long_press_at.is_some_and(|long_press_at| now >= long_press_at) {
+                state.long_press_at = None;
+                state.handled = true;
+                gestures.push((*switch_ref, Gesture::Long));
+            }
+
+            if state.short_press_at.is_some_and(|short_press_at| now >= short_press_at) {
+                state.short_press_at = None;
+                state.first_pressed_at = None;
+                gestures.push((*switch_ref, Gesture::Short));
+            }
+        }
+
+        self.states.retain(|_, state| state.first_pressed_at.is_some() || state.handled);
+        gestures.sort_by_key(|(switch_ref, _)| switch_ref.switch_index);
+        gestures
+    }
+
+    /// The next time at which expire() should be called
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.states
+            .values()
+            .flat_map(|state| [state.long_press_at, state.short_press_at])
+            .flatten()
+            .min()
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use crate::config::SwitchRef;
+    use crate::gesture::{Gesture, GestureDetector, GestureTiming};
+    use pretty_assertions::{assert_eq};
+
+    const SWITCH: SwitchRef = SwitchRef { switch_index: 0 };
+    const TIMING: GestureTiming = GestureTiming {
+        long_press: Some(Duration::from_millis(800)),
+        double_press: Some(Duration::from_millis(400)),
+    };
+
+    #[test]
+    fn short_press() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut detector = GestureDetector::new();
+
+        assert_eq!(detector.pressed(SWITCH, TIMING, at(0)), None);
+        assert_eq!(detector.released(SWITCH, TIMING, at(100)), None);
+        assert_eq!(detector.next_deadline(), Some(at(400)));
+        assert_eq!(detector.expire(at(399)), vec![]);
+        assert_eq!(detector.expire(at(400)), vec![(SWITCH, Gesture::Short)]);
+        assert_eq!(detector.next_deadline(), None);
+
+        // Without a double press, a short press is detected immediately on release
+        let timing = GestureTiming { long_press: TIMING.long_press, double_press: None };
+        assert_eq!(detector.pressed(SWITCH, timing, at(1000)), None);
+        assert_eq!(detector.released(SWITCH, timing, at(1100)), Some(Gesture::Short));
+        assert_eq!(detector.next_deadline(), None);
+    }
+
+    #[test]
+    fn long_press() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut detector = GestureDetector::new();
+
+        assert_eq!(detector.pressed(SWITCH, TIMING, at(0)), None);
+        assert_eq!(detector.next_deadline(), Some(at(800)));
+        assert_eq!(detector.expire(at(799)), vec![]);
+        assert_eq!(detector.expire(at(800)), vec![(SWITCH, Gesture::Long)]);
+        assert_eq!(detector.expire(at(900)), vec![]);
+        assert_eq!(detector.released(SWITCH, TIMING, at(1000)), None);
+        assert_eq!(detector.expire(at(2000)), vec![]);
+    }
+
+    #[test]
+    fn double_press() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut detector = GestureDetector::new();
+
+        assert_eq!(detector.pressed(SWITCH, TIMING, at(0)), None);
+        assert_eq!(detector.released(SWITCH, TIMING, at(100)), None);
+        assert_eq!(detector.pressed(SWITCH, TIMING, at(300)), Some(Gesture::Double));
+        // the second press can not become a long press
+        assert_eq!(detector.expire(at(1500)), vec![]);
+        assert_eq!(detector.released(SWITCH, TIMING, at(1600)), None);
+        assert_eq!(detector.expire(at(3000)), vec![]);
+
+        // too slow for a double press
+        assert_eq!(detector.pressed(SWITCH, TIMING, at(5000)), None);
+        assert_eq!(detector.released(SWITCH, TIMING, at(5100)), None);
+        assert_eq!(detector.expire(at(5400)), vec![(SWITCH, Gesture::Short)]);
+        assert_eq!(detector.pressed(SWITCH, TIMING, at(5500)), None);
+        assert_eq!(detector.released(SWITCH, TIMING, at(5600)), None);
+
+        // the second press is too late, but arrives before expire() is called
+        assert_eq!(detector.pressed(SWITCH, TIMING, at(6000)), Some(Gesture::Short));
+        assert_eq!(detector.released(SWITCH, TIMING, at(6100)), None);
+        assert_eq!(detector.expire(at(6400)), vec![(SWITCH, Gesture::Short)]);
+    }
+}
@@ -1,21 +1,39 @@
-use iced::{Alignment, Application, Command, Element, Error, Event, keyboard, Length, Settings, Subscription, Theme, theme, window};
-use iced::widget::{Column, button, Container, text};
+use iced::{Alignment, Application, Command, Element, Error, Event, event, keyboard, Length, mouse, Settings, Subscription, Theme, theme, window};
+use iced::widget::{Column, button, Container, Row, text};
 use iced::time::{every as iced_time_every};
 use iced::window::icon;
-use std::time::{Duration, Instant};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
 use std::sync::mpsc::Receiver;
-use crate::config::{Config, SwitchRef};
-use crate::sound_thread::{SoundThread, SoundThreadRpc, SoundThreadEvent};
+use crate::config::{Config, ScanAction, SwitchRef};
+use crate::sound_thread::{InputSource, SoundThread, SoundThreadRpc, SoundThreadEvent};
 use crate::gamepad_thread::{GamepadThread};
+use crate::midi_thread::MidiThread;
+use crate::config_watch_thread::ConfigWatchThread;
+use crate::osc_thread::OscThread;
+use crate::http_thread::HttpThread;
+use crate::recording_thread::recording_path;
+use crate::session_record_thread::SessionRecordThread;
+use crate::session_replay_thread::SessionReplayThread;
 use crate::gui::executor::MyExecutor;
+use crate::gui::scanner::Scanner;
 use crate::gui::style::ButtonStyleSheet;
-use crate::gui::types::{Message, PlayButtonState};
+use crate::gui::types::{HeldTrigger, Message, PlayButtonState};
 
+/// The threads that have been started before the application, which are stopped when the window
+/// is closed
 pub struct ApplicationFlags {
-    config: Config,
-    sound_thread: SoundThread,
-    gamepad_thread: GamepadThread,
-    sound_thread_event_receiver: Receiver<SoundThreadEvent>,
+    pub config: Config,
+    pub sound_thread: SoundThread,
+    pub gamepad_thread: GamepadThread,
+    pub midi_thread: MidiThread,
+    pub config_watch_thread: Option<ConfigWatchThread>,
+    pub osc_thread: Option<OscThread>,
+    pub http_thread: Option<HttpThread>,
+    pub session_record_thread: Option<SessionRecordThread>,
+    pub session_replay_thread: Option<SessionReplayThread>,
+    pub sound_thread_event_receiver: Receiver<SoundThreadEvent>,
 }
 
 pub struct MyApplication {
@@ -23,17 +41,78 @@ pub struct MyApplication {
     sound_thread: Option<SoundThread>,
     sound_thread_rpc: SoundThreadRpc,
     gamepad_thread: Option<GamepadThread>,
+    midi_thread: Option<MidiThread>,
+    /// None if the config was embedded
+    config_watch_thread: Option<ConfigWatchThread>,
+    /// None if OSC has not been configured
+    osc_thread: Option<OscThread>,
+    /// None if the remote control has not been configured
+    http_thread: Option<HttpThread>,
+    /// None if the session log has not been configured
+    session_record_thread: Option<SessionRecordThread>,
+    /// None unless a session log is being replayed
+    session_replay_thread: Option<SessionReplayThread>,
     sound_thread_event_receiver: Receiver<SoundThreadEvent>,
     /// The state of each rendered play button. Each configured switch (SwitchConfig) has a 1:1
     /// correspondence with a button, at the same index.
     play_buttons: Vec<PlayButtonState>,
+    /// None if scanning has not been configured
+    scanner: Option<Scanner>,
+    /// The WAV file that is being recorded to, if any
+    recording: Option<PathBuf>,
+    /// Shown when the sound device has been disconnected, or when it has changed afterwards
+    audio_output_notice: Option<String>,
     now: Instant,
 }
 
+fn make_play_buttons(config: &Config) -> Vec<PlayButtonState> {
+    config.switches
+        .iter()
+        .map(|switch_config| PlayButtonState {
+            switch_title: switch_config.title.clone(),
+            held_triggers: HashSet::new(),
+            last_played_at: None,
+            filtered_presses: 0,
+        })
+        .collect()
+}
+
+fn make_scanner(config: &Config, now: Instant) -> Option<Scanner> {
+    config.scanning.as_ref().map(|scanning| {
+        // the switches that control scanning are not scanned themselves
+        let scannable = config.switches.iter().map(|switch_config| !switch_config.has_scan_action()).collect();
+        Scanner::new(scanning, scannable, now)
+    })
+}
+
 impl MyApplication {
     fn before_close(&mut self) {
         let sound_thread = self.sound_thread.take().unwrap();
         let gamepad_thread = self.gamepad_thread.take().unwrap();
+        let midi_thread = self.midi_thread.take().unwrap();
+
+        // stop these first, they might still try to send switch presses or a new config to the
+        // SoundThread
+        if let Some(session_replay_thread) = self.session_replay_thread.take() {
+            if let Err(err) = session_replay_thread.stop() {
+                eprintln!("Error while stopping SessionReplayThread: {:?}", err);
+            }
+        }
+        if let Some(config_watch_thread) = self.config_watch_thread.take() {
+            if let Err(err) = config_watch_thread.stop() {
+                eprintln!("Error while stopping ConfigWatchThread: {:?}", err);
+            }
+        }
+        if let Some(osc_thread) = self.osc_thread.take() {
+            if let Err(err) = osc_thread.stop() {
+                eprintln!("Error while stopping OscThread: {:?}", err);
+            }
+        }
+        if let Some(http_thread) = self.http_thread.take() {
+            if let Err(err) = http_thread.stop() {
+                eprintln!("Error while stopping HttpThread: {:?}", err);
+            }
+        }
 
         if let Err(err) = sound_thread.stop() {
             eprintln!("Error while stopping SoundThread: {:?}", err);
@@ -41,13 +120,98 @@ impl MyApplication {
         if let Err(err) = gamepad_thread.stop() {
             eprintln!("Error while stopping GamepadThread: {:?}", err);
         }
+        if let Err(err) = midi_thread.stop() {
+            eprintln!("Error while stopping MidiThread: {:?}", err);
+        }
+
+        // stop this one last, so that it receives every event of the SoundThread
+        if let Some(session_record_thread) = self.session_record_thread.take() {
+            if let Err(err) = session_record_thread.stop() {
+                eprintln!("Error while stopping SessionRecordThread: {:?}", err);
+            }
+        }
     }
 
-    fn switch_pressed(&self, switch_ref: SwitchRef) {
-        if let Err(err) = self.sound_thread_rpc.switch_pressed(switch_ref) {
+    fn switch_pressed(&self, switch_ref: SwitchRef, source: InputSource) {
+        if let Err(err) = self.sound_thread_rpc.switch_pressed(switch_ref, source) {
             eprintln!("Error sending switch_pressed to SoundThread {}", err);
         }
     }
+
+    fn switch_clicked(&self, switch_ref: SwitchRef, source: InputSource) {
+        if let Err(err) = self.sound_thread_rpc.switch_clicked(switch_ref, source) {
+            eprintln!("Error sending switch_clicked to SoundThread {}", err);
+        }
+    }
+
+    fn switch_released(&self, switch_ref: SwitchRef, source: InputSource) {
+        if let Err(err) = self.sound_thread_rpc.switch_released(switch_ref, source) {
+            eprintln!("Error sending switch_released to SoundThread {}", err);
+        }
+    }
+
+    /// A keyboard key or mouse button went down. Each switch is only pressed once, even if the
+    /// operating system repeats the event while the trigger is held down.
+    fn trigger_pressed(&mut self, trigger: HeldTrigger, switch_refs: &[SwitchRef]) {
+        for &switch_ref in switch_refs {
+            let newly_held = self.play_buttons[switch_ref.switch_index].held_triggers.insert(trigger);
+
+            if newly_held {
+                self.switch_pressed(switch_ref, trigger.source());
+            }
+        }
+    }
+
+    /// A keyboard key or mouse button went up. A switch is released once none of its triggers are
+    /// held down anymore.
+    fn trigger_released(&mut self, trigger: HeldTrigger, switch_refs: &[SwitchRef]) {
+        for &switch_ref in switch_refs {
+            let held_triggers = &mut self.play_buttons[switch_ref.switch_index].held_triggers;
+
+            if held_triggers.remove(&trigger) && held_triggers.is_empty() {
+                self.switch_released(switch_ref, trigger.source());
+            }
+        }
+    }
+
+    fn trigger_released_everywhere(&mut self, trigger: HeldTrigger) {
+        let switch_refs: Vec<SwitchRef> = self.config.switches.iter().map(|switch_config| switch_config.switch_ref).collect();
+        self.trigger_released(trigger, &switch_refs);
+    }
+
+    fn scan(&mut self, action: ScanAction) {
+        let Some(scanner) = &mut self.scanner else {
+            return;
+        };
+
+        match action {
+            ScanAction::Step => scanner.step(self.now),
+            ScanAction::Select => {
+                if let Some(index) = scanner.select(self.now) {
+                    self.switch_clicked(self.config.switches[index].switch_ref, InputSource::Scan);
+                }
+            },
+        }
+    }
+
+    /// Called after the SoundThread has switched over to a new config
+    fn config_reloaded(&mut self, config: Config) {
+        if let Some(gamepad_thread) = &self.gamepad_thread {
+            if let Err(err) = gamepad_thread.reload_config(&config) {
+                eprintln!("Error sending reload_config to GamepadThread {}", err);
+            }
+        }
+
+        if let Some(midi_thread) = &self.midi_thread {
+            if let Err(err) = midi_thread.reload_config(&config) {
+                eprintln!("Error sending reload_config to MidiThread {}", err);
+            }
+        }
+
+        self.play_buttons = make_play_buttons(&config);
+        self.scanner = make_scanner(&config, self.now);
+        self.config = config;
+    }
 }
 
 impl Application for MyApplication {
@@ -61,26 +225,34 @@ impl Application for MyApplication {
         let sound_thread = flags.sound_thread;
         let sound_thread_rpc = SoundThreadRpc::new(&sound_thread);
         let gamepad_thread = flags.gamepad_thread;
+        let midi_thread = flags.midi_thread;
+        let config_watch_thread = flags.config_watch_thread;
+        let osc_thread = flags.osc_thread;
+        let http_thread = flags.http_thread;
+        let session_record_thread = flags.session_record_thread;
+        let session_replay_thread = flags.session_replay_thread;
         let sound_thread_event_receiver = flags.sound_thread_event_receiver;
-        let switches = &config.switches;
-
-        let play_buttons = switches
-            .into_iter()
-            .map(|switch_config| PlayButtonState {
-                switch_title: switch_config.title.clone(),
-                key_held_down: false,
-                last_played_at: None,
-            })
-            .collect();
+        let play_buttons = make_play_buttons(&config);
+        let now = Instant::now();
+        let scanner = make_scanner(&config, now);
 
         let app = MyApplication {
             config,
             sound_thread: Some(sound_thread),
             sound_thread_rpc,
             gamepad_thread: Some(gamepad_thread),
+            midi_thread: Some(midi_thread),
+            config_watch_thread,
+            osc_thread,
+            http_thread,
+            session_record_thread,
+            session_replay_thread,
             sound_thread_event_receiver,
-            now: Instant::now(),
+            now,
             play_buttons,
+            scanner,
+            recording: None,
+            audio_output_notice: None,
         };
 
         (app, Command::none())
@@ -95,7 +267,12 @@ impl Application for MyApplication {
             Message::Tick(now) => {
                 self.now = now;
 
-                for event in self.sound_thread_event_receiver.try_iter() {
+                if let Some(scanner) = &mut self.scanner {
+                    scanner.tick(now);
+                }
+
+                let events: Vec<SoundThreadEvent> = self.sound_thread_event_receiver.try_iter().collect();
+                for event in events {
                     match event {
                         SoundThreadEvent::PlayedSample(bank_sample_ref) => {
                             let switches = self.config.find_switch_play_for_sample(bank_sample_ref);
@@ -105,39 +282,78 @@ impl Application for MyApplication {
                                 button_state.last_played_at = Some(self.now);
                             }
                         },
+                        SoundThreadEvent::ConfigReloaded(config) => {
+                            self.config_reloaded(*config);
+                        },
+                        SoundThreadEvent::PressFiltered { switch_ref, filtered_count } => {
+                            self.play_buttons[switch_ref.switch_index].filtered_presses = filtered_count;
+                        },
+                        SoundThreadEvent::Scan(action) => {
+                            self.scan(action);
+                        },
+                        SoundThreadEvent::RecordingStarted(path) => {
+                            self.recording = Some(path);
+                        },
+                        SoundThreadEvent::RecordingStopped(_) => {
+                            self.recording = None;
+                        },
+                        SoundThreadEvent::AudioOutputLost => {
+                            self.audio_output_notice = Some(String::from("The sound device has been disconnected, waiting for it to return"));
+                        },
+                        SoundThreadEvent::AudioOutputRestored(device_name) => {
+                            self.audio_output_notice = Some(match device_name {
+                                Some(device_name) => format!("Playing on the sound device \"{}\"", device_name),
+                                None => String::from("Playing sound again"),
+                            });
+                        },
+                        SoundThreadEvent::SwitchInputReceived { .. } |
+                        SoundThreadEvent::SwitchPressed(_) |
+                        SoundThreadEvent::SwitchReleased(_) |
+                        SoundThreadEvent::StoppedSample(_) |
+                        SoundThreadEvent::StoppedSounds => {},
                     }
                 }
             },
             Message::PlayButtonPressed(index) => {
                 // for now a button is created for each SwitchConfig, so we can just use the button
                 // index to look up the SwitchConfig
-                let switch_ref = self.config.switches[index].switch_ref;
-                self.switch_pressed(switch_ref);
+                // iced only reports the click, not how long the button was held down
+                self.switch_clicked(self.config.switches[index].switch_ref, InputSource::Gui);
+            },
+            Message::RecordButtonPressed => {
+                let result = match self.recording {
+                    None => self.sound_thread_rpc.start_recording(recording_path(&self.config.recording_directory(), SystemTime::now())),
+                    Some(_) => self.sound_thread_rpc.stop_recording(),
+                };
+                if let Err(err) = result {
+                    eprintln!("Error sending recording operation to SoundThread {}", err);
+                }
             },
             Message::EventOccurred(Event::Window(window::Event::CloseRequested)) => {
                 println!("Close requested");
                 self.before_close();
                 return window::close();
             },
-            Message::EventOccurred(Event::Keyboard(keyboard::Event::KeyPressed { key_code, modifiers: _ })) => {
-                if let Some(switch_config) = self.config.find_switch_for_keyboard_key(key_code) {
-                    let switch_ref = switch_config.switch_ref;
-                    let button_state = &mut self.play_buttons[switch_ref.switch_index];
-                    let was_held_down = button_state.key_held_down;
-                    button_state.key_held_down = true;
-
-                    if !was_held_down {
-                        self.switch_pressed(switch_ref);
-                    }
-                }
+            Message::EventOccurred(Event::Keyboard(keyboard::Event::KeyPressed { key_code, modifiers })) => {
+                let switch_refs = self.config.find_switches_for_keyboard_key(key_code, modifiers).clone();
+                self.trigger_pressed(HeldTrigger::Key(key_code), &switch_refs);
             },
             Message::EventOccurred(Event::Keyboard(keyboard::Event::KeyReleased { key_code, modifiers: _ })) => {
                 println!("Keyboard release {:?}", key_code);
-
-                if let Some(switch_config) = self.config.find_switch_for_keyboard_key(key_code) {
-                    let button_state = &mut self.play_buttons[switch_config.switch_ref.switch_index];
-                    button_state.key_held_down = false;
-                }
+                // The modifiers might have been released before the key itself, so release this
+                // key for every switch
+                self.trigger_released_everywhere(HeldTrigger::Key(key_code));
+            },
+            Message::EventOccurred(Event::Mouse(mouse::Event::ButtonPressed(button))) => {
+                let switch_refs = self.config.find_switches_for_mouse_button(button).clone();
+                self.trigger_pressed(HeldTrigger::MouseButton(button), &switch_refs);
+            },
+            Message::EventOccurred(Event::Mouse(mouse::Event::ButtonReleased(button))) => {
+                let switch_refs = self.config.find_switches_for_mouse_button(button).clone();
+                self.trigger_released(HeldTrigger::MouseButton(button), &switch_refs);
+            },
+            Message::CapturedMouseEvent(event) if !self.config.ignore_gui_clicks => {
+                return self.update(Message::EventOccurred(Event::Mouse(event)));
             },
             _ => {},
         }
@@ -147,35 +363,72 @@ impl Application for MyApplication {
 
     fn subscription(&self) -> Subscription<Message> {
         Subscription::batch([
-            iced::subscription::events().map(Message::EventOccurred),
+            iced::subscription::events_with(|event, status| match (event, status) {
+                (event, event::Status::Ignored) => Some(Message::EventOccurred(event)),
+                (Event::Mouse(event), event::Status::Captured) => Some(Message::CapturedMouseEvent(event)),
+                _ => None,
+            }),
             iced_time_every(Duration::from_millis(8)).map(|_| Message::Tick(Instant::now())),
         ])
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         let now = self.now;
         let play_buttons = self.play_buttons.iter();
 
+        let columns = self.config.scanning.as_ref().map_or(1, |scanning| scanning.columns.max(1));
+
         let mut column = Column::new()
             .padding(20)
             .align_items(Alignment::Center);
+        let mut row = Row::new();
 
-        let mut index = 0;
-        for play_button in play_buttons {
+        for (index, play_button) in play_buttons.enumerate() {
             let stylesheet = Box::new(ButtonStyleSheet {
                 last_played_ago: play_button.last_played_at.map(|ago| now.duration_since(ago)),
+                scan_highlighted: self.scanner.as_ref().is_some_and(|scanner| scanner.is_highlighted(index)),
             });
 
-            let button = button(text(&play_button.switch_title))
+            let label = match play_button.filtered_presses {
+                0 => play_button.switch_title.clone(),
+                filtered => format!("{} ({} filtered)", play_button.switch_title, filtered),
+            };
+
+            let button = button(text(label))
                 .width(Length::Fill)
                 .style(theme::Button::Custom(stylesheet))
                 .on_press(Message::PlayButtonPressed(index));
 
-            column = column.push(
+            row = row.push(
                 Container::new(button)
                 .width(Length::Fill).padding(5)
             );
-            index += 1;
+
+            if (index + 1) % columns == 0 {
+                column = column.push(row);
+                row = Row::new();
+            }
+        }
+
+        if self.play_buttons.len() % columns != 0 {
+            column = column.push(row);
+        }
+
+        if let Some(notice) = &self.audio_output_notice {
+            column = column.push(Container::new(text(notice)).padding(5));
+        }
+
+        // only shown if recordings have been configured, or if a recording has been started using
+        // `microswitch record`
+        if self.config.recording.is_some() || self.recording.is_some() {
+            let label = match &self.recording {
+                None => String::from("Start recording"),
+                Some(path) => format!("Stop recording ({})", path.file_name().unwrap_or_default().to_string_lossy()),
+            };
+            let record_button = button(text(label))
+                .style(theme::Button::Secondary)
+                .on_press(Message::RecordButtonPressed);
+            column = column.push(Container::new(record_button).padding(5));
         }
 
         column.into()
@@ -188,14 +441,7 @@ fn make_icon() -> icon::Icon {
     icon::from_rgba(bytes, 32, 32).expect("Failed to load window icon")
 }
 
-pub fn run_application(
-    config: &Config,
-    sound_thread: SoundThread,
-    gamepad_thread: GamepadThread,
-    sound_thread_event_receiver: Receiver<SoundThreadEvent>,
-) -> Result<(), Error> {
-    let config = config.clone();
-    let flags = ApplicationFlags { config, sound_thread, gamepad_thread, sound_thread_event_receiver };
+pub fn run_application(flags: ApplicationFlags) -> Result<(), Error> {
     let mut settings = Settings::with_flags(flags);
 
     // this we will handle ourselves so that we can do cleanup (Event::CloseRequested)
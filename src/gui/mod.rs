@@ -1,5 +1,6 @@
 pub mod application;
 pub mod types;
 mod executor;
+mod scanner;
 mod style;
 
@@ -0,0 +1,206 @@
+use std::time::{Duration, Instant};
+use crate::config::{ScanMode, ScanningConfig};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScanHighlight {
+    /// All buttons in this row are highlighted, selecting it starts scanning the buttons within it
+    Row(usize),
+    /// The button at this index is highlighted
+    Button(usize),
+}
+
+/// Moves a highlight through the play buttons, so that a single switch can activate any of them.
+/// With more than one column, the rows are scanned first, and then the buttons within the
+/// selected row.
+#[derive(Debug)]
+pub struct Scanner {
+    mode: ScanMode,
+    interval: Duration,
+    columns: usize,
+    /// For each play button, whether it can be highlighted
+    scannable: Vec<bool>,
+    /// The selected row, while scanning the buttons within it
+    row: Option<usize>,
+    highlight: Option<ScanHighlight>,
+    /// When the highlight moves by itself, only in the `auto` mode
+    next_step_at: Option<Instant>,
+}
+
+impl Scanner {
+    pub fn new(config: &ScanningConfig, scannable: Vec<bool>, now: Instant) -> Self {
+        let mut scanner = Scanner {
+            mode: config.mode,
+            interval: Duration::from_millis(config.interval_ms),
+            columns: config.columns.max(1),
+            scannable,
+            row: None,
+            highlight: None,
+            next_step_at: None,
+        };
+        scanner.highlight = scanner.candidates().first().copied();
+        scanner.restart_timer(now);
+        scanner
+    }
+
+    fn is_scanning_rows(&self) -> bool {
+        self.columns > 1
+    }
+
+    fn row_of(&self, index: usize) -> usize {
+        index / self.columns
+    }
+
+    /// Everything that may be highlighted next, in order
+    fn candidates(&self) -> Vec<ScanHighlight> {
+        let buttons = self.scannable
+            .iter()
+            .enumerate()
+            .filter(|(_, &scannable)| scannable)
+            .map(|(index, _)| index);
+
+        if !self.is_scanning_rows() {
+            return buttons.map(ScanHighlight::Button).collect();
+        }
+
+        match self.row {
+            Some(row) => buttons
+                .filter(|&index| self.row_of(index) == row)
+                .map(ScanHighlight::Button)
+                .collect(),
+            None => {
+                let mut rows: Vec<usize> = buttons.map(|index| self.row_of(index)).collect();
+                rows.dedup();
+                rows.into_iter().map(ScanHighlight::Row).collect()
+            },
+        }
+    }
+
+    fn restart_timer(&mut self, now: Instant) {
+        self.next_step_at = match self.mode {
+            ScanMode::Auto => Some(now + self.interval),
+            ScanMode::Step => None,
+        };
+    }
+
+    pub fn highlight(&self) -> Option<ScanHighlight> {
+        self.highlight
+    }
+
+    pub fn is_highlighted(&self, index: usize) -> bool {
+        match self.highlight() {
+            Some(ScanHighlight::Row(row)) => self.scannable[index] && self.row_of(index) == row,
+            Some(ScanHighlight::Button(button)) => button == index,
+            None => false,
+        }
+    }
+
+    /// Move the highlight to the next button or row. After the last button of the selected row,
+    /// the rows are scanned again.
+    pub fn step(&mut self, now: Instant) {
+        let candidates = self.candidates();
+        let position = self.highlight.and_then(|highlight| candidates.iter().position(|&candidate| candidate == highlight));
+
+        self.highlight = match (position, self.row) {
+            (Some(position), _) if position + 1 < candidates.len() => Some(candidates[position + 1]),
+            (_, Some(row)) => {
+                self.row = None;
+                Some(ScanHighlight::Row(row))
+            },
+            _ => candidates.first().copied(),
+        };
+        self.restart_timer(now);
+    }
+
+    /// Returns the index of the play button that should be activated, if any
+    pub fn select(&mut self, now: Instant) -> Option<usize> {
+        let selected = match self.highlight? {
+            ScanHighlight::Row(row) => {
+                self.row = Some(row);
+                self.highlight = self.candidates().first().copied();
+                None
+            },
+            ScanHighlight::Button(index) => {
+                if self.row.take().is_some() {
+                    self.highlight = Some(ScanHighlight::Row(self.row_of(index)));
+                }
+                Some(index)
+            },
+        };
+
+        self.restart_timer(now);
+        selected
+    }
+
+    /// Moves the highlight in the `auto` mode, once it has been highlighted for long enough
+    pub fn tick(&mut self, now: Instant) {
+        if self.next_step_at.is_some_and(|next_step_at| now >= next_step_at) {
+            self.step(now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use crate::config::{ScanMode, ScanningConfig};
+    use crate::gui::scanner::{Scanner, ScanHighlight};
+    use pretty_assertions::{assert_eq};
+
+    #[test]
+    fn auto_scanning() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let config = ScanningConfig { mode: ScanMode::Auto, interval_ms: 1000, columns: 1 };
+        // the second button is the select switch itself
+        let mut scanner = Scanner::new(&config, vec![true, false, true, true], at(0));
+
+        assert_eq!(scanner.highlight(), Some(ScanHighlight::Button(0)));
+        scanner.tick(at(999));
+        assert_eq!(scanner.highlight(), Some(ScanHighlight::Button(0)));
+        scanner.tick(at(1000));
+        assert_eq!(scanner.highlight(), Some(ScanHighlight::Button(2)));
+        scanner.tick(at(2000));
+        assert_eq!(scanner.highlight(), Some(ScanHighlight::Button(3)));
+        scanner.tick(at(3000));
+        assert_eq!(scanner.highlight(), Some(ScanHighlight::Button(0)));
+
+        // selecting restarts the interval
+        assert_eq!(scanner.select(at(3500)), Some(0));
+        scanner.tick(at(4000));
+        assert_eq!(scanner.highlight(), Some(ScanHighlight::Button(0)));
+        scanner.tick(at(4500));
+        assert_eq!(scanner.highlight(), Some(ScanHighlight::Button(2)));
+    }
+
+    #[test]
+    fn row_column_step_scanning() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let config = ScanningConfig { mode: ScanMode::Step, interval_ms: 1000, columns: 2 };
+        let mut scanner = Scanner::new(&config, vec![true, true, true, true, true, false], at(0));
+
+        // does not move by itself
+        scanner.tick(at(5000));
+        assert_eq!(scanner.highlight(), Some(ScanHighlight::Row(0)));
+        assert!(scanner.is_highlighted(1));
+        assert!(!scanner.is_highlighted(2));
+
+        scanner.step(at(5000));
+        assert_eq!(scanner.highlight(), Some(ScanHighlight::Row(1)));
+        assert_eq!(scanner.select(at(5000)), None);
+        assert_eq!(scanner.highlight(), Some(ScanHighlight::Button(2)));
+        scanner.step(at(5000));
+        assert_eq!(scanner.select(at(5000)), Some(3));
+        assert_eq!(scanner.highlight(), Some(ScanHighlight::Row(1)));
+
+        // stepping past the last button of a row returns to the rows
+        scanner.step(at(5000));
+        assert_eq!(scanner.highlight(), Some(ScanHighlight::Row(2)));
+        assert_eq!(scanner.select(at(5000)), None);
+        assert_eq!(scanner.highlight(), Some(ScanHighlight::Button(4)));
+        scanner.step(at(5000));
+        assert_eq!(scanner.highlight(), Some(ScanHighlight::Row(2)));
+        scanner.step(at(5000));
+        assert_eq!(scanner.highlight(), Some(ScanHighlight::Row(0)));
+    }
+}
@@ -9,12 +9,17 @@ const BUTTON_PLAY_FEEDBACK_DURATION: u128 = 1000;
 const BUTTON_PLAY_FEEDBACK_FROM: (f32, f32, f32) = (51.0 / 255.0, 147.0 / 255.0, 129.9 / 255.0);
 const BUTTON_PLAY_FEEDBACK_TO: (f32, f32, f32) = (0.87, 0.87, 0.87);
 
+// The border of the buttons highlighted by scanning
+const BUTTON_SCAN_HIGHLIGHT_WIDTH: f32 = 4.0;
+const BUTTON_SCAN_HIGHLIGHT_COLOR: (f32, f32, f32) = (0.95, 0.6, 0.0);
+
 fn interpolate(from: f32, to: f32, progress: f32) -> f32 {
     (to - from) * progress + from
 }
 
 pub struct ButtonStyleSheet {
     pub last_played_ago: Option<Duration>,
+    pub scan_highlighted: bool,
 }
 
 impl StyleSheet for ButtonStyleSheet {
@@ -40,12 +45,19 @@ impl StyleSheet for ButtonStyleSheet {
             interpolate(BUTTON_PLAY_FEEDBACK_FROM.2, BUTTON_PLAY_FEEDBACK_TO.2, play_feedback_progress),
         );
 
+        let (border_width, border_color) = if self.scan_highlighted {
+            (BUTTON_SCAN_HIGHLIGHT_WIDTH, Color::from_rgb(BUTTON_SCAN_HIGHLIGHT_COLOR.0, BUTTON_SCAN_HIGHLIGHT_COLOR.1, BUTTON_SCAN_HIGHLIGHT_COLOR.2))
+        }
+        else {
+            (1.0, [0.7, 0.7, 0.7].into())
+        };
+
         Appearance {
             shadow_offset: Vector::new(0.0, 0.0),
             background: Some(Background::Color(background_color)),
             border_radius: 2.0.into(),
-            border_width: 1.0,
-            border_color: [0.7, 0.7, 0.7].into(),
+            border_width,
+            border_color,
             text_color: Color::BLACK,
         }
     }
@@ -1,17 +1,42 @@
+use std::collections::HashSet;
 use std::time::Instant;
-use iced::Event;
+use iced::{Event, keyboard, mouse};
+use crate::sound_thread::InputSource;
+
+/// A keyboard key or mouse button which is currently held down
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum HeldTrigger {
+    Key(keyboard::KeyCode),
+    MouseButton(mouse::Button),
+}
+
+impl HeldTrigger {
+    pub fn source(&self) -> InputSource {
+        match self {
+            HeldTrigger::Key(_) => InputSource::Keyboard,
+            HeldTrigger::MouseButton(_) => InputSource::Mouse,
+        }
+    }
+}
 
 pub struct PlayButtonState {
     pub switch_title: String,
-    /// Is the keyboard key currently down? A sample should only play for a single press, without repeating.
-    pub key_held_down: bool,
+    /// The keyboard keys and mouse buttons of this switch which are currently down. A sample should
+    /// only play for a single press, without repeating.
+    pub held_triggers: HashSet<HeldTrigger>,
     /// The last time the corresponding sample (the one specified by config.switches[button_index].play.unwrap().bank_sample_ref) has been played.
     pub last_played_at: Option<Instant>,
+    /// How many presses have been ignored by the input filter of the switch
+    pub filtered_presses: u64,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     Tick(Instant),
     EventOccurred(Event),
+    /// A mouse event that has been handled by a widget, such as a click on a play button
+    CapturedMouseEvent(mouse::Event),
     PlayButtonPressed(usize), // (index)
+    /// Start or stop recording
+    RecordButtonPressed,
 }
@@ -0,0 +1,520 @@
+use crate::config::{BankSampleRef, Config, HttpConfig, SwitchRef};
+use crate::error::{readable_thread_panic_error, HttpThreadError};
+use crate::sound_thread::{InputSource, SoundThreadEvent, SoundThreadRpc, SwitchCommand};
+use serde_json::{json, Value};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::mpsc::{Receiver, Sender, SyncSender, TryRecvError};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Method, ReadWrite, Request, Response, Server, StatusCode};
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+/// How long to wait for a request before checking for events and operations
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The amount of messages that may be waiting to be sent to a WebSocket client. A client that
+/// falls this far behind, for example because it stopped reading, is disconnected.
+const WEBSOCKET_QUEUE_SIZE: usize = 256;
+
+/// A page with a button for every switch, served at `/`
+const REMOTE_PAGE: &str = include_str!("http_remote.html");
+
+#[derive(Debug)]
+enum HttpOperation {
+    Stop,
+}
+
+/// The requests understood by the HttpThread
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Route {
+    /// `GET /`
+    RemotePage,
+    /// `GET /api/switches`
+    Switches,
+    /// `POST /api/switches/{index}/press`, `.../release` or `.../click`
+    Switch(SwitchCommand, usize),
+    /// `GET /api/events`, upgraded to a WebSocket
+    Events,
+    NotFound,
+    MethodNotAllowed,
+}
+
+fn route(method: &Method, url: &str) -> Route {
+    let path = url.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    let (expected_method, route) = match segments.as_slice() {
+        [""] => (Method::Get, Route::RemotePage),
+        ["api", "switches"] => (Method::Get, Route::Switches),
+        ["api", "events"] => (Method::Get, Route::Events),
+        ["api", "switches", index, command] => {
+            let command = match *command {
+                "press" => SwitchCommand::Press,
+                "release" => SwitchCommand::Release,
+                "click" => SwitchCommand::Click,
+                _ => return Route::NotFound,
+            };
+            let Ok(index) = index.parse() else {
+                return Route::NotFound;
+            };
+            (Method::Post, Route::Switch(command, index))
+        },
+        _ => return Route::NotFound,
+    };
+
+    if *method == expected_method { route } else { Route::MethodNotAllowed }
+}
+
+fn switches_json(config: &Config) -> Value {
+    config.switches
+        .iter()
+        .map(|switch_config| json!({
+            "index": switch_config.switch_ref.switch_index,
+            "title": switch_config.title,
+        }))
+        .collect()
+}
+
+/// Returns the WebSocket message that is sent for an event, or None if the event is not sent
+fn event_json(config: &Config, event: &SoundThreadEvent) -> Option<Value> {
+    let switch_json = |event: &str, switch_ref: SwitchRef| json!({
+        "event": event,
+        "switch": switch_ref.switch_index,
+        "title": config.switch(switch_ref).title,
+    });
+    let sample_json = |event: &str, bank_sample_ref: BankSampleRef| {
+        let (bank_config, sample_config) = config.sample(bank_sample_ref);
+        json!({
+            "event": event,
+            "bank": bank_config.id,
+            "sample": sample_config.id,
+        })
+    };
+
+    let value = match event {
+        SoundThreadEvent::SwitchPressed(switch_ref) => switch_json("pressed", *switch_ref),
+        SoundThreadEvent::SwitchReleased(switch_ref) => switch_json("released", *switch_ref),
+        SoundThreadEvent::PlayedSample(bank_sample_ref) => {
+            let mut value = sample_json("played", *bank_sample_ref);
+            let switches: Vec<usize> = config.find_switch_play_for_sample(*bank_sample_ref)
+                .iter()
+                .map(|switch_ref| switch_ref.switch_index)
+                .collect();
+            value["switches"] = json!(switches);
+            value
+        },
+        SoundThreadEvent::StoppedSample(bank_sample_ref) => sample_json("stopped", *bank_sample_ref),
+        SoundThreadEvent::StoppedSounds => json!({ "event": "stopped" }),
+        SoundThreadEvent::ConfigReloaded(config) => json!({
+            "event": "reloaded",
+            "switches": switches_json(config),
+        }),
+        SoundThreadEvent::PressFiltered { switch_ref, filtered_count } => {
+            let mut value = switch_json("filtered", *switch_ref);
+            value["filteredCount"] = json!(filtered_count);
+            value
+        },
+        SoundThreadEvent::SwitchInputReceived { .. } |
+        SoundThreadEvent::Scan(_) |
+        SoundThreadEvent::RecordingStarted(_) |
+        SoundThreadEvent::RecordingStopped(_) |
+        SoundThreadEvent::AudioOutputLost |
+        SoundThreadEvent::AudioOutputRestored(_) => return None,
+    };
+
+    Some(value)
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).expect("Invalid HTTP header")
+}
+
+fn header_value(request: &Request, field: &str) -> Option<String> {
+    request.headers()
+        .iter()
+        .find(|header| header.field.equiv(field))
+        .map(|header| header.value.as_str().to_owned())
+}
+
+/// True if the Host header of a request names the address the server is listening on, or
+/// localhost. This prevents web pages from reaching the server through DNS rebinding.
+fn is_allowed_host(host: &str, local_addr: SocketAddr) -> bool {
+    let is_allowed_ip = |ip: IpAddr| {
+        ip == local_addr.ip() || ip.is_loopback() || local_addr.ip().is_unspecified()
+    };
+
+    if let Ok(address) = host.parse::<SocketAddr>() {
+        return is_allowed_ip(address.ip()) && address.port() == local_addr.port();
+    }
+
+    // without a port, such as `localhost` or `[::1]`, the default port is used
+    let (name, port) = match host.rsplit_once(':') {
+        Some((name, port)) if !host.ends_with(']') => match port.parse::<u16>() {
+            Ok(port) => (name, port),
+            Err(_) => return false,
+        },
+        _ => (host, 80),
+    };
+
+    if port != local_addr.port() {
+        return false;
+    }
+
+    let name = name.trim_start_matches('[').trim_end_matches(']');
+    name.eq_ignore_ascii_case("localhost") || name.parse().is_ok_and(is_allowed_ip)
+}
+
+/// True if a request made by a web page comes from the remote control page itself. Requests that
+/// are not made by a web page have no Origin header.
+fn is_allowed_origin(origin: Option<&str>, host: &str) -> bool {
+    origin.map_or(true, |origin| origin.eq_ignore_ascii_case(&format!("http://{}", host)))
+}
+
+/// A client of `/api/events`. Messages are written by a thread of its own, so that a client which
+/// does not read its messages can not block the HttpThread.
+struct WebSocketClient {
+    sender: SyncSender<String>,
+}
+
+impl WebSocketClient {
+    fn new(mut websocket: WebSocket<Box<dyn ReadWrite + Send>>) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<String>(WEBSOCKET_QUEUE_SIZE);
+
+        thread::spawn(move || {
+            for text in receiver {
+                if websocket.send(Message::Text(text)).is_err() {
+                    return;
+                }
+            }
+
+            // the HttpThread has dropped this client
+            let _ = websocket.close(None);
+            let _ = websocket.flush();
+        });
+
+        Self { sender }
+    }
+
+    /// Returns false if the client has gone away, or if it is not keeping up
+    fn send(&self, text: &str) -> bool {
+        self.sender.try_send(text.to_owned()).is_ok()
+    }
+}
+
+struct HttpThreadBody {
+    config: Config,
+    sound_thread_rpc: SoundThreadRpc,
+    server: Server,
+    local_addr: SocketAddr,
+    event_receiver: Receiver<SoundThreadEvent>,
+    operation_receiver: Receiver<HttpOperation>,
+    /// The clients of `/api/events`
+    websockets: Vec<WebSocketClient>,
+}
+
+impl HttpThreadBody {
+    fn is_allowed(&self, request: &Request) -> bool {
+        let Some(host) = header_value(request, "Host") else {
+            return false;
+        };
+
+        is_allowed_host(&host, self.local_addr) && is_allowed_origin(header_value(request, "Origin").as_deref(), &host)
+    }
+
+    fn handle_request(&mut self, request: Request) -> Result<(), HttpThreadError> {
+        if !self.is_allowed(&request) {
+            println!("HTTP request for {} rejected because of its Host or Origin", request.url());
+            request.respond(Response::empty(403))?;
+            return Ok(());
+        }
+
+        match route(request.method(), request.url()) {
+            Route::RemotePage => {
+                let response = Response::from_string(REMOTE_PAGE)
+                    .with_header(header("Content-Type", "text/html; charset=utf-8"));
+                request.respond(response)?;
+            },
+            Route::Switches => {
+                let response = Response::from_string(switches_json(&self.config).to_string())
+                    .with_header(header("Content-Type", "application/json"));
+                request.respond(response)?;
+            },
+            Route::Switch(command, index) => {
+                let Some(switch_config) = self.config.switches.get(index) else {
+                    request.respond(Response::empty(404))?;
+                    return Ok(());
+                };
+
+                println!("HTTP {:?} switch \"{}\"", command, switch_config.title);
+                if let Err(err) = self.sound_thread_rpc.switch_command(command, switch_config.switch_ref, InputSource::Http) {
+                    eprintln!("Error sending {:?} {:?} to sound thread {}", command, switch_config.switch_ref, err);
+                    request.respond(Response::empty(500))?;
+                    return Err(HttpThreadError::SendSoundThread);
+                }
+                request.respond(Response::empty(204))?;
+            },
+            Route::Events => self.accept_websocket(request)?,
+            Route::NotFound => request.respond(Response::empty(404))?,
+            Route::MethodNotAllowed => request.respond(Response::empty(405))?,
+        }
+
+        Ok(())
+    }
+
+    fn accept_websocket(&mut self, request: Request) -> Result<(), HttpThreadError> {
+        let Some(key) = header_value(&request, "Sec-WebSocket-Key") else {
+            request.respond(Response::empty(400))?;
+            return Ok(());
+        };
+
+        let response = Response::new_empty(StatusCode(101))
+            .with_header(header("Upgrade", "websocket"))
+            .with_header(header("Connection", "Upgrade"))
+            .with_header(header("Sec-WebSocket-Accept", &derive_accept_key(key.as_bytes())));
+
+        let stream = request.upgrade("websocket", response);
+        self.websockets.push(WebSocketClient::new(WebSocket::from_raw_socket(stream, Role::Server, None)));
+        Ok(())
+    }
+
+    fn broadcast(&mut self, event: &SoundThreadEvent) {
+        let Some(value) = event_json(&self.config, event) else {
+            return;
+        };
+
+        let text = value.to_string();
+        // forget about clients that have gone away or that are not keeping up
+        self.websockets.retain(|websocket| websocket.send(&text));
+    }
+
+    fn thread_body(mut self) -> Result<(), HttpThreadError> {
+        loop {
+            match self.server.recv_timeout(POLL_INTERVAL) {
+                Ok(Some(request)) => {
+                    if let Err(err) = self.handle_request(request) {
+                        eprintln!("Error while handling HTTP request {:?}", err);
+                    }
+                },
+                Ok(None) => {},
+                Err(err) => eprintln!("Failed to receive HTTP request: {}", err),
+            }
+
+            let events: Vec<SoundThreadEvent> = self.event_receiver.try_iter().collect();
+            for event in events {
+                // the events that follow refer to the switches and samples of the new config
+                if let SoundThreadEvent::ConfigReloaded(config) = &event {
+                    self.config = (**config).clone();
+                }
+
+                self.broadcast(&event);
+            }
+
+            match self.operation_receiver.try_recv() {
+                Ok(HttpOperation::Stop) => {
+                    return Ok(());
+                },
+                Err(TryRecvError::Empty) => {
+                    // continue the loop
+                },
+                Err(err @ TryRecvError::Disconnected) => {
+                    return Err(HttpThreadError::from(err));
+                },
+            }
+        }
+    }
+}
+
+/// The HttpThread serves a remote control: a list of the switches, endpoints to press them, and a
+/// WebSocket stream of SoundThreadEvents. Changes to the `http` section of the config are only
+/// applied after a restart.
+pub struct HttpThread {
+    operation_sender: Sender<HttpOperation>,
+    handle: JoinHandle<()>,
+    local_addr: SocketAddr,
+}
+
+impl HttpThread {
+    pub fn new(config: &Config, http_config: &HttpConfig, sound_thread_rpc: SoundThreadRpc) -> Result<Self, HttpThreadError> {
+        let address = http_config.listen_addr;
+        let server = Server::http(address)
+            .map_err(|err| HttpThreadError::Bind { address, message: err.to_string() })?;
+        let local_addr = server.server_addr().to_ip().unwrap_or(address);
+        println!("Remote control available at http://{}/", local_addr);
+
+        let event_receiver = sound_thread_rpc.subscribe()
+            .map_err(|_| HttpThreadError::SendSoundThread)?;
+        let (operation_sender, operation_receiver) = mpsc::channel();
+
+        let body = HttpThreadBody {
+            config: config.clone(),
+            sound_thread_rpc,
+            server,
+            local_addr,
+            event_receiver,
+            operation_receiver,
+            websockets: Vec::new(),
+        };
+
+        let handle = thread::spawn(move || {
+            body.thread_body()
+                .expect("Error during HttpThreadBody.thread_body()");
+        });
+
+        Ok(Self { operation_sender, handle, local_addr })
+    }
+
+    /// The address that the server is listening on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn stop(self) -> Result<(), HttpThreadError> {
+        if let Err(err) = self.operation_sender.send(HttpOperation::Stop) {
+            eprintln!("Failed to send stop operation to HttpThread: {}", err);
+            // Still try to join in this case, this will probably give us more error details
+        }
+        let handle = self.handle;
+
+        match handle.join() {
+            Ok(_) => Ok(()),
+            Err(join_error) => {
+                let join_error_str = readable_thread_panic_error(&join_error);
+                Err(HttpThreadError::JoinPanic {
+                    join_error,
+                    join_error_str,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use serde_json::{json, Value};
+    use tiny_http::Method;
+    use tungstenite::Message;
+    use crate::config::{BankSampleRef, Config};
+    use crate::http_thread::{event_json, is_allowed_host, is_allowed_origin, route, HttpThread, Route};
+    use crate::sound_thread::{Operation, SoundThreadEvent, SoundThreadRpc, SwitchCommand};
+    use crate::test_util::drums_config;
+    use pretty_assertions::{assert_eq};
+
+    fn test_config() -> Config {
+        drums_config(r###"
+http:
+  listen: "127.0.0.1:0"
+"###)
+    }
+
+    #[test]
+    fn routes() {
+        assert_eq!(route(&Method::Get, "/"), Route::RemotePage);
+        assert_eq!(route(&Method::Get, "/api/switches"), Route::Switches);
+        assert_eq!(route(&Method::Get, "/api/switches/?pretty"), Route::Switches);
+        assert_eq!(route(&Method::Get, "/api/events"), Route::Events);
+        assert_eq!(route(&Method::Post, "/api/switches/3/press"), Route::Switch(SwitchCommand::Press, 3));
+        assert_eq!(route(&Method::Post, "/api/switches/0/release"), Route::Switch(SwitchCommand::Release, 0));
+        assert_eq!(route(&Method::Post, "/api/switches/1/click"), Route::Switch(SwitchCommand::Click, 1));
+        assert_eq!(route(&Method::Get, "/api/switches/1/click"), Route::MethodNotAllowed);
+        assert_eq!(route(&Method::Post, "/api/switches/-1/click"), Route::NotFound);
+        assert_eq!(route(&Method::Post, "/api/switches/1/hold"), Route::NotFound);
+        assert_eq!(route(&Method::Get, "/favicon.ico"), Route::NotFound);
+    }
+
+    #[test]
+    fn hosts_and_origins() {
+        let local = "127.0.0.1:8080".parse().unwrap();
+        assert!(is_allowed_host("127.0.0.1:8080", local));
+        assert!(is_allowed_host("localhost:8080", local));
+        assert!(is_allowed_host("[::1]:8080", local));
+        assert!(!is_allowed_host("127.0.0.1:9000", local));
+        assert!(!is_allowed_host("evil.example:8080", local));
+        assert!(!is_allowed_host("192.168.1.10:8080", local));
+        assert!(!is_allowed_host("localhost", local));
+
+        let any = "0.0.0.0:80".parse().unwrap();
+        assert!(is_allowed_host("192.168.1.10", any));
+        assert!(is_allowed_host("192.168.1.10:80", any));
+        assert!(!is_allowed_host("evil.example", any));
+
+        assert!(is_allowed_origin(None, "127.0.0.1:8080"));
+        assert!(is_allowed_origin(Some("http://127.0.0.1:8080"), "127.0.0.1:8080"));
+        assert!(!is_allowed_origin(Some("http://evil.example"), "127.0.0.1:8080"));
+    }
+
+    #[test]
+    fn events() {
+        let config = test_config();
+
+        assert_eq!(event_json(&config, &SoundThreadEvent::PlayedSample(BankSampleRef::default())), Some(json!({
+            "event": "played",
+            "bank": "drums",
+            "sample": "kick",
+            "switches": [0],
+        })));
+        assert_eq!(event_json(&config, &SoundThreadEvent::StoppedSounds), Some(json!({ "event": "stopped" })));
+    }
+
+    /// Send a request without a body and return the complete response
+    fn http_request(address: &str, method: &str, path: &str) -> String {
+        http_request_with_host(address, address, method, path)
+    }
+
+    fn http_request_with_host(address: &str, host: &str, method: &str, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", method, path, host).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn switches_are_pressed_and_events_are_streamed_on_localhost() {
+        let config = test_config();
+        let (operation_sender, operation_receiver) = mpsc::channel();
+        let http_thread = HttpThread::new(&config, config.http.as_ref().unwrap(), SoundThreadRpc::from_sender(operation_sender)).unwrap();
+        let address = http_thread.local_addr().to_string();
+        let receive_operation = || operation_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        let event_sender = match receive_operation() {
+            Operation::Subscribe { event_sender } => event_sender,
+            operation => panic!("Unexpected operation {:?}", operation),
+        };
+
+        let response = http_request(&address, "GET", "/api/switches");
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        let body: Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body, json!([{ "index": 0, "title": "Kick" }, { "index": 1, "title": "Stop" }]));
+
+        let response = http_request(&address, "POST", "/api/switches/1/press");
+        assert!(response.starts_with("HTTP/1.1 204"), "{}", response);
+        match receive_operation() {
+            Operation::SwitchPressed { switch_ref, .. } => assert_eq!(switch_ref.switch_index, 1),
+            operation => panic!("Unexpected operation {:?}", operation),
+        }
+
+        let response = http_request(&address, "POST", "/api/switches/2/press");
+        assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+
+        let response = http_request_with_host(&address, "evil.example", "POST", "/api/switches/1/press");
+        assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+
+        let stream = TcpStream::connect(&address).unwrap();
+        let (mut websocket, _) = tungstenite::client(format!("ws://{}/api/events", address), stream).unwrap();
+        event_sender.send(SoundThreadEvent::StoppedSounds).unwrap();
+        match websocket.read().unwrap() {
+            Message::Text(text) => assert_eq!(serde_json::from_str::<Value>(&text).unwrap(), json!({ "event": "stopped" })),
+            message => panic!("Unexpected message {:?}", message),
+        }
+
+        http_thread.stop().unwrap();
+    }
+}
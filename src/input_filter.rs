@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use crate::config::SwitchRef;
+
+/// The filters that apply to a switch, a zero duration disables that filter
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct InputFilterSettings {
+    /// Ignore a press that follows the previous release within this duration
+    pub debounce: Duration,
+    /// Only accept a press once the switch has been held down for this duration
+    pub min_hold: Duration,
+    /// Ignore a press that follows the previous accepted press within this duration
+    pub cooldown: Duration,
+}
+
+impl InputFilterSettings {
+    /// True if none of the filters are enabled
+    pub fn is_disabled(&self) -> bool {
+        self.debounce.is_zero() && self.min_hold.is_zero() && self.cooldown.is_zero()
+    }
+}
+
+#[derive(Debug, Default)]
+struct InputFilterState {
+    last_released_at: Option<Instant>,
+    last_accepted_at: Option<Instant>,
+    /// The switch is held down, but not for long enough yet. It is accepted at this time.
+    accept_at: Option<Instant>,
+    /// The switch is held down and the press has been accepted
+    accepted: bool,
+    /// The switch is held down, but the press has been filtered. Its release is ignored as well.
+    filtered: bool,
+    filtered_count: u64,
+}
+
+impl InputFilterState {
+    fn is_held(&self) -> bool {
+        self.accepted || self.filtered || self.accept_at.is_some()
+    }
+
+    /// Returns true if the press had been accepted. `now` is None if the moment of the release is
+    /// not known.
+    fn release(&mut self, now: Option<Instant>) -> bool {
+        self.last_released_at = now.or(self.last_released_at);
+        self.filtered = false;
+
+        if self.accept_at.take().is_some() {
+            // released before min_hold
+            self.filtered_count += 1;
+        }
+
+        std::mem::take(&mut self.accepted)
+    }
+}
+
+/// Filters out unintended presses, for example caused by a tremor, before they are handled.
+/// Presses that are accepted after some time has passed (because of `min_hold`) are returned by
+/// expire().
+#[derive(Debug, Default)]
+pub struct InputFilter {
+    states: HashMap<SwitchRef, InputFilterState>,
+}
+
+impl InputFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if the press should be handled right away
+    pub fn pressed(&mut self, switch_ref: SwitchRef, settings: InputFilterSettings, now: Instant) -> bool {
+        let state = self.states.entry(switch_ref).or_default();
+
+        if state.is_held() {
+            // treat this as a release followed by a new press, see release_lost()
+            state.release(None);
+        }
+
+        if settings.is_disabled() {
+            state.accepted = true;
+            state.last_accepted_at = Some(now);
+            return true;
+        }
+
+        let within = |since: Option<Instant>, duration: Duration| {
+            since.is_some_and(|since| now.saturating_duration_since(since) < duration)
+        };
+
+        if within(state.last_released_at, settings.debounce) || within(state.last_accepted_at, settings.cooldown) {
+            state.filtered = true;
+            state.filtered_count += 1;
+            return false;
+        }
+
+        if !settings.min_hold.is_zero() {
+            state.accept_at = Some(now + settings.min_hold);
+            return false;
+        }
+
+        state.accepted = true;
+        state.last_accepted_at = Some(now);
+        true
+    }
+
+    /// Returns true if the release should be handled, which is the case if the press has been
+    /// handled
+    pub fn released(&mut self, switch_ref: SwitchRef, now: Instant) -> bool {
+        self.states.entry(switch_ref).or_default().release(Some(now))
+    }
+
+    /// Should be called for a press of a switch that is still held down, which means that the
+    /// release of the previous press got lost (e.g. a MIDI note off that never arrived). Forgets
+    /// about the previous press so that the switch does not get stuck. Returns true if the missing
+    /// release should be handled.
+    pub fn release_lost(&mut self, switch_ref: SwitchRef) -> bool {
+        match self.states.get_mut(&switch_ref) {
+            Some(state) if state.is_held() => state.release(None),
+            _ => false,
+        }
+    }
+
+    /// Returns the switches of which the press has been accepted because they have been held down
+    /// long enough
+    pub fn expire(&mut self, now: Instant) -> Vec<SwitchRef> {
+        let mut accepted = Vec::new();
+
+        for (switch_ref, state) in self.states.iter_mut() {
+            if let Some(accept_at) = state.accept_at {
+                if now >= accept_at {
+                    state.accept_at = None;
+                    state.accepted = true;
+                    state.last_accepted_at = Some(accept_at);
+                    accepted.push(*switch_ref);
+                }
+            }
+        }
+
+        accepted.sort_by_key(|switch_ref| switch_ref.switch_index);
+        accepted
+    }
+
+    /// The next time at which expire() should be called
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.states.values().filter_map(|state| state.accept_at).min()
+    }
+
+    /// The amount of presses of the given switch that have been filtered out
+    pub fn filtered_count(&self, switch_ref: SwitchRef) -> u64 {
+        self.states.get(&switch_ref).map_or(0, |state| state.filtered_count)
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use crate::config::SwitchRef;
+    use crate::input_filter::{InputFilter, InputFilterSettings};
+    use pretty_assertions::{assert_eq};
+
+    const SWITCH: SwitchRef = SwitchRef { switch_index: 0 };
+
+    #[test]
+    fn debounce_and_cooldown() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let settings = InputFilterSettings {
+            debounce: Duration::from_millis(50),
+            min_hold: Duration::ZERO,
+            cooldown: Duration::from_millis(300),
+        };
+        let mut filter = InputFilter::new();
+
+        assert!(filter.pressed(SWITCH, settings, at(0)));
+        assert!(filter.released(SWITCH, at(100)));
+
+        // bounce after the release
+        assert!(!filter.pressed(SWITCH, settings, at(120)));
+        assert!(!filter.released(SWITCH, at(130)));
+        assert_eq!(filter.filtered_count(SWITCH), 1);
+
+        // within the cooldown of the first press
+        assert!(!filter.pressed(SWITCH, settings, at(250)));
+        assert!(!filter.released(SWITCH, at(260)));
+        assert_eq!(filter.filtered_count(SWITCH), 2);
+
+        assert!(filter.pressed(SWITCH, settings, at(400)));
+        assert!(filter.released(SWITCH, at(500)));
+        assert_eq!(filter.filtered_count(SWITCH), 2);
+    }
+
+    #[test]
+    fn press_without_release() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut filter = InputFilter::new();
+
+        assert!(filter.pressed(SWITCH, InputFilterSettings::default(), at(0)));
+        assert!(filter.pressed(SWITCH, InputFilterSettings::default(), at(10)));
+        assert!(filter.released(SWITCH, at(20)));
+        assert!(!filter.release_lost(SWITCH));
+
+        let settings = InputFilterSettings {
+            debounce: Duration::from_millis(50),
+            min_hold: Duration::ZERO,
+            cooldown: Duration::ZERO,
+        };
+
+        assert!(filter.pressed(SWITCH, settings, at(1000)));
+        assert!(filter.release_lost(SWITCH));
+        assert!(filter.pressed(SWITCH, settings, at(2000)));
+        assert!(filter.released(SWITCH, at(2100)));
+
+        // bounce, followed by a press of which the release is missing
+        assert!(!filter.pressed(SWITCH, settings, at(2110)));
+        assert!(!filter.pressed(SWITCH, settings, at(2120)));
+        assert_eq!(filter.filtered_count(SWITCH), 2);
+        assert!(filter.pressed(SWITCH, settings, at(3000)));
+        assert!(filter.released(SWITCH, at(3100)));
+        assert_eq!(filter.filtered_count(SWITCH), 2);
+    }
+
+    #[test]
+    fn min_hold() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let settings = InputFilterSettings {
+            debounce: Duration::ZERO,
+            min_hold: Duration::from_millis(200),
+            cooldown: Duration::ZERO,
+        };
+        let mut filter = InputFilter::new();
+
+        // released too soon
+        assert!(!filter.pressed(SWITCH, settings, at(0)));
+        assert_eq!(filter.next_deadline(), Some(at(200)));
+        assert!(!filter.released(SWITCH, at(150)));
+        assert_eq!(filter.next_deadline(), None);
+        assert_eq!(filter.expire(at(200)), vec![]);
+        assert_eq!(filter.filtered_count(SWITCH), 1);
+
+        assert!(!filter.pressed(SWITCH, settings, at(1000)));
+        assert_eq!(filter.expire(at(1199)), vec![]);
+        assert_eq!(filter.expire(at(1200)), vec![SWITCH]);
+        assert!(filter.released(SWITCH, at(1500)));
+        assert_eq!(filter.filtered_count(SWITCH), 1);
+    }
+}
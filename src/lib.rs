@@ -1,20 +1,50 @@
-use crate::gui::application::run_application;
-use crate::config::Config;
+use crate::gui::application::{run_application, ApplicationFlags};
+use crate::audio_output::{output_devices, AudioOutput, NullAudioOutput, SystemAudioOutput};
+use crate::config::{AudioOutputKind, Config};
 use crate::gamepad_thread::GamepadThread;
+use crate::midi_thread::{MidiThread, SystemMidiInput};
+use crate::config_watch_thread::ConfigWatchThread;
+use crate::osc_thread::OscThread;
+use crate::http_thread::HttpThread;
+use crate::recording_thread::recording_path;
+use crate::offline_render::render_session_log;
+use crate::session_record_thread::SessionRecordThread;
+use crate::session_replay_thread::SessionReplayThread;
 use crate::sound_thread::{SoundThread, SoundThreadRpc};
-use crate::error::AppRunError;
+use crate::error::{AppRunError, ConfigError, OfflineRenderError};
 use path_absolutize::Absolutize;
 use std::env;
 use std::path::PathBuf;
+use std::time::SystemTime;
+use crate::check::{check_config_file, check_config_string, EXIT_SAMPLES_INVALID};
 use crate::sample_loader::{DiskSampleLoader, EmbeddedSampleLoader, SampleLoader};
 
 pub mod config;
 pub mod gui;
 mod sound_bank;
 mod sound_thread;
+mod gesture;
+mod input_filter;
 mod gamepad_thread;
+mod midi_thread;
+mod midi_output;
+mod mixer;
+mod audio_output;
+mod recording_thread;
+mod offline_render;
+mod osc_thread;
+mod http_thread;
+mod session_log;
+mod session_record_thread;
+mod session_replay_thread;
+mod config_watch_thread;
 pub mod error;
 mod sample_loader;
+mod check;
+mod yaml_location;
+mod name_match;
+#[cfg(test)]
+mod test_util;
 
 include! {
     // pub fn embedded_samples() -> HashMap<&'static str, &'static [u8]> { ... }
@@ -22,46 +52,274 @@ include! {
     concat!(env!("OUT_DIR"), "/embedded_config.rs")
 }
 
-pub fn run(mut args: env::Args) -> Result<(), AppRunError> {
+/// Returns the config path given on the command line, or the default location next to the
+/// executable if it was not given.
+fn config_path_from_args(args: &mut impl Iterator<Item = String>) -> PathBuf {
+    let config_path: PathBuf = if let Some(path) = args.next() {
+        PathBuf::from(path)
+    } else {
+        let mut exe_path = std::env::current_exe().unwrap();
+        exe_path.pop();
+        exe_path.push("config");
+        exe_path.push("config.yaml");
+        exe_path
+    };
+
+    config_path.absolutize().unwrap().into_owned()
+}
+
+/// Validate the config and decode all of its samples, without opening an audio device or a
+/// window. A report is printed to stdout and the process exit code is returned.
+///
+/// Usage: `microswitch check [path/to/config.yaml]`
+pub fn check(mut args: env::Args) -> i32 {
     args.next().unwrap(); // skip executable path
+    args.next().unwrap(); // skip "check"
 
-    let (config, sample_loader): (Config, Box<dyn SampleLoader + Send>) = match embedded_config() {
-        // no embedded config, try to read the config from a file
+    let report = match embedded_config() {
         None => {
-            let config_path: PathBuf = if args.len() > 0 {
-                PathBuf::from(&args.next().unwrap())
-            } else {
-                let mut exe_path = std::env::current_exe().unwrap();
-                exe_path.pop();
-                exe_path.push("config");
-                exe_path.push("config.yaml");
-                exe_path
+            let config_path = config_path_from_args(&mut args);
+            println!("Checking config file: {}", config_path.to_str().unwrap());
+            check_config_file(&config_path, &mut DiskSampleLoader::new())
+        },
+        Some(embedded_config_yaml) => {
+            println!("Checking embedded config");
+            let mut loader = match EmbeddedSampleLoader::new(embedded_samples()) {
+                Ok(v) => v,
+                Err(err) => {
+                    println!("Unable to load embedded sample: {}", err);
+                    return EXIT_SAMPLES_INVALID;
+                },
             };
+            check_config_string(embedded_config_yaml, &mut loader)
+        },
+    };
 
-            let config_path = config_path.absolutize().unwrap().into_owned();
-            let config_path = config_path.as_path();
+    report.print();
+    report.exit_code()
+}
 
+/// Render the switch inputs of a session log to a WAV file, without opening an audio device or
+/// a window. The samples are mixed as fast as possible, with the timing of the session log.
+/// Returns the process exit code.
+///
+/// Usage: `microswitch render path/to/session.jsonl path/to/output.wav [path/to/config.yaml]`
+pub fn render(mut args: env::Args) -> i32 {
+    args.next().unwrap(); // skip executable path
+    args.next().unwrap(); // skip "render"
+
+    let (session_log_path, output_path) = match (args.next(), args.next()) {
+        (Some(session_log_path), Some(output_path)) => (
+            PathBuf::from(session_log_path).absolutize().unwrap().into_owned(),
+            PathBuf::from(output_path).absolutize().unwrap().into_owned(),
+        ),
+        _ => {
+            println!("Usage: microswitch render path/to/session.jsonl path/to/output.wav [path/to/config.yaml]");
+            return 1;
+        },
+    };
+
+    let (config, sample_loader): (Result<Config, ConfigError>, Box<dyn SampleLoader>) = match embedded_config() {
+        None => {
+            let config_path = config_path_from_args(&mut args);
             println!("Using config file: {}", config_path.to_str().unwrap());
-            let config = Config::from_file(config_path)?;
+            (Config::from_file(&config_path), Box::new(DiskSampleLoader::new()))
+        },
+        Some(embedded_config_yaml) => {
+            println!("Using embedded config");
+            let loader = match EmbeddedSampleLoader::new(embedded_samples()) {
+                Ok(v) => v,
+                Err(err) => {
+                    println!("Unable to load embedded sample: {}", err);
+                    return 1;
+                },
+            };
+            (Config::from_string(embedded_config_yaml, None), Box::new(loader))
+        },
+    };
+
+    let result = config
+        .map_err(OfflineRenderError::from)
+        .and_then(|config| render_session_log(&config, sample_loader, &session_log_path, &output_path));
+
+    match result {
+        Ok(render) => {
+            println!("Rendered {:.1} seconds to {}", render.duration().as_secs_f64(), output_path.to_str().unwrap());
+            0
+        },
+        Err(err) => {
+            println!("{}", err);
+            1
+        },
+    }
+}
+
+/// Splits the command line arguments that are left after the executable path and the subcommand
+/// into the positional arguments, and the value of the `--audio-device NAME` option which
+/// overrides `audio.device` of the config.
+fn start_args(mut args: impl Iterator<Item = String>, usage: &'static str) -> Result<(Vec<String>, Option<String>), AppRunError> {
+    let mut positional = Vec::new();
+    let mut audio_device = None;
+
+    while let Some(arg) = args.next() {
+        if arg == "--audio-device" {
+            audio_device = Some(args.next().ok_or(AppRunError::Usage { usage })?);
+        } else {
+            positional.push(arg);
+        }
+    }
+
+    Ok((positional, audio_device))
+}
+
+/// Print the names of the sound output devices, which can be used for `audio.device`. Returns the
+/// process exit code.
+///
+/// Usage: `microswitch list-audio-devices`
+pub fn list_audio_devices() -> i32 {
+    let devices = match output_devices() {
+        Ok(v) => v,
+        Err(err) => {
+            println!("{}", err);
+            return 1;
+        },
+    };
+
+    if devices.is_empty() {
+        println!("No audio output devices found");
+    } else {
+        println!("Audio output devices:");
+    }
+    for (name, is_default) in devices {
+        if is_default {
+            println!("  {} (default)", name);
+        } else {
+            println!("  {}", name);
+        }
+    }
+    0
+}
+
+/// Usage: `microswitch [--audio-device NAME] [path/to/config.yaml]`
+pub fn run(args: env::Args) -> Result<(), AppRunError> {
+    // skip executable path
+    let (args, audio_device) = start_args(args.skip(1), "microswitch [--audio-device NAME] [path/to/config.yaml]")?;
+
+    start(args.into_iter(), audio_device, None, false)
+}
+
+/// Start the application and immediately record everything that is played to a WAV file, until
+/// the window is closed.
+///
+/// Usage: `microswitch record [--audio-device NAME] [path/to/config.yaml]`
+pub fn record(args: env::Args) -> Result<(), AppRunError> {
+    // skip executable path and "record"
+    let (args, audio_device) = start_args(args.skip(2), "microswitch record [--audio-device NAME] [path/to/config.yaml]")?;
+
+    start(args.into_iter(), audio_device, None, true)
+}
+
+/// Start the application and press the switches of a session log that was recorded earlier,
+/// with the original timing.
+///
+/// Usage: `microswitch replay path/to/session.jsonl [--audio-device NAME] [path/to/config.yaml]`
+pub fn replay(args: env::Args) -> Result<(), AppRunError> {
+    let usage = "microswitch replay path/to/session.jsonl [--audio-device NAME] [path/to/config.yaml]";
+    // skip executable path and "replay"
+    let (args, audio_device) = start_args(args.skip(2), usage)?;
+    let mut args = args.into_iter();
+
+    let session_log_path = match args.next() {
+        Some(path) => PathBuf::from(path).absolutize().unwrap().into_owned(),
+        None => return Err(AppRunError::Usage { usage }),
+    };
+
+    start(args, audio_device, Some(session_log_path), false)
+}
+
+/// `args` contains the remaining positional command line arguments, after the executable path
+/// and the subcommand
+fn start(
+    mut args: impl Iterator<Item = String>,
+    audio_device: Option<String>,
+    session_log_path: Option<PathBuf>,
+    record: bool,
+) -> Result<(), AppRunError> {
+    // config_path is None if the config was embedded
+    let (mut config, config_path, sample_loader): (Config, Option<PathBuf>, Box<dyn SampleLoader + Send>) = match embedded_config() {
+        // no embedded config, try to read the config from a file
+        None => {
+            let config_path = config_path_from_args(&mut args);
+
+            println!("Using config file: {}", config_path.to_str().unwrap());
+            let config = Config::from_file(&config_path)?;
+            config.print_warnings();
             println!("Relative path for sample files is {}", config.resolve_path.as_ref().unwrap().to_str().unwrap());
 
             let sample_loader = Box::new(DiskSampleLoader::new());
 
-            (config, sample_loader)
+            (config, Some(config_path), sample_loader)
         },
         Some(embedded_config_yaml) => {
             println!("Using embedded config");
             let config = Config::from_string(embedded_config_yaml, None)?;
+            config.print_warnings();
             let loader = EmbeddedSampleLoader::new(embedded_samples()).expect("Unable to load embedded sample");
             let sample_loader = Box::new(loader);
 
-            (config, sample_loader)
+            (config, None, sample_loader)
         },
     };
 
-    let (sound_thread, sound_thread_event_receiver) = SoundThread::new(&config, sample_loader)?;
+    if audio_device.is_some() {
+        config.audio.device = audio_device;
+    }
+    let audio_config = config.audio.clone();
+    let (sound_thread, sound_thread_event_receiver) = SoundThread::new(&config, sample_loader, move || -> Box<dyn AudioOutput> {
+        match audio_config.output {
+            AudioOutputKind::System => Box::new(SystemAudioOutput::new(&audio_config)),
+            AudioOutputKind::Null => {
+                println!("Not playing any sound, because the audio output is \"none\"");
+                Box::new(NullAudioOutput::new())
+            },
+        }
+    })?;
+    if record {
+        let path = recording_path(&config.recording_directory(), SystemTime::now());
+        if let Err(err) = SoundThreadRpc::new(&sound_thread).start_recording(path) {
+            eprintln!("Error sending start_recording to SoundThread {}", err);
+        }
+    }
     let gamepad_thread = GamepadThread::new(&config, SoundThreadRpc::new(&sound_thread))?;
+    let midi_thread = MidiThread::new(&config, SoundThreadRpc::new(&sound_thread), || Box::new(SystemMidiInput::new()));
+    let config_watch_thread = config_path.map(|config_path| {
+        ConfigWatchThread::new(&config_path, &config, SoundThreadRpc::new(&sound_thread))
+    });
+    let osc_thread = config.osc.as_ref().map(|osc_config| {
+        OscThread::new(&config, osc_config, SoundThreadRpc::new(&sound_thread))
+    }).transpose()?;
+    let http_thread = config.http.as_ref().map(|http_config| {
+        HttpThread::new(&config, http_config, SoundThreadRpc::new(&sound_thread))
+    }).transpose()?;
+
+    let session_record_thread = config.session_log.as_ref().map(|session_log_config| {
+        SessionRecordThread::new(&config, session_log_config, SoundThreadRpc::new(&sound_thread))
+    }).transpose()?;
+    let session_replay_thread = session_log_path.map(|session_log_path| {
+        SessionReplayThread::new(&session_log_path, &config, SoundThreadRpc::new(&sound_thread))
+    }).transpose()?;
 
-    run_application(&config, sound_thread, gamepad_thread, sound_thread_event_receiver)?;
+    run_application(ApplicationFlags {
+        config,
+        sound_thread,
+        gamepad_thread,
+        midi_thread,
+        config_watch_thread,
+        osc_thread,
+        http_thread,
+        session_record_thread,
+        session_replay_thread,
+        sound_thread_event_receiver,
+    })?;
     Ok(())
 }
@@ -1,5 +1,5 @@
-use std::env;
-use microswitch::run;
+use std::{env, process};
+use microswitch::{check, list_audio_devices, record, render, replay, run};
 use microswitch::error::{error_msgbox, AppRunError, SoundThreadError};
 
 #[cfg(target_os = "windows")]
@@ -39,10 +39,23 @@ fn hide_console_window() {
 fn main() -> Result<(), AppRunError> {
     println!(concat!("μSwitch ", env!("CARGO_PKG_VERSION")));
 
+    match env::args().nth(1).as_deref() {
+        Some("check") => process::exit(check(env::args())),
+        Some("render") => process::exit(render(env::args())),
+        Some("list-audio-devices") => process::exit(list_audio_devices()),
+        _ => {},
+    }
+
     hide_console_window();
 
     let args = env::args();
-    if let Err(err) = run(args) {
+    let result = match env::args().nth(1).as_deref() {
+        Some("replay") => replay(args),
+        Some("record") => record(args),
+        _ => run(args),
+    };
+
+    if let Err(err) = result {
         match &err {
             AppRunError::Config { source } => {
                 error_msgbox("The configuration is not valid", &source);
@@ -50,6 +63,9 @@ fn main() -> Result<(), AppRunError> {
             AppRunError::SoundThread { source: SoundThreadError::SampleLoad { source } } => {
                 error_msgbox("The configuration is not valid", &source);
             },
+            AppRunError::SoundThread { source: source @ SoundThreadError::AudioDeviceNotFound { .. } } => {
+                error_msgbox("The audio output device was not found", &source);
+            },
             _ => {
                 error_msgbox("Unexpected error", &err)
             },
@@ -0,0 +1,261 @@
+use crate::config::{MidiControl, MidiOut, SwitchRef};
+use crate::error::MidiOutputError;
+use crate::name_match::name_matches;
+use crate::midi_thread::MIDI_CLIENT_NAME;
+use midir::{MidiOutput as MidirOutput, MidiOutputConnection};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Notes without a `durationMs`, which are not started by pressing a switch (for example by a
+/// gesture or a click in the GUI), are stopped after this duration
+const DEFAULT_NOTE_DURATION: Duration = Duration::from_millis(500);
+
+/// Sends raw MIDI messages to an output port. Implemented by SystemMidiOutput, and by an in-memory
+/// port for testing.
+pub trait MidiOutputSink {
+    /// `port` is a name pattern, the first port is used if it is None
+    fn send(&mut self, port: Option<&str>, bytes: &[u8]) -> Result<(), MidiOutputError>;
+}
+
+/// The MIDI output ports of the operating system. A port is connected the first time a message
+/// is sent to it.
+#[derive(Default)]
+pub struct SystemMidiOutput {
+    /// port name pattern => connection
+    connections: HashMap<Option<String>, MidiOutputConnection>,
+}
+
+impl SystemMidiOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn connect(port: Option<&str>) -> Result<MidiOutputConnection, MidiOutputError> {
+        let midi_output = MidirOutput::new(MIDI_CLIENT_NAME)
+            .map_err(|err| MidiOutputError::Init { message: err.to_string() })?;
+
+        let found = midi_output.ports()
+            .into_iter()
+            .map(|output_port| (midi_output.port_name(&output_port).unwrap_or_default(), output_port))
+            .find(|(name, _)| port.map_or(true, |pattern| name_matches(pattern, name)));
+
+        let (name, output_port) = found.ok_or_else(|| MidiOutputError::NoPort {
+            pattern: port.unwrap_or("*").to_string(),
+        })?;
+
+        let connection = midi_output.connect(&output_port, MIDI_CLIENT_NAME)
+            .map_err(|err| MidiOutputError::Connect { port: name.clone(), message: err.to_string() })?;
+
+        println!("MIDI output port \"{}\" connected", name);
+        Ok(connection)
+    }
+}
+
+impl MidiOutputSink for SystemMidiOutput {
+    fn send(&mut self, port: Option<&str>, bytes: &[u8]) -> Result<(), MidiOutputError> {
+        let key = port.map(String::from);
+
+        if !self.connections.contains_key(&key) {
+            let connection = Self::connect(port)?;
+            self.connections.insert(key.clone(), connection);
+        }
+
+        let connection = self.connections.get_mut(&key).unwrap();
+        if let Err(err) = connection.send(bytes) {
+            // the device might have been disconnected, try to connect again next time
+            self.connections.remove(&key);
+            return Err(MidiOutputError::Send { message: err.to_string() });
+        }
+
+        Ok(())
+    }
+}
+
+/// Discards every message, for when nothing should be sent to the MIDI devices of the system
+pub struct NullMidiOutput;
+
+impl MidiOutputSink for NullMidiOutput {
+    fn send(&mut self, _port: Option<&str>, _bytes: &[u8]) -> Result<(), MidiOutputError> {
+        Ok(())
+    }
+}
+
+/// A note for which a note off message still has to be sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PlayingNote {
+    port: Option<String>,
+    /// Between 0 and 15
+    channel: u8,
+    note: u8,
+}
+
+/// Performs `midiOut` actions, and stops the notes that they have started at the right time
+pub struct MidiOutput {
+    sink: Box<dyn MidiOutputSink>,
+    /// The notes that are stopped when the switch that started them is released
+    held_notes: HashMap<SwitchRef, Vec<PlayingNote>>,
+    /// The notes that are stopped at a specific time
+    timed_notes: Vec<(Instant, PlayingNote)>,
+}
+
+impl MidiOutput {
+    pub fn new(sink: Box<dyn MidiOutputSink>) -> Self {
+        Self { sink, held_notes: HashMap::new(), timed_notes: Vec::new() }
+    }
+
+    /// `held_by` is the switch that has been pressed to perform this action, a note without a
+    /// duration is stopped when it is released
+    pub fn perform(&mut self, midi_out: &MidiOut, held_by: Option<SwitchRef>, now: Instant) -> Result<(), MidiOutputError> {
+        let port = midi_out.port.as_deref();
+        let channel = midi_out.channel - 1;
+
+        match midi_out.control {
+            MidiControl::Note(note) => {
+                self.sink.send(port, &[0x90 | channel, note, midi_out.velocity])?;
+                let playing = PlayingNote { port: midi_out.port.clone(), channel, note };
+
+                match (midi_out.duration_ms, held_by) {
+                    (None, Some(switch_ref)) => {
+                        self.held_notes.entry(switch_ref).or_default().push(playing);
+                    },
+                    (duration_ms, _) => {
+                        let duration = duration_ms.map_or(DEFAULT_NOTE_DURATION, Duration::from_millis);
+                        self.timed_notes.push((now + duration, playing));
+                    },
+                }
+            },
+            MidiControl::Cc(cc) => {
+                self.sink.send(port, &[0xB0 | channel, cc, midi_out.value])?;
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Send a note off message for all of the given notes, even if one of them fails
+    fn stop_notes(&mut self, notes: Vec<PlayingNote>) -> Result<(), MidiOutputError> {
+        let mut result = Ok(());
+
+        for note in notes {
+            if let Err(err) = self.sink.send(note.port.as_deref(), &[0x80 | note.channel, note.note, 0]) {
+                result = Err(err);
+            }
+        }
+
+        result
+    }
+
+    /// Stop the notes started by pressing this switch
+    pub fn release(&mut self, switch_ref: SwitchRef) -> Result<(), MidiOutputError> {
+        let notes = self.held_notes.remove(&switch_ref).unwrap_or_default();
+        self.stop_notes(notes)
+    }
+
+    /// Like release(), but the notes keep playing for a while. Used when the switch has been
+    /// released immediately after it was pressed, such as a click in the GUI.
+    pub fn release_later(&mut self, switch_ref: SwitchRef, now: Instant) {
+        for note in self.held_notes.remove(&switch_ref).unwrap_or_default() {
+            self.timed_notes.push((now + DEFAULT_NOTE_DURATION, note));
+        }
+    }
+
+    /// Stop the notes whose duration has passed
+    pub fn expire(&mut self, now: Instant) -> Result<(), MidiOutputError> {
+        let (expired, remaining) = self.timed_notes.drain(..).partition(|(stop_at, _)| now >= *stop_at);
+        self.timed_notes = remaining;
+
+        let expired: Vec<(Instant, PlayingNote)> = expired;
+        self.stop_notes(expired.into_iter().map(|(_, note)| note).collect())
+    }
+
+    /// The next time at which expire() should be called
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.timed_notes.iter().map(|(stop_at, _)| *stop_at).min()
+    }
+
+    /// Stop all notes that are still playing
+    pub fn stop_all(&mut self) -> Result<(), MidiOutputError> {
+        let mut notes: Vec<PlayingNote> = self.held_notes.drain().flat_map(|(_, notes)| notes).collect();
+        notes.extend(self.timed_notes.drain(..).map(|(_, note)| note));
+        self.stop_notes(notes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+    use crate::config::{Config, MidiOut, SwitchRef};
+    use crate::error::MidiOutputError;
+    use crate::midi_output::{MidiOutput, MidiOutputSink};
+    use pretty_assertions::{assert_eq};
+
+    type Sent = Rc<RefCell<Vec<(Option<String>, Vec<u8>)>>>;
+
+    /// Records the messages instead of sending them to a device
+    struct RecordingMidiOutput {
+        sent: Sent,
+    }
+
+    impl MidiOutputSink for RecordingMidiOutput {
+        fn send(&mut self, port: Option<&str>, bytes: &[u8]) -> Result<(), MidiOutputError> {
+            self.sent.borrow_mut().push((port.map(String::from), bytes.to_vec()));
+            Ok(())
+        }
+    }
+
+    fn midi_out_actions() -> Vec<MidiOut> {
+        let config_source = r###"
+banks: []
+switches:
+  - title: Held note
+    midiOut: { note: 60 }
+  - title: Short note
+    midiOut: { port: "Synth*", channel: 2, note: 62, velocity: 80, durationMs: 100 }
+  - title: Controller
+    midiOut: { cc: 7, value: 90 }
+"###;
+        let config = Config::from_string(config_source, None).unwrap();
+        config.switches.iter().map(|switch_config| switch_config.midi_out.clone().unwrap()).collect()
+    }
+
+    #[test]
+    fn notes_are_stopped_on_release_or_after_their_duration() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let switch = SwitchRef { switch_index: 0 };
+        let actions = midi_out_actions();
+        let sent: Sent = Rc::default();
+        let mut output = MidiOutput::new(Box::new(RecordingMidiOutput { sent: sent.clone() }));
+        let take_sent = || sent.borrow_mut().drain(..).collect::<Vec<_>>();
+        let synth = Some(String::from("Synth*"));
+
+        output.perform(&actions[0], Some(switch), at(0)).unwrap();
+        output.perform(&actions[1], Some(switch), at(0)).unwrap();
+        output.perform(&actions[2], Some(switch), at(0)).unwrap();
+        assert_eq!(take_sent(), vec![
+            (None, vec![0x90, 60, 100]),
+            (synth.clone(), vec![0x91, 62, 80]),
+            (None, vec![0xB0, 7, 90]),
+        ]);
+
+        assert_eq!(output.next_deadline(), Some(at(100)));
+        output.expire(at(99)).unwrap();
+        assert_eq!(take_sent(), vec![]);
+        output.expire(at(100)).unwrap();
+        assert_eq!(take_sent(), vec![(synth.clone(), vec![0x81, 62, 0])]);
+
+        output.release(switch).unwrap();
+        assert_eq!(take_sent(), vec![(None, vec![0x80, 60, 0])]);
+
+        // a click
+        output.perform(&actions[0], Some(switch), at(1000)).unwrap();
+        output.release_later(switch, at(1000));
+        assert_eq!(take_sent(), vec![(None, vec![0x90, 60, 100])]);
+        assert_eq!(output.next_deadline(), Some(at(1500)));
+        output.stop_all().unwrap();
+        assert_eq!(take_sent(), vec![(None, vec![0x80, 60, 0])]);
+        assert_eq!(output.next_deadline(), None);
+    }
+}
@@ -0,0 +1,512 @@
+use crate::config::{Config, MidiControl, MidiTriggerRef, SwitchRef};
+use crate::error::{readable_thread_panic_error, MidiThreadError};
+use crate::name_match::name_matches;
+use crate::sound_thread::{InputSource, SoundThreadRpc};
+use midir::{MidiInput, MidiInputConnection};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::mpsc::{Receiver, Sender, SendError, TryRecvError};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const MIDI_CLIENT_NAME: &str = "microswitch";
+
+/// How often the system is checked for MIDI ports that have been connected or disconnected
+const PORT_SCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long to wait for a MIDI event before checking for operations
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug)]
+pub enum MidiOperation {
+    Stop,
+    ReloadConfig {
+        config: Box<Config>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MidiInputEvent {
+    /// A raw MIDI message received from the named port
+    Message { port: String, bytes: Vec<u8> },
+    Disconnected { port: String },
+}
+
+/// Provides the MIDI messages of all connected ports. Implemented by SystemMidiInput, and by an
+/// in-memory port for testing.
+pub trait MidiInputSource {
+    /// Only connect to the ports whose name matches one of these patterns, and to none at all if
+    /// there are no patterns
+    fn select_ports(&mut self, patterns: &[&str]);
+
+    /// Wait at most `timeout` for the next event
+    fn next_event(&mut self, timeout: Duration) -> Option<MidiInputEvent>;
+}
+
+/// The MIDI input ports of the operating system. Ports that are connected later on are picked up
+/// automatically.
+pub struct SystemMidiInput {
+    event_sender: Sender<MidiInputEvent>,
+    event_receiver: Receiver<MidiInputEvent>,
+    /// Used to list the available ports, created once and then reused for every scan. None if the
+    /// MIDI system was not available yet.
+    scanner: Option<MidiInput>,
+    /// Set by select_ports(), no ports are scanned while this is empty
+    port_patterns: Vec<String>,
+    /// port name => connection
+    connections: HashMap<String, MidiInputConnection<()>>,
+    /// Disconnected events detected by scan_ports(), which are returned before any new message
+    pending_events: VecDeque<MidiInputEvent>,
+    next_scan_at: Instant,
+    /// Only print an unavailable MIDI system once
+    reported_error: bool,
+}
+
+impl SystemMidiInput {
+    pub fn new() -> Self {
+        let (event_sender, event_receiver) = mpsc::channel();
+        Self {
+            event_sender,
+            event_receiver,
+            scanner: None,
+            port_patterns: Vec::new(),
+            connections: HashMap::new(),
+            pending_events: VecDeque::new(),
+            next_scan_at: Instant::now(),
+            reported_error: false,
+        }
+    }
+
+    fn is_selected(&self, port_name: &str) -> bool {
+        self.port_patterns.iter().any(|pattern| name_matches(pattern, port_name))
+    }
+
+    fn disconnect(&mut self, port: String) {
+        println!("MIDI port \"{}\" disconnected", port);
+        if let Some(connection) = self.connections.remove(&port) {
+            connection.close();
+        }
+        self.pending_events.push_back(MidiInputEvent::Disconnected { port });
+    }
+
+    fn scan_ports(&mut self) {
+        self.next_scan_at = Instant::now() + PORT_SCAN_INTERVAL;
+
+        if self.port_patterns.is_empty() {
+            let connected: Vec<String> = self.connections.keys().cloned().collect();
+            for port in connected {
+                self.disconnect(port);
+            }
+            return;
+        }
+
+        if self.scanner.is_none() {
+            match MidiInput::new(MIDI_CLIENT_NAME) {
+                Ok(midi_input) => self.scanner = Some(midi_input),
+                Err(err) => {
+                    if !self.reported_error {
+                        eprintln!("MIDI input is not available: {}", err);
+                        self.reported_error = true;
+                    }
+                    return;
+                },
+            }
+        }
+        let Some(scanner) = &self.scanner else {
+            return;
+        };
+
+        let ports: Vec<(String, midir::MidiInputPort)> = scanner.ports()
+            .into_iter()
+            .filter_map(|port| scanner.port_name(&port).ok().map(|name| (name, port)))
+            .filter(|(name, _)| self.is_selected(name))
+            .collect();
+
+        let disconnected: Vec<String> = self.connections
+            .keys()
+            .filter(|name| !ports.iter().any(|(port_name, _)| port_name == *name))
+            .cloned()
+            .collect();
+
+        for port in disconnected {
+            self.disconnect(port);
+        }
+
+        for (name, port) in ports {
+            if self.connections.contains_key(&name) {
+                continue;
+            }
+
+            // connecting consumes the MidiInput, so a new one is needed for each port
+            let midi_input = match MidiInput::new(MIDI_CLIENT_NAME) {
+                Ok(midi_input) => midi_input,
+                Err(err) => {
+                    eprintln!("Failed to connect to MIDI port \"{}\": {}", name, err);
+                    continue;
+                },
+            };
+
+            let event_sender = self.event_sender.clone();
+            let port_name = name.clone();
+            let callback = move |_timestamp: u64, bytes: &[u8], _: &mut ()| {
+                let _ = event_sender.send(MidiInputEvent::Message { port: port_name.clone(), bytes: bytes.to_vec() });
+            };
+
+            match midi_input.connect(&port, MIDI_CLIENT_NAME, callback, ()) {
+                Ok(connection) => {
+                    println!("MIDI port \"{}\" connected", name);
+                    self.connections.insert(name, connection);
+                },
+                Err(err) => {
+                    eprintln!("Failed to connect to MIDI port \"{}\": {}", name, err);
+                },
+            }
+        }
+    }
+}
+
+impl Default for SystemMidiInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MidiInputSource for SystemMidiInput {
+    fn select_ports(&mut self, patterns: &[&str]) {
+        self.port_patterns = patterns.iter().map(|pattern| pattern.to_string()).collect();
+        // apply the new selection right away
+        self.next_scan_at = Instant::now();
+    }
+
+    fn next_event(&mut self, timeout: Duration) -> Option<MidiInputEvent> {
+        if Instant::now() >= self.next_scan_at {
+            self.scan_ports();
+        }
+
+        if let Some(event) = self.pending_events.pop_front() {
+            return Some(event);
+        }
+
+        // event_sender is owned by self, so this never disconnects
+        self.event_receiver.recv_timeout(timeout).ok()
+    }
+}
+
+/// Returns the note or controller, the channel (1 to 16) and the velocity or value of a note or
+/// control change message. Other messages are ignored.
+fn parse_midi_message(bytes: &[u8]) -> Option<(MidiControl, u8, u8)> {
+    let (&status, data) = bytes.split_first()?;
+    let channel = (status & 0x0F) + 1;
+
+    match (status & 0xF0, data) {
+        // note off
+        (0x80, &[note, _velocity, ..]) => Some((MidiControl::Note(note), channel, 0)),
+        // note on, a velocity of 0 means note off
+        (0x90, &[note, velocity, ..]) => Some((MidiControl::Note(note), channel, velocity)),
+        // control change
+        (0xB0, &[cc, value, ..]) => Some((MidiControl::Cc(cc), channel, value)),
+        _ => None,
+    }
+}
+
+/// A MIDI trigger which is currently holding down a switch
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct HeldInput {
+    port: String,
+    trigger_ref: MidiTriggerRef,
+}
+
+struct MidiThreadBody {
+    config: Config,
+    sound_thread_rpc: SoundThreadRpc,
+    source: Box<dyn MidiInputSource>,
+    operation_receiver: Receiver<MidiOperation>,
+    /// The inputs which are currently holding down each switch. A switch is released once all of
+    /// its inputs have been released.
+    held_inputs: HashMap<SwitchRef, HashSet<HeldInput>>,
+}
+
+impl MidiThreadBody {
+    fn press(&mut self, switch_ref: SwitchRef, input: HeldInput) -> Result<(), MidiThreadError> {
+        let newly_held = self.held_inputs.entry(switch_ref).or_default().insert(input);
+
+        if newly_held {
+            if let Err(err) = self.sound_thread_rpc.switch_pressed(switch_ref, InputSource::Midi) {
+                eprintln!("Error sending switch_pressed {:?} to sound thread {}", switch_ref, err);
+                return Err(MidiThreadError::SendSoundThread);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Release all held inputs that match the predicate
+    fn release_where(&mut self, predicate: impl Fn(&HeldInput) -> bool) -> Result<(), MidiThreadError> {
+        let mut released = Vec::new();
+
+        self.held_inputs.retain(|switch_ref, inputs| {
+            let held_before = !inputs.is_empty();
+            inputs.retain(|input| !predicate(input));
+
+            if held_before && inputs.is_empty() {
+                released.push(*switch_ref);
+            }
+            !inputs.is_empty()
+        });
+
+        for switch_ref in released {
+            if let Err(err) = self.sound_thread_rpc.switch_released(switch_ref, InputSource::Midi) {
+                eprintln!("Error sending switch_released {:?} to sound thread {}", switch_ref, err);
+                return Err(MidiThreadError::SendSoundThread);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_midi_message(&mut self, port: String, bytes: &[u8]) -> Result<(), MidiThreadError> {
+        let Some((control, channel, value)) = parse_midi_message(bytes) else {
+            return Ok(());
+        };
+
+        for trigger_ref in self.config.find_midi_triggers(&port, control, channel) {
+            let input = HeldInput { port: port.clone(), trigger_ref };
+
+            if self.config.midi_trigger(trigger_ref).is_held(value) {
+                println!("MIDI \"{}\" channel {}: {:?} = {}", port, channel, control, value);
+                self.press(trigger_ref.switch_ref, input)?;
+            } else {
+                self.release_where(|held_input| *held_input == input)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn thread_body(mut self) -> Result<(), MidiThreadError> {
+        loop {
+            match self.source.next_event(POLL_INTERVAL) {
+                Some(MidiInputEvent::Message { port, bytes }) => {
+                    if let Err(err) = self.handle_midi_message(port, &bytes) {
+                        eprintln!("Error while handling MIDI message {:?}", err);
+                    }
+                },
+                Some(MidiInputEvent::Disconnected { port }) => {
+                    if let Err(err) = self.release_where(|input| input.port == port) {
+                        eprintln!("Error while releasing the switches of a MIDI port {:?}", err);
+                    }
+                },
+                None => {},
+            }
+
+            match self.operation_receiver.try_recv() {
+                Ok(MidiOperation::Stop) => {
+                    return Ok(());
+                },
+                Ok(MidiOperation::ReloadConfig { config }) => {
+                    self.config = *config;
+                    self.source.select_ports(&self.config.midi_port_patterns());
+                    // the SoundThread has already forgotten about held switches
+                    self.held_inputs.clear();
+                },
+                Err(TryRecvError::Empty) => {
+                    // continue the loop
+                },
+                Err(err @ TryRecvError::Disconnected) => {
+                    return Err(MidiThreadError::from(err));
+                },
+            }
+        }
+    }
+}
+
+/// The MidiThread presses and releases switches based on the messages of MIDI devices
+pub struct MidiThread {
+    operation_sender: Sender<MidiOperation>,
+    handle: JoinHandle<()>,
+}
+
+impl MidiThread {
+    /// The source is created by the thread itself, because MIDI connections can not always be
+    /// moved between threads
+    pub fn new<F>(config: &Config, sound_thread_rpc: SoundThreadRpc, make_source: F) -> Self
+    where
+        F: FnOnce() -> Box<dyn MidiInputSource> + Send + 'static,
+    {
+        let config = config.clone();
+        let (operation_sender, operation_receiver) = mpsc::channel();
+
+        let handle = thread::spawn(move || {
+            let mut source = make_source();
+            source.select_ports(&config.midi_port_patterns());
+
+            let body = MidiThreadBody {
+                config,
+                sound_thread_rpc,
+                source,
+                operation_receiver,
+                held_inputs: HashMap::new(),
+            };
+
+            body.thread_body()
+                .expect("Error during MidiThreadBody.thread_body()");
+        });
+
+        Self { operation_sender, handle }
+    }
+
+    pub fn reload_config(&self, config: &Config) -> Result<(), SendError<MidiOperation>> {
+        self.operation_sender.send(MidiOperation::ReloadConfig { config: Box::new(config.clone()) })
+    }
+
+    pub fn stop(self) -> Result<(), MidiThreadError> {
+        if let Err(err) = self.operation_sender.send(MidiOperation::Stop) {
+            eprintln!("Failed to send stop operation to MidiThread: {}", err);
+            // Still try to join in this case, this will probably give us more error details
+        }
+        let handle = self.handle;
+
+        match handle.join() {
+            Ok(_) => Ok(()),
+            Err(join_error) => {
+                let join_error_str = readable_thread_panic_error(&join_error);
+                Err(MidiThreadError::JoinPanic {
+                    join_error,
+                    join_error_str,
+                })
+            }
+        }
+    }
+}
+
+/// An in-memory MIDI port, messages are sent to it using the returned Sender
+#[cfg(test)]
+pub struct VirtualMidiInput {
+    event_receiver: Receiver<MidiInputEvent>,
+}
+
+#[cfg(test)]
+impl VirtualMidiInput {
+    pub fn new() -> (Self, Sender<MidiInputEvent>) {
+        let (event_sender, event_receiver) = mpsc::channel();
+        (Self { event_receiver }, event_sender)
+    }
+}
+
+#[cfg(test)]
+impl MidiInputSource for VirtualMidiInput {
+    fn select_ports(&mut self, _patterns: &[&str]) {
+        // the test decides which ports send messages
+    }
+
+    fn next_event(&mut self, timeout: Duration) -> Option<MidiInputEvent> {
+        match self.event_receiver.recv_timeout(timeout) {
+            Ok(event) => Some(event),
+            Err(mpsc::RecvTimeoutError::Timeout) => None,
+            // the test has finished sending
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                thread::sleep(timeout);
+                None
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use crate::config::{Config, MidiControl};
+    use crate::midi_thread::{MidiInputEvent, MidiThread, VirtualMidiInput, parse_midi_message};
+    use crate::sound_thread::{Operation, SoundThreadRpc};
+    use pretty_assertions::{assert_eq};
+
+    #[test]
+    fn midi_messages() {
+        assert_eq!(parse_midi_message(&[0x90, 60, 100]), Some((MidiControl::Note(60), 1, 100)));
+        assert_eq!(parse_midi_message(&[0x9F, 60, 0]), Some((MidiControl::Note(60), 16, 0)));
+        assert_eq!(parse_midi_message(&[0x83, 61, 64]), Some((MidiControl::Note(61), 4, 0)));
+        assert_eq!(parse_midi_message(&[0xB0, 64, 127]), Some((MidiControl::Cc(64), 1, 127)));
+        // pitch bend
+        assert_eq!(parse_midi_message(&[0xE0, 0, 64]), None);
+        // truncated
+        assert_eq!(parse_midi_message(&[0x90, 60]), None);
+        assert_eq!(parse_midi_message(&[]), None);
+    }
+
+    #[test]
+    fn switches_are_pressed_by_a_virtual_port() {
+        let config_source = r###"
+banks: []
+switches:
+  - title: Note
+    midi: { channel: 1, note: 60 }
+
+  - title: Sustain pedal
+    midiMessages:
+      - cc: 64
+"###;
+        let config = Config::from_string(config_source, None).unwrap();
+        let (operation_sender, operation_receiver) = mpsc::channel();
+        let (input, port) = VirtualMidiInput::new();
+        let midi_thread = MidiThread::new(&config, SoundThreadRpc::from_sender(operation_sender), move || Box::new(input));
+
+        let send = |bytes: &[u8]| port.send(MidiInputEvent::Message { port: String::from("virtual"), bytes: bytes.to_vec() }).unwrap();
+        let receive = || match operation_receiver.recv_timeout(Duration::from_secs(5)).unwrap() {
+            Operation::SwitchPressed { switch_ref, .. } => (true, switch_ref.switch_index),
+            Operation::SwitchReleased { switch_ref, .. } => (false, switch_ref.switch_index),
+            operation => panic!("Unexpected operation {:?}", operation),
+        };
+
+        // other channel
+        send(&[0x91, 60, 100]);
+        send(&[0x90, 60, 100]);
+        assert_eq!(receive(), (true, 0));
+        send(&[0x90, 60, 0]);
+        assert_eq!(receive(), (false, 0));
+
+        send(&[0xB5, 64, 20]);
+        send(&[0xB5, 64, 100]);
+        assert_eq!(receive(), (true, 1));
+        // still above the threshold
+        send(&[0xB5, 64, 90]);
+        port.send(MidiInputEvent::Disconnected { port: String::from("virtual") }).unwrap();
+        assert_eq!(receive(), (false, 1));
+
+        midi_thread.stop().unwrap();
+        assert!(operation_receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn triggers_only_match_their_port() {
+        let config_source = r###"
+banks: []
+switches:
+  - title: Keyboard
+    midi: { note: 60, port: "nanoKEY*" }
+"###;
+        let config = Config::from_string(config_source, None).unwrap();
+        let (operation_sender, operation_receiver) = mpsc::channel();
+        let (input, port) = VirtualMidiInput::new();
+        let midi_thread = MidiThread::new(&config, SoundThreadRpc::from_sender(operation_sender), move || Box::new(input));
+
+        let send = |port_name: &str, bytes: &[u8]| port.send(MidiInputEvent::Message { port: String::from(port_name), bytes: bytes.to_vec() }).unwrap();
+
+        let receive = || match operation_receiver.recv_timeout(Duration::from_secs(5)).unwrap() {
+            Operation::SwitchPressed { switch_ref, .. } => (true, switch_ref.switch_index),
+            Operation::SwitchReleased { switch_ref, .. } => (false, switch_ref.switch_index),
+            operation => panic!("Unexpected operation {:?}", operation),
+        };
+
+        // another port would press the switch a second time
+        send("Digital Piano", &[0x90, 60, 100]);
+        send("nanoKEY2 KEYBOARD", &[0x90, 60, 100]);
+        assert_eq!(receive(), (true, 0));
+        send("nanoKEY2 KEYBOARD", &[0x80, 60, 0]);
+        assert_eq!(receive(), (false, 0));
+
+        midi_thread.stop().unwrap();
+        assert!(operation_receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,273 @@
+use rodio::dynamic_mixer::{self, DynamicMixer, DynamicMixerController};
+use rodio::Source;
+use std::mem;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// The amount of channels of the mix of all banks, and of recordings
+pub const MIX_CHANNELS: u16 = 2;
+/// The sample rate of the mix of all banks, and of recordings
+pub const MIX_SAMPLE_RATE: u32 = 44100;
+/// The amount of samples that are sent to the tap at once. A multiple of MIX_CHANNELS, so that
+/// every chunk starts with the first channel.
+const TAP_CHUNK_SIZE: usize = 4096;
+
+enum TapCommand {
+    Start(Sender<Vec<f32>>),
+    Stop,
+}
+
+/// Creates the mix of all banks. The sinks of every bank are added to the returned controller, and
+/// the MixSource is played on the sound output.
+pub fn mixer() -> (Arc<DynamicMixerController<f32>>, MixSource, MixTap) {
+    let (controller, mixer) = dynamic_mixer::mixer(MIX_CHANNELS, MIX_SAMPLE_RATE);
+    let (tap_command_sender, tap_command_receiver) = mpsc::channel();
+
+    let source = MixSource {
+        mixer,
+        tap_commands: tap_command_receiver,
+        tap: None,
+        tap_buffer: Vec::new(),
+        position: 0,
+    };
+
+    (controller, source, MixTap { commands: tap_command_sender })
+}
+
+/// A Source which plays the mix of all banks. Silence is played while nothing is playing, so that
+/// the source never ends. A copy of the samples can be sent to another thread, see MixTap.
+pub struct MixSource {
+    mixer: DynamicMixer<f32>,
+    tap_commands: Receiver<TapCommand>,
+    tap: Option<Sender<Vec<f32>>>,
+    tap_buffer: Vec<f32>,
+    /// The amount of samples that have been played
+    position: usize,
+}
+
+impl MixSource {
+    fn flush_tap(&mut self) {
+        if let Some(tap) = &self.tap {
+            if !self.tap_buffer.is_empty() {
+                let samples = mem::replace(&mut self.tap_buffer, Vec::with_capacity(TAP_CHUNK_SIZE));
+                if tap.send(samples).is_err() {
+                    // the receiver has gone away
+                    self.tap = None;
+                }
+            }
+        }
+    }
+
+    fn handle_tap_commands(&mut self) {
+        while let Ok(command) = self.tap_commands.try_recv() {
+            self.flush_tap();
+            self.tap_buffer.clear();
+            self.tap = match command {
+                TapCommand::Start(sender) => Some(sender),
+                TapCommand::Stop => None,
+            };
+        }
+    }
+}
+
+impl Iterator for MixSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position % TAP_CHUNK_SIZE == 0 {
+            self.flush_tap();
+            self.handle_tap_commands();
+        }
+        self.position = self.position.wrapping_add(1);
+
+        // the DynamicMixer ends as soon as nothing is playing
+        let sample = self.mixer.next().unwrap_or(0.0);
+
+        if self.tap.is_some() {
+            self.tap_buffer.push(sample);
+        }
+
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl Source for MixSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        MIX_CHANNELS
+    }
+
+    fn sample_rate(&self) -> u32 {
+        MIX_SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Moves a source to the left or to the right. A mono source is turned into stereo, a source with
+/// more than two channels only has its first two channels adjusted.
+pub struct Panned<S> {
+    source: S,
+    left: f32,
+    right: f32,
+    /// The channel of the next sample of the source
+    channel: u16,
+    /// The right channel of a mono source, which is played after the left channel
+    mono_right: Option<f32>,
+}
+
+impl<S: Source<Item = f32>> Panned<S> {
+    /// `pan` is from -1.0 (left) to 1.0 (right). The channel on the side that the source is moved
+    /// to keeps its volume.
+    pub fn new(source: S, pan: f32) -> Self {
+        Self {
+            source,
+            left: (1.0 - pan).min(1.0),
+            right: (1.0 + pan).min(1.0),
+            channel: 0,
+            mono_right: None,
+        }
+    }
+
+    fn is_mono(&self) -> bool {
+        self.source.channels() == 1
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for Panned<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(sample) = self.mono_right.take() {
+            return Some(sample * self.right);
+        }
+
+        let sample = self.source.next()?;
+
+        if self.is_mono() {
+            self.mono_right = Some(sample);
+            return Some(sample * self.left);
+        }
+
+        let gain = match self.channel {
+            0 => self.left,
+            1 => self.right,
+            _ => 1.0,
+        };
+        self.channel = (self.channel + 1) % self.source.channels();
+        Some(sample * gain)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.source.size_hint();
+        if self.is_mono() {
+            (lower.saturating_mul(2), upper.and_then(|upper| upper.checked_mul(2)))
+        } else {
+            (lower, upper)
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Panned<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels().max(2)
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+}
+
+/// Sends a copy of the samples played by a MixSource to a channel, in chunks
+pub struct MixTap {
+    commands: Sender<TapCommand>,
+}
+
+impl MixTap {
+    /// Start sending the samples to the given channel, instead of the previous one
+    pub fn start(&self, sender: Sender<Vec<f32>>) {
+        // If the MixSource has been dropped, the sender is dropped as well and the receiver will
+        // notice that nothing is being played anymore
+        let _ = self.commands.send(TapCommand::Start(sender));
+    }
+
+    /// Stop sending samples. The samples that have not been sent yet are sent first, after which
+    /// the MixSource drops the sender, so that the receiver knows that nothing else will follow.
+    pub fn stop(&self) {
+        let _ = self.commands.send(TapCommand::Stop);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use rodio::Source;
+    use rodio::buffer::SamplesBuffer;
+    use crate::mixer::{mixer, Panned, MIX_CHANNELS, MIX_SAMPLE_RATE, TAP_CHUNK_SIZE};
+    use pretty_assertions::{assert_eq};
+
+    #[test]
+    fn silence_and_tap() {
+        let (controller, mut source, tap) = mixer();
+
+        // nothing is playing
+        assert_eq!(source.next(), Some(0.0));
+        assert_eq!(source.next(), Some(0.0));
+
+        let (sender, receiver) = mpsc::channel();
+        tap.start(sender);
+        // the tap starts at the next chunk
+        for _ in 2..TAP_CHUNK_SIZE {
+            source.next();
+        }
+
+        controller.add(SamplesBuffer::new(MIX_CHANNELS, MIX_SAMPLE_RATE, vec![0.5f32, -0.5]));
+        let played: Vec<f32> = source.by_ref().take(4).collect();
+        assert_eq!(played, vec![0.5, -0.5, 0.0, 0.0]);
+
+        tap.stop();
+        for _ in 4..TAP_CHUNK_SIZE {
+            source.next();
+        }
+        assert_eq!(source.next(), Some(0.0));
+
+        let chunks: Vec<Vec<f32>> = receiver.try_iter().collect();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].len(), TAP_CHUNK_SIZE);
+        assert_eq!(&chunks[0][..4], &[0.5, -0.5, 0.0, 0.0]);
+        // the receiver knows that nothing else will be sent
+        assert_eq!(receiver.try_recv(), Err(mpsc::TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn pan() {
+        let mono = || SamplesBuffer::new(1, MIX_SAMPLE_RATE, vec![1.0f32, 0.5]);
+        let stereo = || SamplesBuffer::new(2, MIX_SAMPLE_RATE, vec![1.0f32, 1.0, 0.5, 0.5]);
+
+        let panned = Panned::new(mono(), 0.0);
+        assert_eq!(panned.channels(), 2);
+        assert_eq!(panned.collect::<Vec<f32>>(), vec![1.0, 1.0, 0.5, 0.5]);
+        assert_eq!(Panned::new(mono(), -0.5).collect::<Vec<f32>>(), vec![1.0, 0.5, 0.5, 0.25]);
+        assert_eq!(Panned::new(stereo(), 1.0).collect::<Vec<f32>>(), vec![0.0, 1.0, 0.0, 0.5]);
+        assert_eq!(Panned::new(stereo(), 0.0).collect::<Vec<f32>>(), vec![1.0, 1.0, 0.5, 0.5]);
+    }
+}
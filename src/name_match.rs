@@ -0,0 +1,40 @@
+/// Match a name against a pattern in which `*` matches any sequence of characters. The
+/// comparison ignores case.
+pub fn name_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+
+    // matches[j] is true if the pattern processed so far matches the first j characters of name
+    let mut matches = vec![false; name.len() + 1];
+    matches[0] = true;
+
+    for pattern_char in pattern {
+        if pattern_char == '*' {
+            for j in 1..=name.len() {
+                matches[j] = matches[j] || matches[j - 1];
+            }
+        } else {
+            for j in (1..=name.len()).rev() {
+                matches[j] = matches[j - 1] && name[j - 1] == pattern_char;
+            }
+            matches[0] = false;
+        }
+    }
+
+    matches[name.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::name_match::name_matches;
+
+    #[test]
+    fn name_patterns() {
+        assert!(name_matches("Xbox Adaptive Controller", "Xbox Adaptive Controller"));
+        assert!(name_matches("xbox*", "Xbox Adaptive Controller"));
+        assert!(name_matches("*adaptive*", "Xbox Adaptive Controller"));
+        assert!(name_matches("*", ""));
+        assert!(!name_matches("Xbox", "Xbox Adaptive Controller"));
+        assert!(!name_matches("*Wireless*", "Xbox Adaptive Controller"));
+    }
+}
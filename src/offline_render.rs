@@ -0,0 +1,140 @@
+use crate::config::Config;
+use crate::error::OfflineRenderError;
+use crate::recording_thread::to_i16_sample;
+use crate::sample_loader::SampleLoader;
+use crate::session_log::{read_session_log, replay_inputs};
+use crate::sound_thread::{render_offline, OfflineRender};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::path::Path;
+
+/// Write the mix of an offline render to a 16-bit WAV file
+pub fn write_wav(path: &Path, render: &OfflineRender) -> Result<(), OfflineRenderError> {
+    let spec = WavSpec {
+        channels: render.channels(),
+        sample_rate: render.sample_rate(),
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let map_err = |source| OfflineRenderError::Write { path: path.to_path_buf(), source };
+
+    let mut writer = WavWriter::create(path, spec).map_err(map_err)?;
+    for sample in &render.samples {
+        writer.write_sample(to_i16_sample(*sample)).map_err(map_err)?;
+    }
+    writer.finalize().map_err(map_err)?;
+    Ok(())
+}
+
+/// Render the switch inputs of a session log (see crate::session_log) to a WAV file, with the
+/// given config instead of a sound output device.
+pub fn render_session_log(
+    config: &Config,
+    sample_loader: Box<dyn SampleLoader>,
+    session_log_path: &Path,
+    output_path: &Path,
+) -> Result<OfflineRender, OfflineRenderError> {
+    let entries = read_session_log(session_log_path)?;
+    let inputs = replay_inputs(config, &entries);
+    println!("Rendering {} switch inputs from {}", inputs.len(), session_log_path.to_str().unwrap());
+
+    let render = render_offline(config, sample_loader, &inputs)?;
+    write_wav(output_path, &render)?;
+    Ok(render)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use crate::config::{Config, SwitchRef};
+    use crate::sample_loader::EmbeddedSampleLoader;
+    use crate::session_log::ReplayInput;
+    use crate::sound_thread::{render_offline, OfflineRender, SoundThreadEvent, SwitchCommand};
+    use crate::test_util::test_sample;
+    use pretty_assertions::{assert_eq};
+
+    fn render(config_source: &str, inputs: &[ReplayInput]) -> OfflineRender {
+        let config = Config::from_string(config_source, None).unwrap();
+        let loader = EmbeddedSampleLoader::new(HashMap::from([("beep.wav", test_sample())])).unwrap();
+        render_offline(&config, Box::new(loader), inputs).unwrap()
+    }
+
+    fn input(time_ms: u64, switch_index: usize, command: SwitchCommand) -> ReplayInput {
+        ReplayInput { time: Duration::from_millis(time_ms), command, switch_ref: SwitchRef { switch_index } }
+    }
+
+    /// The amount of samples (of both channels) in the given duration
+    fn samples(ms: usize) -> usize {
+        ms * 44100 / 1000 * 2
+    }
+
+    #[test]
+    fn samples_are_mixed_at_the_time_of_each_input() {
+        let config_source = r###"
+banks:
+  - id: beeps
+    poly: true
+    samples:
+      - id: beep
+        file: beep.wav
+      - id: beep again
+        file: beep.wav
+switches:
+  - title: Beep
+    play:
+      bank: beeps
+      sample: beep
+  - title: Beep again
+    play:
+      bank: beeps
+      sample: beep again
+"###;
+        let inputs = [
+            input(0, 0, SwitchCommand::Click),
+            input(50, 1, SwitchCommand::Click),
+            input(500, 0, SwitchCommand::Click),
+        ];
+        let result = render(config_source, &inputs);
+
+        assert!(result.samples[..samples(50)].iter().all(|sample| *sample == 0.5));
+        // both clicks are playing
+        assert!(result.samples[samples(50)..samples(100)].iter().all(|sample| *sample == 1.0));
+        assert!(result.samples[samples(100)..samples(150)].iter().all(|sample| *sample == 0.5));
+        assert!(result.samples[samples(150)..samples(500)].iter().all(|sample| *sample == 0.0));
+        assert!(result.samples[samples(500)..samples(600)].iter().all(|sample| *sample == 0.5));
+        assert!(result.samples[samples(600)..].iter().all(|sample| *sample == 0.0));
+        assert!(result.duration() >= Duration::from_millis(600));
+        assert!(result.duration() <= Duration::from_millis(700));
+
+        let played: Vec<Duration> = result.events.iter()
+            .filter(|(_, event)| matches!(event, SoundThreadEvent::PlayedSample(_)))
+            .map(|(time, _)| *time)
+            .collect();
+        assert_eq!(played, vec![Duration::from_millis(0), Duration::from_millis(50), Duration::from_millis(500)]);
+
+        // the result does not depend on the speed of the computer
+        assert_eq!(render(config_source, &inputs).samples, result.samples);
+    }
+
+    #[test]
+    fn hold_to_play_is_stopped_on_release() {
+        let config_source = r###"
+banks:
+  - id: beeps
+    samples:
+      - id: beep
+        file: beep.wav
+switches:
+  - title: Beep
+    holdToPlay: true
+    play:
+      bank: beeps
+      sample: beep
+"###;
+        let result = render(config_source, &[input(0, 0, SwitchCommand::Press), input(20, 0, SwitchCommand::Release)]);
+
+        assert!(result.samples[..samples(20)].iter().all(|sample| *sample == 0.5));
+        // the sink notices that it has been stopped within 5ms
+        assert!(result.samples[samples(30)..].iter().all(|sample| *sample == 0.0));
+    }
+}
@@ -0,0 +1,347 @@
+use crate::config::{BankSampleRef, Config, OscConfig, SwitchRef};
+use crate::error::{readable_thread_panic_error, OscThreadError};
+use crate::sound_thread::{InputSource, SoundThreadEvent, SoundThreadRpc, SwitchCommand};
+use rosc::{OscMessage, OscPacket, OscType};
+use std::convert::TryFrom;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait for an OSC message before checking for events and operations
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug)]
+enum OscOperation {
+    Stop,
+}
+
+/// Returns what to do with which switch, for a received message such as `/microswitch/press 2`
+/// or `/microswitch/click "Drum"`. The switch is selected by its index or by its title.
+fn parse_message(config: &Config, prefix: &str, message: &OscMessage) -> Option<(SwitchCommand, SwitchRef)> {
+    let command = match message.addr.strip_prefix(prefix)? {
+        "/press" => SwitchCommand::Press,
+        "/release" => SwitchCommand::Release,
+        "/click" => SwitchCommand::Click,
+        _ => return None,
+    };
+
+    let switch_config = match message.args.first()? {
+        OscType::Int(index) => usize::try_from(*index).ok().and_then(|index| config.switches.get(index)),
+        OscType::String(title) => config.switches.iter().find(|switch_config| switch_config.title == *title),
+        _ => None,
+    }?;
+
+    Some((command, switch_config.switch_ref))
+}
+
+/// Returns the message that is sent for an event, or None if the event is not sent
+fn event_message(config: &Config, prefix: &str, event: &SoundThreadEvent) -> Option<OscMessage> {
+    let switch_args = |switch_ref: SwitchRef| vec![
+        OscType::Int(i32::try_from(switch_ref.switch_index).unwrap_or(i32::MAX)),
+        OscType::String(config.switch(switch_ref).title.clone()),
+    ];
+    let sample_args = |bank_sample_ref: BankSampleRef| {
+        let (bank_config, sample_config) = config.sample(bank_sample_ref);
+        vec![OscType::String(bank_config.id.clone()), OscType::String(sample_config.id.clone())]
+    };
+
+    let (address, args) = match event {
+        SoundThreadEvent::SwitchPressed(switch_ref) => ("/pressed", switch_args(*switch_ref)),
+        SoundThreadEvent::SwitchReleased(switch_ref) => ("/released", switch_args(*switch_ref)),
+        SoundThreadEvent::PlayedSample(bank_sample_ref) => ("/played", sample_args(*bank_sample_ref)),
+        SoundThreadEvent::StoppedSample(bank_sample_ref) => ("/stopped", sample_args(*bank_sample_ref)),
+        SoundThreadEvent::StoppedSounds => ("/stopped", vec![]),
+        SoundThreadEvent::ConfigReloaded(_) => ("/reloaded", vec![]),
+        SoundThreadEvent::PressFiltered { switch_ref, filtered_count } => {
+            let mut args = switch_args(*switch_ref);
+            args.push(OscType::Long(i64::try_from(*filtered_count).unwrap_or(i64::MAX)));
+            ("/filtered", args)
+        },
+        SoundThreadEvent::SwitchInputReceived { .. } |
+        SoundThreadEvent::Scan(_) |
+        SoundThreadEvent::RecordingStarted(_) |
+        SoundThreadEvent::RecordingStopped(_) |
+        SoundThreadEvent::AudioOutputLost |
+        SoundThreadEvent::AudioOutputRestored(_) => return None,
+    };
+
+    Some(OscMessage { addr: format!("{}{}", prefix, address), args })
+}
+
+struct OscThreadBody {
+    config: Config,
+    prefix: String,
+    sound_thread_rpc: SoundThreadRpc,
+    socket: UdpSocket,
+    /// False if `listen` has not been configured, received messages are then ignored
+    listening: bool,
+    send_addr: Option<SocketAddr>,
+    event_receiver: Receiver<SoundThreadEvent>,
+    operation_receiver: Receiver<OscOperation>,
+}
+
+impl OscThreadBody {
+    fn handle_packet(&self, packet: OscPacket) -> Result<(), OscThreadError> {
+        match packet {
+            OscPacket::Message(message) => self.handle_message(&message),
+            OscPacket::Bundle(bundle) => {
+                for packet in bundle.content {
+                    self.handle_packet(packet)?;
+                }
+                Ok(())
+            },
+        }
+    }
+
+    fn handle_message(&self, message: &OscMessage) -> Result<(), OscThreadError> {
+        let Some((command, switch_ref)) = parse_message(&self.config, &self.prefix, message) else {
+            eprintln!("Ignoring OSC message {} {:?}", message.addr, message.args);
+            return Ok(());
+        };
+
+        println!("OSC {} {:?}", message.addr, message.args);
+        if let Err(err) = self.sound_thread_rpc.switch_command(command, switch_ref, InputSource::Osc) {
+            eprintln!("Error sending {:?} {:?} to sound thread {}", command, switch_ref, err);
+            return Err(OscThreadError::SendSoundThread);
+        }
+
+        Ok(())
+    }
+
+    /// Wait at most POLL_INTERVAL for a message and handle it
+    fn receive(&self, buffer: &mut [u8]) {
+        match self.socket.recv_from(buffer) {
+            Ok((size, _)) if self.listening => {
+                match rosc::decoder::decode_udp(&buffer[..size]) {
+                    Ok((_, packet)) => {
+                        if let Err(err) = self.handle_packet(packet) {
+                            eprintln!("Error while handling OSC message {:?}", err);
+                        }
+                    },
+                    Err(err) => eprintln!("Failed to decode OSC message: {:?}", err),
+                }
+            },
+            Ok(_) => {},
+            Err(err) if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {},
+            Err(err) => eprintln!("Failed to receive OSC message: {}", err),
+        }
+    }
+
+    fn send_event(&self, event: &SoundThreadEvent) -> Result<(), OscThreadError> {
+        let Some(send_addr) = self.send_addr else {
+            return Ok(());
+        };
+        let Some(message) = event_message(&self.config, &self.prefix, event) else {
+            return Ok(());
+        };
+
+        let bytes = rosc::encoder::encode(&OscPacket::Message(message))
+            .map_err(|err| OscThreadError::Encode { message: format!("{:?}", err) })?;
+        self.socket.send_to(&bytes, send_addr)?;
+        Ok(())
+    }
+
+    fn thread_body(mut self) -> Result<(), OscThreadError> {
+        let mut buffer = [0u8; rosc::decoder::MTU];
+
+        loop {
+            self.receive(&mut buffer);
+
+            let events: Vec<SoundThreadEvent> = self.event_receiver.try_iter().collect();
+            for event in events {
+                // the events that follow refer to the switches and samples of the new config
+                if let SoundThreadEvent::ConfigReloaded(config) = &event {
+                    self.config = (**config).clone();
+                }
+
+                if let Err(err) = self.send_event(&event) {
+                    eprintln!("Error while sending OSC message {:?}", err);
+                }
+            }
+
+            match self.operation_receiver.try_recv() {
+                Ok(OscOperation::Stop) => {
+                    return Ok(());
+                },
+                Err(TryRecvError::Empty) => {
+                    // continue the loop
+                },
+                Err(err @ TryRecvError::Disconnected) => {
+                    return Err(OscThreadError::from(err));
+                },
+            }
+        }
+    }
+}
+
+/// The OscThread presses switches when it receives Open Sound Control messages over UDP, and
+/// sends an OSC message for every SoundThreadEvent. Changes to the `osc` section of the config
+/// are only applied after a restart.
+pub struct OscThread {
+    operation_sender: Sender<OscOperation>,
+    handle: JoinHandle<()>,
+    local_addr: SocketAddr,
+}
+
+impl OscThread {
+    pub fn new(config: &Config, osc_config: &OscConfig, sound_thread_rpc: SoundThreadRpc) -> Result<Self, OscThreadError> {
+        let bind_addr = match (osc_config.listen_addr, osc_config.send_addr) {
+            (Some(listen_addr), _) => listen_addr,
+            // only sending, let the operating system pick a port
+            (None, Some(send_addr)) if send_addr.is_ipv6() => SocketAddr::from(([0u16; 8], 0)),
+            _ => SocketAddr::from(([0u8; 4], 0)),
+        };
+
+        let socket = UdpSocket::bind(bind_addr)
+            .map_err(|source| OscThreadError::Bind { address: bind_addr, source })?;
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        let local_addr = socket.local_addr()?;
+
+        if osc_config.listen_addr.is_some() {
+            println!("Receiving OSC messages on {}", local_addr);
+        }
+        if let Some(send_addr) = osc_config.send_addr {
+            println!("Sending OSC messages to {}", send_addr);
+        }
+
+        let event_receiver = sound_thread_rpc.subscribe()
+            .map_err(|_| OscThreadError::SendSoundThread)?;
+        let (operation_sender, operation_receiver) = mpsc::channel();
+
+        let body = OscThreadBody {
+            config: config.clone(),
+            prefix: osc_config.prefix.clone(),
+            sound_thread_rpc,
+            socket,
+            listening: osc_config.listen_addr.is_some(),
+            send_addr: osc_config.send_addr,
+            event_receiver,
+            operation_receiver,
+        };
+
+        let handle = thread::spawn(move || {
+            body.thread_body()
+                .expect("Error during OscThreadBody.thread_body()");
+        });
+
+        Ok(Self { operation_sender, handle, local_addr })
+    }
+
+    /// The address that messages are received on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn stop(self) -> Result<(), OscThreadError> {
+        if let Err(err) = self.operation_sender.send(OscOperation::Stop) {
+            eprintln!("Failed to send stop operation to OscThread: {}", err);
+            // Still try to join in this case, this will probably give us more error details
+        }
+        let handle = self.handle;
+
+        match handle.join() {
+            Ok(_) => Ok(()),
+            Err(join_error) => {
+                let join_error_str = readable_thread_panic_error(&join_error);
+                Err(OscThreadError::JoinPanic {
+                    join_error,
+                    join_error_str,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::UdpSocket;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use rosc::{OscMessage, OscPacket, OscType};
+    use crate::config::{BankSampleRef, Config, SwitchRef};
+    use crate::osc_thread::{event_message, parse_message, OscThread};
+    use crate::sound_thread::{Operation, SoundThreadEvent, SoundThreadRpc, SwitchCommand};
+    use crate::test_util::drums_config;
+    use pretty_assertions::{assert_eq};
+
+    fn test_config(send: &str) -> Config {
+        drums_config(&format!(r###"
+osc:
+  listen: "127.0.0.1:0"
+  send: "{}"
+"###, send))
+    }
+
+    fn message(addr: &str, args: Vec<OscType>) -> OscMessage {
+        OscMessage { addr: String::from(addr), args }
+    }
+
+    #[test]
+    fn received_messages() {
+        let config = test_config("127.0.0.1:9001");
+        let parse = |addr, args| parse_message(&config, "/microswitch", &message(addr, args));
+        let switch = |switch_index| SwitchRef { switch_index };
+
+        assert_eq!(parse("/microswitch/press", vec![OscType::Int(1)]), Some((SwitchCommand::Press, switch(1))));
+        assert_eq!(parse("/microswitch/release", vec![OscType::String(String::from("Kick"))]), Some((SwitchCommand::Release, switch(0))));
+        assert_eq!(parse("/microswitch/click", vec![OscType::Int(0)]), Some((SwitchCommand::Click, switch(0))));
+        assert_eq!(parse("/microswitch/press", vec![OscType::Int(2)]), None);
+        assert_eq!(parse("/microswitch/press", vec![OscType::String(String::from("Snare"))]), None);
+        assert_eq!(parse("/microswitch/press", vec![]), None);
+        assert_eq!(parse("/other/press", vec![OscType::Int(0)]), None);
+    }
+
+    #[test]
+    fn sent_messages() {
+        let config = test_config("127.0.0.1:9001");
+        let event = |event| event_message(&config, "/ms", &event);
+        let kick = BankSampleRef::default();
+
+        assert_eq!(event(SoundThreadEvent::SwitchPressed(SwitchRef { switch_index: 1 })), Some(message("/ms/pressed", vec![
+            OscType::Int(1),
+            OscType::String(String::from("Stop")),
+        ])));
+        assert_eq!(event(SoundThreadEvent::PlayedSample(kick)), Some(message("/ms/played", vec![
+            OscType::String(String::from("drums")),
+            OscType::String(String::from("kick")),
+        ])));
+        assert_eq!(event(SoundThreadEvent::StoppedSounds), Some(message("/ms/stopped", vec![])));
+    }
+
+    #[test]
+    fn switches_are_pressed_and_events_are_sent_on_localhost() {
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let config = test_config(&peer.local_addr().unwrap().to_string());
+        let (operation_sender, operation_receiver) = mpsc::channel();
+        let osc_thread = OscThread::new(&config, config.osc.as_ref().unwrap(), SoundThreadRpc::from_sender(operation_sender)).unwrap();
+        let receive_operation = || operation_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        let event_sender = match receive_operation() {
+            Operation::Subscribe { event_sender } => event_sender,
+            operation => panic!("Unexpected operation {:?}", operation),
+        };
+
+        let packet = OscPacket::Message(message("/microswitch/press", vec![OscType::String(String::from("Stop"))]));
+        peer.send_to(&rosc::encoder::encode(&packet).unwrap(), osc_thread.local_addr()).unwrap();
+        match receive_operation() {
+            Operation::SwitchPressed { switch_ref, .. } => assert_eq!(switch_ref.switch_index, 1),
+            operation => panic!("Unexpected operation {:?}", operation),
+        }
+
+        event_sender.send(SoundThreadEvent::PlayedSample(BankSampleRef::default())).unwrap();
+        let mut buffer = [0u8; rosc::decoder::MTU];
+        let (size, _) = peer.recv_from(&mut buffer).unwrap();
+        let (_, received) = rosc::decoder::decode_udp(&buffer[..size]).unwrap();
+        assert_eq!(received, OscPacket::Message(message("/microswitch/played", vec![
+            OscType::String(String::from("drums")),
+            OscType::String(String::from("kick")),
+        ])));
+
+        osc_thread.stop().unwrap();
+    }
+}
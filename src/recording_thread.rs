@@ -0,0 +1,254 @@
+use crate::error::{readable_thread_panic_error, RecordingThreadError};
+use crate::session_log::UtcDateTime;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long to wait for samples before checking for operations
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How long to wait for the last samples after the recording has been stopped. The MixTap sends
+/// them as soon as the sound output asks for more samples, which does not happen if nothing is
+/// playing the mix.
+const FLUSH_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug)]
+enum RecordingOperation {
+    Stop,
+}
+
+/// Returns the path of a new recording in the given directory, named after the given time (e.g.
+/// `recording-20261017-161434.wav`)
+pub fn recording_path(directory: &Path, now: SystemTime) -> PathBuf {
+    directory.join(format!("recording-{}.wav", UtcDateTime::from_system_time(now).to_file_stem()))
+}
+
+/// Creates a new file at the given path. If there already is a file (e.g. a recording that has
+/// been started within the same second), a number is added to the name instead:
+/// `recording-20261017-161434-2.wav`
+fn create_new_file(path: &Path) -> Result<(PathBuf, File), RecordingThreadError> {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+    let mut attempt = 1;
+
+    loop {
+        let attempt_path = match attempt {
+            1 => path.to_path_buf(),
+            _ => path.with_file_name(format!("{}-{}{}", stem, attempt, extension)),
+        };
+
+        match OpenOptions::new().write(true).create_new(true).open(&attempt_path) {
+            Ok(file) => return Ok((attempt_path, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+            Err(source) => return Err(RecordingThreadError::Create { path: attempt_path, source: source.into() }),
+        }
+    }
+}
+
+pub fn to_i16_sample(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16
+}
+
+struct RecordingThreadBody {
+    path: PathBuf,
+    writer: WavWriter<BufWriter<File>>,
+    sample_receiver: Receiver<Vec<f32>>,
+    operation_receiver: Receiver<RecordingOperation>,
+}
+
+impl RecordingThreadBody {
+    fn write(&mut self, samples: &[f32]) -> Result<(), RecordingThreadError> {
+        for sample in samples {
+            self.writer.write_sample(to_i16_sample(*sample))?;
+        }
+        Ok(())
+    }
+
+    /// Write the samples that are still on their way, until every MixTap has let go of the
+    /// channel
+    fn write_remaining(&mut self) -> Result<(), RecordingThreadError> {
+        let deadline = Instant::now() + FLUSH_TIMEOUT;
+
+        while let Ok(samples) = self.sample_receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            self.write(&samples)?;
+        }
+
+        Ok(())
+    }
+
+    fn thread_body(mut self) -> Result<(), RecordingThreadError> {
+        loop {
+            match self.sample_receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(samples) => self.write(&samples)?,
+                Err(RecvTimeoutError::Timeout) => {},
+                Err(RecvTimeoutError::Disconnected) => {
+                    // Nothing is being played anymore, wait for the stop operation
+                    let _ = self.operation_receiver.recv();
+                    break;
+                },
+            }
+
+            match self.operation_receiver.try_recv() {
+                Ok(RecordingOperation::Stop) => {
+                    self.write_remaining()?;
+                    break;
+                },
+                Err(TryRecvError::Empty) => {
+                    // continue the loop
+                },
+                Err(err @ TryRecvError::Disconnected) => {
+                    return Err(RecordingThreadError::from(err));
+                },
+            }
+        }
+
+        // this updates the length in the header of the file
+        self.writer.finalize()?;
+        println!("Saved the recording to {}", self.path.to_str().unwrap());
+        Ok(())
+    }
+}
+
+/// The RecordingThread writes the samples it receives to a 16-bit WAV file. The samples are sent
+/// by the MixTap of the SoundThread, so that the recording contains everything that is played.
+pub struct RecordingThread {
+    operation_sender: Sender<RecordingOperation>,
+    handle: JoinHandle<()>,
+    path: PathBuf,
+}
+
+impl RecordingThread {
+    pub fn new(path: &Path, channels: u16, sample_rate: u32, sample_receiver: Receiver<Vec<f32>>) -> Result<Self, RecordingThreadError> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)
+                .map_err(|source| RecordingThreadError::CreateDirectory { path: directory.to_path_buf(), source })?;
+        }
+
+        let spec = WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let (path, file) = create_new_file(path)?;
+        let writer = WavWriter::new(BufWriter::new(file), spec)
+            .map_err(|source| RecordingThreadError::Create { path: path.clone(), source })?;
+        println!("Recording to {}", path.to_str().unwrap());
+
+        let (operation_sender, operation_receiver) = mpsc::channel();
+
+        let body = RecordingThreadBody {
+            path: path.clone(),
+            writer,
+            sample_receiver,
+            operation_receiver,
+        };
+
+        let handle = thread::spawn(move || {
+            body.thread_body()
+                .expect("Error during RecordingThreadBody.thread_body()");
+        });
+
+        Ok(Self { operation_sender, handle, path })
+    }
+
+    /// The path of the WAV file, which differs from the requested path if a file already existed
+    /// there
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write the remaining samples and close the file. The MixTap should be stopped first, so that
+    /// it sends the samples it still has and then drops its end of the channel.
+    pub fn stop(self) -> Result<(), RecordingThreadError> {
+        if let Err(err) = self.operation_sender.send(RecordingOperation::Stop) {
+            eprintln!("Failed to send stop operation to RecordingThread: {}", err);
+            // Still try to join in this case, this will probably give us more error details
+        }
+        let handle = self.handle;
+
+        match handle.join() {
+            Ok(_) => Ok(()),
+            Err(join_error) => {
+                let join_error_str = readable_thread_panic_error(&join_error);
+                Err(RecordingThreadError::JoinPanic {
+                    join_error,
+                    join_error_str,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+    use std::sync::mpsc;
+    use std::time::{Duration, UNIX_EPOCH};
+    use crate::recording_thread::{recording_path, to_i16_sample, RecordingThread};
+    use pretty_assertions::{assert_eq};
+
+    #[test]
+    fn samples() {
+        assert_eq!(to_i16_sample(0.0), 0);
+        assert_eq!(to_i16_sample(1.0), i16::MAX);
+        assert_eq!(to_i16_sample(-1.0), -i16::MAX);
+        assert_eq!(to_i16_sample(2.0), i16::MAX);
+        assert_eq!(to_i16_sample(0.5), 16383);
+    }
+
+    #[test]
+    fn path() {
+        let now = UNIX_EPOCH + Duration::from_secs(1792253674);
+        assert_eq!(recording_path(Path::new("recordings"), now), Path::new("recordings").join("recording-20261017-161434.wav"));
+    }
+
+    #[test]
+    fn existing_files_are_not_overwritten() {
+        let directory = env::temp_dir().join(format!("microswitch-recording-existing-{}", std::process::id()));
+        let path = directory.join("recording.wav");
+
+        let start = |path: &Path| {
+            let (_, sample_receiver) = mpsc::channel();
+            let recording_thread = RecordingThread::new(path, 2, 44100, sample_receiver).unwrap();
+            let actual_path = recording_thread.path().to_path_buf();
+            recording_thread.stop().unwrap();
+            actual_path
+        };
+
+        let first = start(&path);
+        let second = start(&path);
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(first, path);
+        assert_eq!(second, directory.join("recording-2.wav"));
+    }
+
+    #[test]
+    fn received_samples_are_written() {
+        let directory = env::temp_dir().join(format!("microswitch-recording-{}", std::process::id()));
+        let path = directory.join("recording.wav");
+        let (sample_sender, sample_receiver) = mpsc::channel();
+        let recording_thread = RecordingThread::new(&path, 2, 44100, sample_receiver).unwrap();
+
+        sample_sender.send(vec![0.0, 1.0, -1.0, 0.5]).unwrap();
+        sample_sender.send(vec![0.25, 0.0]).unwrap();
+        drop(sample_sender);
+        recording_thread.stop().unwrap();
+
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        let spec = reader.spec();
+        let samples: Vec<i16> = reader.samples::<i16>().map(Result::unwrap).collect();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!((spec.channels, spec.sample_rate, spec.bits_per_sample), (2, 44100, 16));
+        assert_eq!(samples, vec![0, i16::MAX, -i16::MAX, 16383, 8191, 0]);
+    }
+}
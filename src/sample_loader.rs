@@ -1,11 +1,14 @@
 use std::collections::HashMap;
+use std::fs;
 use std::fs::File;
 use std::io::{BufReader, Cursor, Read, Seek};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::SystemTime;
 use rodio::{Decoder, Sink, Source};
 use rodio::source::{Buffered, SamplesConverter};
-use crate::config::{BankConfig, SampleConfig};
+use crate::config::{BankConfig, Gain, SampleConfig};
+use crate::mixer::Panned;
 use crate::error::SampleLoadError;
 
 #[derive(Clone)]
@@ -29,7 +32,7 @@ fn decode<T: Read + Seek + Send + Sync + 'static>(data: T) -> Result<Buffered<Sa
     {
         let mut source_clone = source.clone();
         loop {
-            if let None = source_clone.next() {
+            if source_clone.next().is_none() {
                 break;
             }
         }
@@ -49,12 +52,29 @@ impl LoadedSample {
         self.source.clone()
     }
 
-    /// Plays the sampe using the given rodio Sink
-    pub fn play(&self, sink: &Sink) {
+    /// The amount of channels that play() appends to a sink. Mono samples are played in stereo,
+    /// so that they can be panned.
+    pub fn channels(&self) -> u16 {
+        let channels = match &self.source {
+            LoadedSampleSource::Disk(source) => source.channels(),
+            LoadedSampleSource::Embedded(source) => source.channels(),
+        };
+        channels.max(2)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        match &self.source {
+            LoadedSampleSource::Disk(source) => source.sample_rate(),
+            LoadedSampleSource::Embedded(source) => source.sample_rate(),
+        }
+    }
+
+    /// Plays the sampe using the given rodio Sink, with the given volume and pan
+    pub fn play(&self, sink: &Sink, gain: Gain) {
         let source = self.to_source();
         match source {
-            LoadedSampleSource::Disk(source) => sink.append(source),
-            LoadedSampleSource::Embedded(source) => sink.append(source),
+            LoadedSampleSource::Disk(source) => sink.append(Panned::new(source.amplify(gain.volume), gain.pan)),
+            LoadedSampleSource::Embedded(source) => sink.append(Panned::new(source.amplify(gain.volume), gain.pan)),
         };
     }
 }
@@ -78,7 +98,7 @@ pub trait SampleLoader {
     }
 
     /// Convenience method which calls load_bank for each BankConfig in the given list
-    fn load_banks(&mut self, bank_configs: &Vec<BankConfig>) -> Result<(), SampleLoadError> {
+    fn load_banks(&mut self, bank_configs: &[BankConfig]) -> Result<(), SampleLoadError> {
         for bank_config in bank_configs.iter() {
             self.load_bank(bank_config)?;
         }
@@ -91,9 +111,17 @@ pub trait SampleLoader {
     fn get(&self, sample_config: &SampleConfig) -> Option<Arc<LoadedSample>>;
 }
 
-/// A SampleLoader which reads samples from disk
+/// A sample that has been read from disk, along with the modification time of the file at the
+/// moment it was read.
+struct DiskLoadedSample {
+    modified: Option<SystemTime>,
+    sample: Arc<LoadedSample>,
+}
+
+/// A SampleLoader which reads samples from disk. A sample is read again if its file has been
+/// modified since it was cached.
 pub struct DiskSampleLoader {
-    loaded_samples: HashMap<PathBuf, Arc<LoadedSample>>,
+    loaded_samples: HashMap<PathBuf, DiskLoadedSample>,
 }
 
 impl DiskSampleLoader {
@@ -109,7 +137,14 @@ impl SampleLoader for DiskSampleLoader {
         let cache_key = sample_config.file.as_path();
         let path = sample_config.file_resolved.as_ref().unwrap().as_path();
 
-        if !self.loaded_samples.contains_key(cache_key) {
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+
+        let up_to_date = match self.loaded_samples.get(cache_key) {
+            None => false,
+            Some(cached) => cached.modified == modified,
+        };
+
+        if !up_to_date {
             let file = match File::open(path) {
                 Ok(v) => v,
                 Err(err) => {
@@ -120,7 +155,8 @@ impl SampleLoader for DiskSampleLoader {
             let loaded_sample = LoadedSample {
                 source: LoadedSampleSource::Disk(source),
             };
-            self.loaded_samples.insert(cache_key.to_path_buf(), Arc::new(loaded_sample));
+            let cached = DiskLoadedSample { modified, sample: Arc::new(loaded_sample) };
+            self.loaded_samples.insert(cache_key.to_path_buf(), cached);
         }
 
         Ok(())
@@ -129,10 +165,9 @@ impl SampleLoader for DiskSampleLoader {
     fn get(&self, sample_config: &SampleConfig) -> Option<Arc<LoadedSample>> {
         let cache_key = sample_config.file.as_path();
 
-        match self.loaded_samples.get(cache_key) {
-            None => None,
-            Some(sample) => Some(Arc::clone(sample)),
-        }
+        self.loaded_samples
+            .get(cache_key)
+            .map(|cached| Arc::clone(&cached.sample))
     }
 }
 
@@ -0,0 +1,320 @@
+use crate::config::{Config, SwitchRef};
+use crate::error::SessionLogError;
+use crate::sound_thread::{InputSource, SoundThreadEvent, SwitchCommand};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A single line of a session log. The file is in the JSON lines format: the first line is a
+/// `session` entry, followed by an entry for every switch input and every played sample.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SessionLogEntry {
+    #[serde(rename_all = "camelCase")]
+    Session {
+        /// e.g. "2026-10-17T16:14:34Z"
+        started_at: String,
+        version: String,
+    },
+    /// A switch has been pressed, released or clicked (before the input filter has been applied)
+    #[serde(rename_all = "camelCase")]
+    Input {
+        /// Milliseconds since the start of the session
+        time_ms: u64,
+        switch: usize,
+        title: String,
+        command: SwitchCommand,
+        source: InputSource,
+    },
+    #[serde(rename_all = "camelCase")]
+    Played {
+        /// Milliseconds since the start of the session
+        time_ms: u64,
+        bank: String,
+        sample: String,
+    },
+}
+
+/// A switch command that should be performed at a specific time while replaying a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayInput {
+    /// Since the start of the replay
+    pub time: Duration,
+    pub command: SwitchCommand,
+    pub switch_ref: SwitchRef,
+}
+
+/// A point in time in UTC, only used to name and describe session logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UtcDateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl UtcDateTime {
+    pub fn from_unix_seconds(seconds: i64) -> Self {
+        let days = seconds.div_euclid(86400);
+        let second_of_day = seconds.rem_euclid(86400);
+
+        // Converts days since 1970-01-01 to a date in the proleptic Gregorian calendar, see
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let day_of_era = z.rem_euclid(146097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        UtcDateTime {
+            year,
+            month: month as u32,
+            day: day as u32,
+            hour: (second_of_day / 3600) as u32,
+            minute: (second_of_day / 60 % 60) as u32,
+            second: (second_of_day % 60) as u32,
+        }
+    }
+
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let seconds = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs() as i64,
+            Err(err) => -(err.duration().as_secs() as i64),
+        };
+        Self::from_unix_seconds(seconds)
+    }
+
+    /// e.g. "2026-10-17T16:14:34Z"
+    pub fn to_rfc3339(&self) -> String {
+        format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", self.year, self.month, self.day, self.hour, self.minute, self.second)
+    }
+
+    /// e.g. "20261017-161434"
+    pub fn to_file_stem(&self) -> String {
+        format!("{:04}{:02}{:02}-{:02}{:02}{:02}", self.year, self.month, self.day, self.hour, self.minute, self.second)
+    }
+}
+
+fn duration_ms(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Returns the entry that is written for an event, or None if the event is not written.
+/// `time` is the time since the start of the session.
+pub fn event_entry(config: &Config, time: Duration, event: &SoundThreadEvent) -> Option<SessionLogEntry> {
+    let time_ms = duration_ms(time);
+
+    match event {
+        SoundThreadEvent::SwitchInputReceived { switch_ref, command, source, .. } => Some(SessionLogEntry::Input {
+            time_ms,
+            switch: switch_ref.switch_index,
+            title: config.switch(*switch_ref).title.clone(),
+            command: *command,
+            source: *source,
+        }),
+        SoundThreadEvent::PlayedSample(bank_sample_ref) => {
+            let (bank_config, sample_config) = config.sample(*bank_sample_ref);
+            Some(SessionLogEntry::Played {
+                time_ms,
+                bank: bank_config.id.clone(),
+                sample: sample_config.id.clone(),
+            })
+        },
+        _ => None,
+    }
+}
+
+/// Returns the switch commands to perform while replaying the given entries. A switch is looked
+/// up by its index if its title is unchanged, otherwise by its title, so that the session can still
+/// be replayed after switches have been added or reordered. Inputs for switches that no longer
+/// exist are skipped with a warning.
+pub fn replay_inputs(config: &Config, entries: &[SessionLogEntry]) -> Vec<ReplayInput> {
+    entries.iter().filter_map(|entry| {
+        let SessionLogEntry::Input { time_ms, switch, title, command, .. } = entry else {
+            return None;
+        };
+
+        let switch_config = config.switches.get(*switch)
+            .filter(|switch_config| switch_config.title == *title)
+            .or_else(|| config.switches.iter().find(|switch_config| switch_config.title == *title));
+
+        match switch_config {
+            Some(switch_config) => Some(ReplayInput {
+                time: Duration::from_millis(*time_ms),
+                command: *command,
+                switch_ref: switch_config.switch_ref,
+            }),
+            None => {
+                eprintln!("Skipping replay of switch \"{}\" at {}ms, it is not in the config", title, time_ms);
+                None
+            },
+        }
+    }).collect()
+}
+
+pub fn parse_session_log<R: BufRead>(reader: R) -> Result<Vec<SessionLogEntry>, SessionLogError> {
+    let mut entries = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let entry = serde_json::from_str(&line)
+            .map_err(|source| SessionLogError::Parse { line: index + 1, source })?;
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+pub fn read_session_log(path: &Path) -> Result<Vec<SessionLogEntry>, SessionLogError> {
+    let file = File::open(path)
+        .map_err(|source| SessionLogError::Open { path: path.to_path_buf(), source })?;
+    parse_session_log(BufReader::new(file))
+}
+
+/// A session log that is being written to. Every entry is written to the file immediately, so
+/// that the session is not lost if μSwitch is not closed properly.
+pub struct SessionLogWriter {
+    path: PathBuf,
+    file: File,
+}
+
+impl SessionLogWriter {
+    /// Create a new file named after the current time (e.g. `session-20261017-161434.jsonl`) in
+    /// the given directory, and write the `session` entry to it.
+    pub fn create(directory: &Path, now: SystemTime) -> Result<Self, SessionLogError> {
+        fs::create_dir_all(directory)
+            .map_err(|source| SessionLogError::Open { path: directory.to_path_buf(), source })?;
+
+        let started_at = UtcDateTime::from_system_time(now);
+        let mut attempt = 1;
+        let (path, file) = loop {
+            let file_name = match attempt {
+                1 => format!("session-{}.jsonl", started_at.to_file_stem()),
+                _ => format!("session-{}-{}.jsonl", started_at.to_file_stem(), attempt),
+            };
+            let path = directory.join(file_name);
+
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => break (path, file),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+                Err(source) => return Err(SessionLogError::Open { path, source }),
+            }
+        };
+
+        let mut writer = SessionLogWriter { path, file };
+        writer.write(&SessionLogEntry::Session {
+            started_at: started_at.to_rfc3339(),
+            version: String::from(env!("CARGO_PKG_VERSION")),
+        })?;
+        Ok(writer)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&mut self, entry: &SessionLogEntry) -> Result<(), SessionLogError> {
+        let mut line = serde_json::to_string(entry)
+            .map_err(|source| SessionLogError::Serialize { source })?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::{Duration, Instant};
+    use crate::config::{BankSampleRef, SwitchRef};
+    use crate::session_log::{event_entry, parse_session_log, replay_inputs, ReplayInput, SessionLogEntry, UtcDateTime};
+    use crate::sound_thread::{InputSource, SoundThreadEvent, SwitchCommand};
+    use crate::test_util::drums_config;
+    use pretty_assertions::{assert_eq};
+
+    fn input(time_ms: u64, switch: usize, title: &str, command: SwitchCommand) -> SessionLogEntry {
+        SessionLogEntry::Input { time_ms, switch, title: String::from(title), command, source: InputSource::Gamepad }
+    }
+
+    #[test]
+    fn utc_date_time() {
+        assert_eq!(UtcDateTime::from_unix_seconds(0).to_rfc3339(), "1970-01-01T00:00:00Z");
+        assert_eq!(UtcDateTime::from_unix_seconds(951868799).to_rfc3339(), "2000-02-29T23:59:59Z");
+        assert_eq!(UtcDateTime::from_unix_seconds(1792253674).to_rfc3339(), "2026-10-17T16:14:34Z");
+        assert_eq!(UtcDateTime::from_unix_seconds(1792253674).to_file_stem(), "20261017-161434");
+        assert_eq!(UtcDateTime::from_unix_seconds(-1).to_rfc3339(), "1969-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn entries() {
+        let config = drums_config("");
+        let time = Duration::from_millis(1500);
+
+        let entry = event_entry(&config, time, &SoundThreadEvent::SwitchInputReceived {
+            switch_ref: SwitchRef { switch_index: 1 },
+            command: SwitchCommand::Press,
+            source: InputSource::Midi,
+            received_at: Instant::now(),
+        }).unwrap();
+        assert_eq!(
+            serde_json::to_string(&entry).unwrap(),
+            r#"{"type":"input","timeMs":1500,"switch":1,"title":"Stop","command":"press","source":"midi"}"#,
+        );
+
+        let entry = event_entry(&config, time, &SoundThreadEvent::PlayedSample(BankSampleRef::default())).unwrap();
+        assert_eq!(
+            serde_json::to_string(&entry).unwrap(),
+            r#"{"type":"played","timeMs":1500,"bank":"drums","sample":"kick"}"#,
+        );
+
+        assert_eq!(event_entry(&config, time, &SoundThreadEvent::SwitchPressed(SwitchRef { switch_index: 1 })), None);
+    }
+
+    #[test]
+    fn parse() {
+        let source = r#"{"type":"session","startedAt":"2026-10-17T16:14:34Z","version":"1.0.0"}
+
+{"type":"input","timeMs":20,"switch":0,"title":"Kick","command":"click","source":"gui"}
+"#;
+        assert_eq!(parse_session_log(Cursor::new(source)).unwrap(), vec![
+            SessionLogEntry::Session { started_at: String::from("2026-10-17T16:14:34Z"), version: String::from("1.0.0") },
+            SessionLogEntry::Input { time_ms: 20, switch: 0, title: String::from("Kick"), command: SwitchCommand::Click, source: InputSource::Gui },
+        ]);
+
+        let err = parse_session_log(Cursor::new("{\"type\":\"session\",\"startedAt\":\"\",\"version\":\"\"}\n{\"type\":\"bogus\"}\n")).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid session log, line 2:"), "{}", err);
+    }
+
+    #[test]
+    fn replay() {
+        let config = drums_config("");
+        let entries = vec![
+            SessionLogEntry::Session { started_at: String::from("2026-10-17T16:14:34Z"), version: String::from("1.0.0") },
+            input(100, 0, "Kick", SwitchCommand::Press),
+            SessionLogEntry::Played { time_ms: 101, bank: String::from("drums"), sample: String::from("kick") },
+            input(250, 0, "Kick", SwitchCommand::Release),
+            // the switches have been reordered since the session was recorded
+            input(300, 0, "Stop", SwitchCommand::Click),
+            // this switch has been removed
+            input(400, 2, "Snare", SwitchCommand::Click),
+        ];
+
+        assert_eq!(replay_inputs(&config, &entries), vec![
+            ReplayInput { time: Duration::from_millis(100), command: SwitchCommand::Press, switch_ref: SwitchRef { switch_index: 0 } },
+            ReplayInput { time: Duration::from_millis(250), command: SwitchCommand::Release, switch_ref: SwitchRef { switch_index: 0 } },
+            ReplayInput { time: Duration::from_millis(300), command: SwitchCommand::Click, switch_ref: SwitchRef { switch_index: 1 } },
+        ]);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::config::{Config, SessionLogConfig};
+use crate::error::{readable_thread_panic_error, SessionRecordThreadError};
+use crate::session_log::{event_entry, SessionLogWriter};
+use crate::sound_thread::{SoundThreadEvent, SoundThreadRpc};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long to wait for an event before checking for operations
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+enum SessionRecordOperation {
+    Stop,
+}
+
+struct SessionRecordThreadBody {
+    config: Config,
+    writer: SessionLogWriter,
+    started_at: Instant,
+    event_receiver: Receiver<SoundThreadEvent>,
+    operation_receiver: Receiver<SessionRecordOperation>,
+}
+
+impl SessionRecordThreadBody {
+    fn handle_event(&mut self, event: SoundThreadEvent) -> Result<(), SessionRecordThreadError> {
+        // the events that follow refer to the switches and samples of the new config
+        if let SoundThreadEvent::ConfigReloaded(config) = &event {
+            self.config = (**config).clone();
+        }
+
+        // an input is logged at the time it was received, rather than when it is written
+        let time = match &event {
+            SoundThreadEvent::SwitchInputReceived { received_at, .. } => received_at.saturating_duration_since(self.started_at),
+            _ => self.started_at.elapsed(),
+        };
+
+        if let Some(entry) = event_entry(&self.config, time, &event) {
+            self.writer.write(&entry)?;
+        }
+
+        Ok(())
+    }
+
+    fn thread_body(mut self) -> Result<(), SessionRecordThreadError> {
+        loop {
+            match self.event_receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(event) => {
+                    if let Err(err) = self.handle_event(event) {
+                        eprintln!("Error while writing session log {:?}", err);
+                    }
+                },
+                Err(RecvTimeoutError::Timeout) => {},
+                // The SoundThread has stopped, wait for our own stop operation
+                Err(RecvTimeoutError::Disconnected) => thread::sleep(POLL_INTERVAL),
+            }
+
+            match self.operation_receiver.try_recv() {
+                Ok(SessionRecordOperation::Stop) => {
+                    // write the events that are still queued
+                    let events: Vec<SoundThreadEvent> = self.event_receiver.try_iter().collect();
+                    for event in events {
+                        self.handle_event(event)?;
+                    }
+                    return Ok(());
+                },
+                Err(TryRecvError::Empty) => {
+                    // continue the loop
+                },
+                Err(err @ TryRecvError::Disconnected) => {
+                    return Err(SessionRecordThreadError::from(err));
+                },
+            }
+        }
+    }
+}
+
+/// The SessionRecordThread writes every switch input and every played sample to a new session
+/// log, which can be replayed later using `microswitch replay`. Changes to the `sessionLog`
+/// section of the config are only applied after a restart.
+pub struct SessionRecordThread {
+    operation_sender: Sender<SessionRecordOperation>,
+    handle: JoinHandle<()>,
+    path: PathBuf,
+}
+
+impl SessionRecordThread {
+    pub fn new(config: &Config, session_log_config: &SessionLogConfig, sound_thread_rpc: SoundThreadRpc) -> Result<Self, SessionRecordThreadError> {
+        let writer = SessionLogWriter::create(&session_log_config.directory_resolved, SystemTime::now())?;
+        let path = writer.path().to_path_buf();
+        println!("Recording this session to {}", path.to_str().unwrap());
+
+        let event_receiver = sound_thread_rpc.subscribe()
+            .map_err(|_| SessionRecordThreadError::SendSoundThread)?;
+        let (operation_sender, operation_receiver) = mpsc::channel();
+
+        let body = SessionRecordThreadBody {
+            config: config.clone(),
+            writer,
+            started_at: Instant::now(),
+            event_receiver,
+            operation_receiver,
+        };
+
+        let handle = thread::spawn(move || {
+            body.thread_body()
+                .expect("Error during SessionRecordThreadBody.thread_body()");
+        });
+
+        Ok(Self { operation_sender, handle, path })
+    }
+
+    /// The session log that is being written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn stop(self) -> Result<(), SessionRecordThreadError> {
+        if let Err(err) = self.operation_sender.send(SessionRecordOperation::Stop) {
+            eprintln!("Failed to send stop operation to SessionRecordThread: {}", err);
+            // Still try to join in this case, this will probably give us more error details
+        }
+        let handle = self.handle;
+
+        match handle.join() {
+            Ok(_) => Ok(()),
+            Err(join_error) => {
+                let join_error_str = readable_thread_panic_error(&join_error);
+                Err(SessionRecordThreadError::JoinPanic {
+                    join_error,
+                    join_error_str,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+    use crate::config::{BankSampleRef, Config, SwitchRef};
+    use crate::session_log::{read_session_log, SessionLogEntry};
+    use crate::session_record_thread::SessionRecordThread;
+    use crate::sound_thread::{InputSource, Operation, SoundThreadEvent, SoundThreadRpc, SwitchCommand};
+    use pretty_assertions::{assert_eq};
+
+    #[test]
+    fn events_are_written_to_the_session_log() {
+        let directory = env::temp_dir().join(format!("microswitch-session-record-{}", std::process::id()));
+        let config_source = format!(r###"
+banks:
+  - id: drums
+    samples:
+      - id: kick
+        file: kick.wav
+switches:
+  - title: Kick
+    play:
+      bank: drums
+      sample: kick
+sessionLog:
+  directory: "{}"
+"###, directory.to_str().unwrap().replace('\\', "\\\\"));
+        let config = Config::from_string(&config_source, None).unwrap();
+        let (operation_sender, operation_receiver) = mpsc::channel();
+        let record_thread = SessionRecordThread::new(&config, config.session_log.as_ref().unwrap(), SoundThreadRpc::from_sender(operation_sender)).unwrap();
+        let path = record_thread.path().to_path_buf();
+
+        let event_sender = match operation_receiver.recv_timeout(Duration::from_secs(5)).unwrap() {
+            Operation::Subscribe { event_sender } => event_sender,
+            operation => panic!("Unexpected operation {:?}", operation),
+        };
+        event_sender.send(SoundThreadEvent::SwitchInputReceived {
+            switch_ref: SwitchRef { switch_index: 0 },
+            command: SwitchCommand::Press,
+            source: InputSource::Keyboard,
+            received_at: Instant::now(),
+        }).unwrap();
+        event_sender.send(SoundThreadEvent::SwitchPressed(SwitchRef { switch_index: 0 })).unwrap();
+        event_sender.send(SoundThreadEvent::PlayedSample(BankSampleRef::default())).unwrap();
+        record_thread.stop().unwrap();
+
+        let entries = read_session_log(&path).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(entries.len(), 3);
+        assert!(matches!(entries[0], SessionLogEntry::Session { .. }));
+        assert!(matches!(&entries[1], SessionLogEntry::Input { switch: 0, title, command: SwitchCommand::Press, source: InputSource::Keyboard, .. } if title == "Kick"));
+        assert!(matches!(&entries[2], SessionLogEntry::Played { bank, sample, .. } if bank == "drums" && sample == "kick"));
+    }
+}
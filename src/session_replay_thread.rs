@@ -0,0 +1,156 @@
+use crate::config::Config;
+use crate::error::{readable_thread_panic_error, SessionReplayThreadError};
+use crate::session_log::{read_session_log, replay_inputs, ReplayInput};
+use crate::sound_thread::{InputSource, SoundThreadRpc};
+use std::path::Path;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::thread;
+use std::time::Instant;
+
+#[derive(Debug)]
+enum SessionReplayOperation {
+    Stop,
+}
+
+struct SessionReplayThreadBody {
+    inputs: Vec<ReplayInput>,
+    sound_thread_rpc: SoundThreadRpc,
+    operation_receiver: Receiver<SessionReplayOperation>,
+}
+
+impl SessionReplayThreadBody {
+    /// Returns false if the thread should stop
+    fn wait_until(&self, deadline: Instant) -> Result<bool, SessionReplayThreadError> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+
+        match self.operation_receiver.recv_timeout(timeout) {
+            Ok(SessionReplayOperation::Stop) => Ok(false),
+            Err(RecvTimeoutError::Timeout) => Ok(true),
+            Err(err @ RecvTimeoutError::Disconnected) => Err(SessionReplayThreadError::from(err)),
+        }
+    }
+
+    fn thread_body(self) -> Result<(), SessionReplayThreadError> {
+        let started_at = Instant::now();
+
+        for input in &self.inputs {
+            if !self.wait_until(started_at + input.time)? {
+                return Ok(());
+            }
+
+            if let Err(err) = self.sound_thread_rpc.switch_command(input.command, input.switch_ref, InputSource::Replay) {
+                eprintln!("Error sending {:?} {:?} to sound thread {}", input.command, input.switch_ref, err);
+                return Err(SessionReplayThreadError::SendSoundThread);
+            }
+        }
+
+        println!("Finished replaying the session");
+
+        // wait for the stop operation
+        match self.operation_receiver.recv() {
+            Ok(SessionReplayOperation::Stop) | Err(_) => Ok(()),
+        }
+    }
+}
+
+/// The SessionReplayThread presses the switches of a recorded session log, with the original
+/// timing. The switches are pressed through the SoundThread like any other input, so the input
+/// filters and gestures are applied again.
+pub struct SessionReplayThread {
+    operation_sender: Sender<SessionReplayOperation>,
+    handle: JoinHandle<()>,
+}
+
+impl SessionReplayThread {
+    pub fn new(session_log_path: &Path, config: &Config, sound_thread_rpc: SoundThreadRpc) -> Result<Self, SessionReplayThreadError> {
+        let entries = read_session_log(session_log_path)?;
+        let inputs = replay_inputs(config, &entries);
+        println!("Replaying {} switch inputs from {}", inputs.len(), session_log_path.to_str().unwrap());
+
+        let (operation_sender, operation_receiver) = mpsc::channel();
+
+        let body = SessionReplayThreadBody {
+            inputs,
+            sound_thread_rpc,
+            operation_receiver,
+        };
+
+        let handle = thread::spawn(move || {
+            body.thread_body()
+                .expect("Error during SessionReplayThreadBody.thread_body()");
+        });
+
+        Ok(Self { operation_sender, handle })
+    }
+
+    pub fn stop(self) -> Result<(), SessionReplayThreadError> {
+        if let Err(err) = self.operation_sender.send(SessionReplayOperation::Stop) {
+            eprintln!("Failed to send stop operation to SessionReplayThread: {}", err);
+            // Still try to join in this case, this will probably give us more error details
+        }
+        let handle = self.handle;
+
+        match handle.join() {
+            Ok(_) => Ok(()),
+            Err(join_error) => {
+                let join_error_str = readable_thread_panic_error(&join_error);
+                Err(SessionReplayThreadError::JoinPanic {
+                    join_error,
+                    join_error_str,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+    use crate::config::Config;
+    use crate::session_replay_thread::SessionReplayThread;
+    use crate::sound_thread::{InputSource, Operation, SoundThreadRpc};
+    use pretty_assertions::{assert_eq};
+
+    #[test]
+    fn switches_are_pressed_with_the_recorded_timing() {
+        let path = env::temp_dir().join(format!("microswitch-session-replay-{}.jsonl", std::process::id()));
+        fs::write(&path, r#"{"type":"session","startedAt":"2026-10-17T16:14:34Z","version":"1.0.0"}
+{"type":"input","timeMs":0,"switch":1,"title":"Stop","command":"press","source":"keyboard"}
+{"type":"played","timeMs":1,"bank":"drums","sample":"kick"}
+{"type":"input","timeMs":200,"switch":1,"title":"Stop","command":"release","source":"keyboard"}
+"#).unwrap();
+        let config = Config::from_string(r###"
+banks: []
+switches:
+  - title: Stop
+    stopSounds: true
+"###, None).unwrap();
+
+        let (operation_sender, operation_receiver) = mpsc::channel();
+        let started_at = Instant::now();
+        let replay_thread = SessionReplayThread::new(&path, &config, SoundThreadRpc::from_sender(operation_sender)).unwrap();
+        fs::remove_file(&path).unwrap();
+        let receive_operation = || operation_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        match receive_operation() {
+            Operation::SwitchPressed { switch_ref, source } => {
+                // the switch has moved to a different index, it is found by its title
+                assert_eq!(switch_ref.switch_index, 0);
+                assert_eq!(source, InputSource::Replay);
+            },
+            operation => panic!("Unexpected operation {:?}", operation),
+        }
+        match receive_operation() {
+            Operation::SwitchReleased { switch_ref, .. } => assert_eq!(switch_ref.switch_index, 0),
+            operation => panic!("Unexpected operation {:?}", operation),
+        }
+        assert!(started_at.elapsed() >= Duration::from_millis(200));
+
+        replay_thread.stop().unwrap();
+    }
+}
@@ -1,10 +1,13 @@
 use std::convert::TryFrom;
 use rand::{Rng, thread_rng};
-use rodio::{OutputStreamHandle, PlayError, Sink};
+use rodio::{Sink, Source};
+use rodio::dynamic_mixer::DynamicMixerController;
+use rodio::queue::SourcesQueueOutput;
 use crate::config::{BankConfig, BankSampleRef, SampleRef};
 use crate::error::{SampleNotFoundError};
 use crate::sample_loader::{LoadedSample, SampleLoader};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// An initialized bank of sounds. This is the combination of a BankConfig and all of its audio
 /// samples loaded into memory.
@@ -78,33 +81,92 @@ fn apply_steps(sample_count: usize, last_played: Option<SampleRef>, steps: i32)
     }
 }
 
+/// The volume of a sample that is being faded out, `elapsed` into a fade of `duration`
+fn fade_out_volume(elapsed: Duration, duration: Duration) -> f32 {
+    if elapsed >= duration {
+        0.0
+    }
+    else {
+        1.0 - elapsed.as_secs_f32() / duration.as_secs_f32()
+    }
+}
+
+/// The output of a Sink that plays a single sample, in the format of that sample. Until it starts
+/// playing, the queue of a Sink reports a placeholder format instead, which would cause the mixer
+/// to convert the start of the sample to the wrong sample rate.
+struct SinkOutput {
+    queue: SourcesQueueOutput<f32>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl Iterator for SinkOutput {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.queue.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.queue.size_hint()
+    }
+}
+
+impl Source for SinkOutput {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// A sink that is no longer associated with a sample, because it is being faded out
+struct FadingSink {
+    sink: Sink,
+    started_at: Instant,
+    duration: Duration,
+}
+
 /// An initialized bank of sounds and its runtime state. This is the combination of a SoundBank,
 /// rodio objects used for playback, and the state needed to properly apply SwitchPlayStep actions.
 pub struct SoundBankState {
-    sound_output_handle: OutputStreamHandle,
+    /// The mix of all banks, see crate::mixer
+    mixer: Arc<DynamicMixerController<f32>>,
     sound_bank: SoundBank,
     // A sink is Option so that we can destroy individual sinks
     sinks: Vec<Option<Sink>>,
+    fading_sinks: Vec<FadingSink>,
     last_played: Option<SampleRef>,
 }
 
 impl SoundBankState {
-    pub fn new(sound_output_handle: OutputStreamHandle, sound_bank: SoundBank) -> Self {
+    pub fn new(mixer: Arc<DynamicMixerController<f32>>, sound_bank: SoundBank) -> Self {
         // reserve a location in a vector of sinks, for every sample, but set them to None for now.
         let sinks = sound_bank.samples.iter().map(|_sample| None).collect();
 
         SoundBankState {
-            sound_output_handle,
+            mixer,
             sound_bank,
             sinks,
+            fading_sinks: Vec::new(),
             last_played: None,
         }
     }
 
-    pub fn new_all(sound_output_handle: &OutputStreamHandle, sound_banks: Vec<SoundBank>) -> Vec<Self> {
+    pub fn new_all(mixer: &Arc<DynamicMixerController<f32>>, sound_banks: Vec<SoundBank>) -> Vec<Self> {
         sound_banks
             .into_iter()
-            .map(|sound_bank| SoundBankState::new(sound_output_handle.clone(), sound_bank))
+            .map(|sound_bank| SoundBankState::new(Arc::clone(mixer), sound_bank))
             .collect()
     }
 
@@ -133,49 +195,83 @@ impl SoundBankState {
         for sink in self.sinks.iter_mut() {
             sink.take();
         }
+        self.fading_sinks.clear();
     }
 
-    pub fn play(&mut self, sample_ref: SampleRef) -> Result<BankSampleRef, PlayError> {
+    /// Play the samples of this bank in another mix from now on. The samples that are playing in
+    /// the current mix are stopped, but the rest of the state (such as the position of
+    /// SwitchPlayStep actions) is kept.
+    pub fn move_to_mixer(&mut self, mixer: &Arc<DynamicMixerController<f32>>) {
+        self.stop();
+        self.mixer = Arc::clone(mixer);
+    }
+
+    /// Stop a single sample, by lowering its volume to 0 over the given duration. The fade
+    /// progresses each time update_fades() is called.
+    pub fn fade_out(&mut self, sample_ref: SampleRef, duration: Duration, now: Instant) {
+        if let Some(sink) = self.sinks[sample_ref.sample_index].take() {
+            if !duration.is_zero() && !sink.empty() {
+                self.fading_sinks.push(FadingSink { sink, started_at: now, duration });
+            }
+        }
+    }
+
+    /// Returns true if any sample is still being faded out
+    pub fn is_fading(&self) -> bool {
+        !self.fading_sinks.is_empty()
+    }
+
+    /// Returns true if any sample has not finished playing yet
+    pub fn is_playing(&self) -> bool {
+        self.is_fading() || self.sinks.iter().flatten().any(|sink| !sink.empty())
+    }
+
+    pub fn update_fades(&mut self, now: Instant) {
+        self.fading_sinks.retain(|fading_sink| {
+            let elapsed = now.saturating_duration_since(fading_sink.started_at);
+            let volume = fade_out_volume(elapsed, fading_sink.duration);
+            fading_sink.sink.set_volume(volume);
+            volume > 0.0
+        });
+    }
+
+    pub fn play(&mut self, sample_ref: SampleRef) -> BankSampleRef {
         self.stop_if_not_poly();
         self.last_played = Some(sample_ref);
         let sample = self.sound_bank.get_sample(sample_ref);
 
-        let sink = Sink::try_new(&self.sound_output_handle)?;
-        self.sinks[sample_ref.sample_index] = Some(sink);
-        let sink = self.sinks[sample_ref.sample_index].as_ref().unwrap();
-
         let bank_config = &self.sound_bank.config;
         let sample_config = bank_config.sample(sample_ref);
         println!("Playing from bank \"{}\", the sample \"{}\"", bank_config.id.as_str(), sample_config.id.as_str());
 
-        sample.play(sink);
-        Ok(BankSampleRef {
+        // the sample is appended before the sink is mixed, so that nothing else is played first
+        let (sink, queue) = Sink::new_idle();
+        sample.play(&sink, sample_config.gain);
+        self.mixer.add(SinkOutput { queue, channels: sample.channels(), sample_rate: sample.sample_rate() });
+        self.sinks[sample_ref.sample_index] = Some(sink);
+
+        BankSampleRef {
             bank: self.sound_bank.config.bank_ref,
             sample: sample_ref,
-        })
+        }
     }
 
-    pub fn play_random(&mut self) -> Result<Option<BankSampleRef>, PlayError> {
-        match self.pick_random_sample() {
-            None => Ok(None),
-            Some(sample_ref) => Ok(Some(self.play(sample_ref)?)),
-        }
+    pub fn play_random(&mut self) -> Option<BankSampleRef> {
+        let sample_ref = self.pick_random_sample()?;
+        Some(self.play(sample_ref))
     }
 
-    pub fn play_step(&mut self, steps: i32)  -> Result<Option<BankSampleRef>, PlayError> {
-        if let Some(sample_ref) = apply_steps(self.sound_bank.sample_count(), self.last_played, steps) {
-            Ok(Some(self.play(sample_ref)?))
-        }
-        else {
-            Ok(None)
-        }
+    pub fn play_step(&mut self, steps: i32) -> Option<BankSampleRef> {
+        let sample_ref = apply_steps(self.sound_bank.sample_count(), self.last_played, steps)?;
+        Some(self.play(sample_ref))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
     use crate::config::SampleRef;
-    use crate::sound_bank::apply_steps;
+    use crate::sound_bank::{apply_steps, fade_out_volume};
 
     #[test]
     fn apply_steps_calculation() {
@@ -205,4 +301,15 @@ mod tests {
         assert_eq!(apply_steps(4, s(3), 2), s(1));
         assert_eq!(apply_steps(4, s(2), 5), s(3));
     }
+
+    #[test]
+    fn fade_out_volume_calculation() {
+        let ms = Duration::from_millis;
+
+        assert_eq!(fade_out_volume(ms(0), ms(200)), 1.0);
+        assert_eq!(fade_out_volume(ms(50), ms(200)), 0.75);
+        assert_eq!(fade_out_volume(ms(100), ms(200)), 0.5);
+        assert_eq!(fade_out_volume(ms(200), ms(200)), 0.0);
+        assert_eq!(fade_out_volume(ms(300), ms(200)), 0.0);
+    }
 }
@@ -1,12 +1,24 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
-use std::sync::mpsc::{Receiver, Sender, SendError};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender, SendError};
 use std::sync::mpsc;
 use std::thread;
 use std::thread::JoinHandle;
-use rodio::OutputStream;
-use crate::config::{BankSampleRef, Config, SwitchRef};
+use std::time::{Duration, Instant};
+use rodio::dynamic_mixer::DynamicMixerController;
+use serde::{Deserialize, Serialize};
+use crate::config::{BankSampleRef, Config, ScanAction, SwitchActionsView, SwitchRef};
+use crate::audio_output::{AudioOutput, AudioOutputStatus, OfflineAudioOutput};
 use crate::error::{readable_thread_panic_error, SoundThreadError};
+use crate::gesture::{Gesture, GestureDetector};
+use crate::input_filter::InputFilter;
+use crate::midi_output::{MidiOutput, NullMidiOutput, SystemMidiOutput};
+use crate::mixer::{mixer, MixTap, MIX_CHANNELS, MIX_SAMPLE_RATE};
+use crate::recording_thread::RecordingThread;
 use crate::sample_loader::{SampleLoader};
+use crate::session_log::ReplayInput;
 use crate::sound_bank::{SoundBank, SoundBankState};
 
 #[derive(Debug)]
@@ -14,23 +26,168 @@ pub enum Operation {
     Stop,
     SwitchPressed {
         switch_ref: SwitchRef,
+        source: InputSource,
     },
+    SwitchReleased {
+        switch_ref: SwitchRef,
+        source: InputSource,
+    },
+    /// A press immediately followed by a release, such as a click on a button in the GUI. The
+    /// samples of a switch with `holdToPlay` are not stopped.
+    SwitchClicked {
+        switch_ref: SwitchRef,
+        source: InputSource,
+    },
+    ReloadConfig {
+        config: Box<Config>,
+    },
+    /// Send a copy of every SoundThreadEvent to this channel as well
+    Subscribe {
+        event_sender: Sender<SoundThreadEvent>,
+    },
+    /// Write everything that is played to a new WAV file, stopping the current recording if any
+    StartRecording {
+        path: PathBuf,
+    },
+    StopRecording,
 }
 
-#[derive(Debug)]
+/// How often the volume of samples that are fading out is updated
+const FADE_INTERVAL: Duration = Duration::from_millis(10);
+/// How often the AudioOutput is checked, to notice that a sound device has been disconnected or
+/// has returned
+const AUDIO_OUTPUT_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+/// How much is rendered at once by render_offline(), while waiting for the last samples to finish
+const RENDER_TAIL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone)]
 pub enum SoundThreadEvent {
+    /// A switch has been pressed, released or clicked, before the input filter has been applied
+    SwitchInputReceived {
+        switch_ref: SwitchRef,
+        command: SwitchCommand,
+        source: InputSource,
+        /// When the SoundThread received the input, so that it can be logged accurately even if
+        /// the event is handled later
+        received_at: Instant,
+    },
+    /// A press of the switch has passed its input filter
+    SwitchPressed(SwitchRef),
+    SwitchReleased(SwitchRef),
     PlayedSample(BankSampleRef),
+    /// A sample has been stopped because the switch that played it (with `holdToPlay`) has been
+    /// released
+    StoppedSample(BankSampleRef),
+    /// All sounds have been stopped by the `stopSounds` action
+    StoppedSounds,
+    /// The SoundThread has loaded all samples of a new config and is now using it
+    ConfigReloaded(Box<Config>),
+    /// A press of the switch has been ignored by its input filter
+    PressFiltered {
+        switch_ref: SwitchRef,
+        filtered_count: u64,
+    },
+    /// A switch has performed a scan action, which is handled by the GUI
+    Scan(ScanAction),
+    /// Everything that is played is now written to this WAV file
+    RecordingStarted(PathBuf),
+    /// The recording has been written to this WAV file
+    RecordingStopped(PathBuf),
+    /// Nothing can be played, because the sound device has been disconnected. The SoundThread
+    /// keeps trying to play on the same device, or on the default device.
+    AudioOutputLost,
+    /// Sound is played again, on the sound device with this name (if known)
+    AudioOutputRestored(Option<String>),
+}
+
+/// Sends every event to the GUI, and a copy to each subscriber (see SoundThreadRpc::subscribe)
+struct EventSenders {
+    gui: Sender<SoundThreadEvent>,
+    subscribers: Vec<Sender<SoundThreadEvent>>,
+}
+
+impl EventSenders {
+    fn send(&mut self, event: SoundThreadEvent) -> Result<(), SendError<SoundThreadEvent>> {
+        // forget about subscribers that have gone away
+        self.subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        self.gui.send(event)
+    }
+}
+
+/// The source of the current time of a SoundThreadBody
+#[derive(Debug, Copy, Clone)]
+enum Clock {
+    System,
+    /// Advanced by render_offline(), so that the result does not depend on how fast the computer
+    /// renders
+    Manual(Instant),
+}
+
+/// The mixer that the sinks of all banks are added to, and where its output goes
+struct MixOutput {
+    mixer: Arc<DynamicMixerController<f32>>,
+    tap: MixTap,
+    /// Where the tap sends the samples, this is kept so that a new mix can be tapped as well
+    tap_sender: Option<Sender<Vec<f32>>>,
+    // if the AudioOutput is dropped, the mix is no longer played
+    audio_output: Box<dyn AudioOutput>,
+}
+
+impl MixOutput {
+    fn new(mut audio_output: Box<dyn AudioOutput>) -> Result<Self, SoundThreadError> {
+        let (mixer, mix_source, tap) = mixer();
+        audio_output.play(mix_source)?;
+        Ok(Self { mixer, tap, tap_sender: None, audio_output })
+    }
+
+    /// Play a new, silent mix on the AudioOutput. The sinks that were added to the previous mix
+    /// are no longer played.
+    fn replace_mix(&mut self) -> Result<(), SoundThreadError> {
+        let (mixer, mix_source, tap) = mixer();
+        self.audio_output.play(mix_source)?;
+
+        if let Some(tap_sender) = &self.tap_sender {
+            tap.start(tap_sender.clone());
+        }
+        self.mixer = mixer;
+        self.tap = tap;
+        Ok(())
+    }
+
+    fn start_tap(&mut self, sender: Sender<Vec<f32>>) {
+        self.tap.start(sender.clone());
+        self.tap_sender = Some(sender);
+    }
+
+    fn stop_tap(&mut self) {
+        self.tap.stop();
+        self.tap_sender = None;
+    }
 }
 
 /// A single SoundThreadBody instance is created for each spawned sound thread, in order to track
 /// the state of the track.
 struct SoundThreadBody {
     operation_receiver: Receiver<Operation>,
-    event_sender: Sender<SoundThreadEvent>,
+    events: EventSenders,
     config: Config,
+    /// Kept around so that samples which have already been decoded are reused when the config is
+    /// reloaded
+    sample_loader: Box<dyn SampleLoader>,
     banks: Vec<SoundBankState>,
-    // if _sound_output is dropped, sound_output_handle will no longer be usable
-    _sound_output: OutputStream,
+    /// The samples played by pressing a switch with `holdToPlay`, which are stopped when the switch
+    /// is released
+    held_samples: HashMap<SwitchRef, Vec<BankSampleRef>>,
+    gestures: GestureDetector,
+    input_filter: InputFilter,
+    midi_output: MidiOutput,
+    output: MixOutput,
+    /// None if nothing is being recorded
+    recording_thread: Option<RecordingThread>,
+    /// True if the AudioOutput is no longer playing the mix, and playing a new mix has failed so far
+    audio_output_lost: bool,
+    next_audio_output_check: Instant,
+    clock: Clock,
 }
 
 impl SoundThreadBody {
@@ -39,78 +196,496 @@ impl SoundThreadBody {
         sample_loader: Box<dyn SampleLoader>,
         operation_receiver: Receiver<Operation>,
         event_sender: Sender<SoundThreadEvent>,
+        audio_output: Box<dyn AudioOutput>,
+    ) -> Result<Self, SoundThreadError> {
+        let output = MixOutput::new(audio_output)?;
+        let midi_output = MidiOutput::new(Box::new(SystemMidiOutput::new()));
+        Self::with_output(config, sample_loader, operation_receiver, event_sender, output, midi_output, Clock::System)
+    }
+
+    fn with_output(
+        config: Config,
+        sample_loader: Box<dyn SampleLoader>,
+        operation_receiver: Receiver<Operation>,
+        event_sender: Sender<SoundThreadEvent>,
+        output: MixOutput,
+        midi_output: MidiOutput,
+        clock: Clock,
     ) -> Result<Self, SoundThreadError> {
-        let mut loader = sample_loader;
-        loader.load_banks(&config.banks)?;
+        let mut sample_loader = sample_loader;
+        let banks = Self::load_banks(&mut *sample_loader, &config, &output.mixer)?;
 
-        let banks = SoundBank::new_all(&*loader, config.banks.clone())
+        Ok(Self {
+            operation_receiver,
+            events: EventSenders { gui: event_sender, subscribers: Vec::new() },
+            config,
+            sample_loader,
+            banks,
+            held_samples: HashMap::new(),
+            gestures: GestureDetector::new(),
+            input_filter: InputFilter::new(),
+            midi_output,
+            output,
+            recording_thread: None,
+            audio_output_lost: false,
+            next_audio_output_check: Instant::now() + AUDIO_OUTPUT_CHECK_INTERVAL,
+            clock,
+        })
+    }
+
+    fn now(&self) -> Instant {
+        match self.clock {
+            Clock::System => Instant::now(),
+            Clock::Manual(now) => now,
+        }
+    }
+
+    fn load_banks(
+        sample_loader: &mut dyn SampleLoader,
+        config: &Config,
+        mixer: &Arc<DynamicMixerController<f32>>,
+    ) -> Result<Vec<SoundBankState>, SoundThreadError> {
+        sample_loader.load_banks(&config.banks)?;
+
+        let banks = SoundBank::new_all(sample_loader, config.banks.clone())
             .expect("SoundThread: Failed to find sound sample, which should just have been loaded");
 
-        // if _sound_output is dropped, sound_output_handle will no longer be usable
-        let (_sound_output, sound_output_handle) = OutputStream::try_default()?;
+        Ok(SoundBankState::new_all(mixer, banks))
+    }
 
-        let banks = SoundBankState::new_all(&sound_output_handle, banks);
+    fn handle_operation_reload_config(&mut self, config: Config) -> Result<(), SoundThreadError> {
+        // If loading fails, the current banks and config remain untouched
+        let banks = Self::load_banks(&mut *self.sample_loader, &config, &self.output.mixer)?;
 
-        Ok(Self { operation_receiver, event_sender, config, banks, _sound_output })
+        // this drops the previous sinks, which stops any sound that is still playing
+        self.banks = banks;
+        self.held_samples.clear();
+        self.gestures.clear();
+        self.input_filter.clear();
+        if let Err(err) = self.midi_output.stop_all() {
+            eprintln!("SoundThread: Failed to stop MIDI notes: {}", err);
+        }
+        self.config = config.clone();
+        println!("SoundThread: Now using the reloaded config");
+
+        self.events.send(SoundThreadEvent::ConfigReloaded(Box::new(config)))?;
+        Ok(())
     }
 
-    fn handle_operation_switch_pressed(&mut self, switch_ref: SwitchRef) -> Result<(), SoundThreadError> {
-        let switch_config = self.config.switch(switch_ref);
+    /// Perform the given actions, returns the samples that have been played. `held_by` is the
+    /// switch that has been pressed to perform these actions, if any.
+    fn perform_actions(
+        banks: &mut [SoundBankState],
+        midi_output: &mut MidiOutput,
+        events: &mut EventSenders,
+        actions: SwitchActionsView,
+        held_by: Option<SwitchRef>,
+        now: Instant,
+    ) -> Result<Vec<BankSampleRef>, SoundThreadError> {
+        let mut played = Vec::new();
 
-        if switch_config.stop_sounds {
-            for bank in self.banks.iter_mut() {
+        if actions.stop_sounds {
+            for bank in banks.iter_mut() {
                 bank.stop();
             }
+            midi_output.stop_all()?;
+            events.send(SoundThreadEvent::StoppedSounds)?;
         }
 
-        if let Some(play) = &switch_config.play {
+        if let Some(play) = actions.play {
             let bank_sample_ref = play.bank_sample_ref;
-            let bank_state = &mut self.banks[bank_sample_ref.bank.bank_index];
-            bank_state.play(bank_sample_ref.sample)?;
-            self.event_sender.send(SoundThreadEvent::PlayedSample(bank_sample_ref))?;
+            let bank_state = &mut banks[bank_sample_ref.bank.bank_index];
+            bank_state.play(bank_sample_ref.sample);
+            played.push(bank_sample_ref);
         }
 
-        if let Some(play) = &switch_config.play_random {
+        if let Some(play) = actions.play_random {
             let bank_ref = play.bank_ref;
-            let bank_state = &mut self.banks[bank_ref.bank_index];
-            if let Some(bank_sample_ref) = bank_state.play_random()? {
-                self.event_sender.send(SoundThreadEvent::PlayedSample(bank_sample_ref))?;
+            let bank_state = &mut banks[bank_ref.bank_index];
+            if let Some(bank_sample_ref) = bank_state.play_random() {
+                played.push(bank_sample_ref);
             }
         }
 
-        if let Some(play) = &switch_config.play_step {
-            let bank_state = &mut self.banks[play.bank_ref.bank_index];
-            if let Some(bank_sample_ref) = bank_state.play_step(play.steps)? {
-                self.event_sender.send(SoundThreadEvent::PlayedSample(bank_sample_ref))?;
+        if let Some(play) = actions.play_step {
+            let bank_state = &mut banks[play.bank_ref.bank_index];
+            if let Some(bank_sample_ref) = bank_state.play_step(play.steps) {
+                played.push(bank_sample_ref);
             }
         }
 
+        for bank_sample_ref in &played {
+            events.send(SoundThreadEvent::PlayedSample(*bank_sample_ref))?;
+        }
+
+        if let Some(scan) = actions.scan {
+            events.send(SoundThreadEvent::Scan(scan))?;
+        }
+
+        if let Some(midi_out) = actions.midi_out {
+            midi_output.perform(midi_out, held_by, now)?;
+        }
+
+        Ok(played)
+    }
+
+    fn handle_gesture(&mut self, switch_ref: SwitchRef, gesture: Option<Gesture>) -> Result<(), SoundThreadError> {
+        let switch_config = self.config.switch(switch_ref);
+
+        if let Some(actions) = gesture.and_then(|gesture| switch_config.gesture_actions(gesture)) {
+            println!("Switch \"{}\": {:?}", switch_config.title, gesture.unwrap());
+            let now = self.now();
+            Self::perform_actions(&mut self.banks, &mut self.midi_output, &mut self.events, actions, None, now)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_expired_gestures(&mut self) -> Result<(), SoundThreadError> {
+        for (switch_ref, gesture) in self.gestures.expire(self.now()) {
+            self.handle_gesture(switch_ref, Some(gesture))?;
+        }
+
+        Ok(())
+    }
+
+    /// Presses which have been accepted by the input filter because the switch has been held down
+    /// long enough
+    fn handle_expired_input_filters(&mut self) -> Result<(), SoundThreadError> {
+        for switch_ref in self.input_filter.expire(self.now()) {
+            self.handle_switch_pressed(switch_ref)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the press passes the input filter of the switch
+    fn filter_switch_pressed(&mut self, switch_ref: SwitchRef, allow_min_hold: bool) -> Result<bool, SoundThreadError> {
+        let mut settings = self.config.input_filter_settings(switch_ref);
+        if !allow_min_hold {
+            // a click does not tell us how long the switch has been held down
+            settings.min_hold = Duration::ZERO;
+        }
+
+        let previous_filtered_count = self.input_filter.filtered_count(switch_ref);
+        let accepted = self.input_filter.pressed(switch_ref, settings, self.now());
+        self.send_filtered_count(switch_ref, previous_filtered_count)?;
+        Ok(accepted)
+    }
+
+    /// Returns true if the release passes the input filter of the switch
+    fn filter_switch_released(&mut self, switch_ref: SwitchRef) -> Result<bool, SoundThreadError> {
+        let previous_filtered_count = self.input_filter.filtered_count(switch_ref);
+        let accepted = self.input_filter.released(switch_ref, self.now());
+        self.send_filtered_count(switch_ref, previous_filtered_count)?;
+        Ok(accepted)
+    }
+
+    fn send_filtered_count(&mut self, switch_ref: SwitchRef, previous_filtered_count: u64) -> Result<(), SoundThreadError> {
+        let filtered_count = self.input_filter.filtered_count(switch_ref);
+
+        if filtered_count != previous_filtered_count {
+            println!("Switch \"{}\": Filtered press ({} in total)", self.config.switch(switch_ref).title, filtered_count);
+            self.events.send(SoundThreadEvent::PressFiltered { switch_ref, filtered_count })?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_operation_switch_pressed(&mut self, switch_ref: SwitchRef, source: InputSource) -> Result<(), SoundThreadError> {
+        self.events.send(SoundThreadEvent::SwitchInputReceived { switch_ref, command: SwitchCommand::Press, source, received_at: self.now() })?;
+
+        if self.input_filter.release_lost(switch_ref) {
+            self.handle_switch_released(switch_ref, true)?;
+        }
+
+        if self.filter_switch_pressed(switch_ref, true)? {
+            self.handle_switch_pressed(switch_ref)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_operation_switch_released(&mut self, switch_ref: SwitchRef, source: InputSource) -> Result<(), SoundThreadError> {
+        self.events.send(SoundThreadEvent::SwitchInputReceived { switch_ref, command: SwitchCommand::Release, source, received_at: self.now() })?;
+
+        if self.filter_switch_released(switch_ref)? {
+            self.handle_switch_released(switch_ref, true)?;
+        }
+
         Ok(())
     }
 
+    fn handle_operation_switch_clicked(&mut self, switch_ref: SwitchRef, source: InputSource) -> Result<(), SoundThreadError> {
+        self.events.send(SoundThreadEvent::SwitchInputReceived { switch_ref, command: SwitchCommand::Click, source, received_at: self.now() })?;
+
+        if self.filter_switch_pressed(switch_ref, false)? {
+            self.handle_switch_pressed(switch_ref)?;
+        }
+
+        if self.filter_switch_released(switch_ref)? {
+            self.handle_switch_released(switch_ref, false)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_switch_pressed(&mut self, switch_ref: SwitchRef) -> Result<(), SoundThreadError> {
+        self.events.send(SoundThreadEvent::SwitchPressed(switch_ref))?;
+        let now = self.now();
+        let switch_config = self.config.switch(switch_ref);
+        let played = Self::perform_actions(&mut self.banks, &mut self.midi_output, &mut self.events, switch_config.press_actions(), Some(switch_ref), now)?;
+
+        if switch_config.hold_to_play {
+            self.held_samples.insert(switch_ref, played);
+        }
+
+        if let Some(timing) = switch_config.gesture_timing() {
+            let gesture = self.gestures.pressed(switch_ref, timing, now);
+            self.handle_gesture(switch_ref, gesture)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_switch_released(&mut self, switch_ref: SwitchRef, stop_held_samples: bool) -> Result<(), SoundThreadError> {
+        self.events.send(SoundThreadEvent::SwitchReleased(switch_ref))?;
+        let now = self.now();
+        let switch_config = self.config.switch(switch_ref);
+
+        if stop_held_samples {
+            if let Some(held_samples) = self.held_samples.remove(&switch_ref) {
+                let fade_out = Duration::from_millis(switch_config.fade_out_ms);
+
+                for bank_sample_ref in held_samples {
+                    self.banks[bank_sample_ref.bank.bank_index].fade_out(bank_sample_ref.sample, fade_out, now);
+                    self.events.send(SoundThreadEvent::StoppedSample(bank_sample_ref))?;
+                }
+            }
+
+            if let Err(err) = self.midi_output.release(switch_ref) {
+                eprintln!("SoundThread: Failed to stop MIDI notes: {}", err);
+            }
+        }
+        else {
+            self.midi_output.release_later(switch_ref, now);
+        }
+
+        if let Some(actions) = switch_config.release_actions() {
+            Self::perform_actions(&mut self.banks, &mut self.midi_output, &mut self.events, actions, None, now)?;
+        }
+
+        if let Some(timing) = switch_config.gesture_timing() {
+            let gesture = self.gestures.released(switch_ref, timing, now);
+            self.handle_gesture(switch_ref, gesture)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_operation_start_recording(&mut self, path: PathBuf) -> Result<(), SoundThreadError> {
+        self.stop_recording()?;
+
+        let (sample_sender, sample_receiver) = mpsc::channel();
+        let recording_thread = RecordingThread::new(&path, MIX_CHANNELS, MIX_SAMPLE_RATE, sample_receiver)?;
+        self.output.start_tap(sample_sender);
+        let path = recording_thread.path().to_path_buf();
+        self.recording_thread = Some(recording_thread);
+
+        self.events.send(SoundThreadEvent::RecordingStarted(path))?;
+        Ok(())
+    }
+
+    fn stop_recording(&mut self) -> Result<(), SoundThreadError> {
+        if let Some(recording_thread) = self.recording_thread.take() {
+            // this drops every sender of the recording once the tap has sent its last samples,
+            // which the RecordingThread waits for
+            self.output.stop_tap();
+            let path = recording_thread.path().to_path_buf();
+            recording_thread.stop()?;
+            self.events.send(SoundThreadEvent::RecordingStopped(path))?;
+        }
+
+        Ok(())
+    }
+
+    fn is_fading(&self) -> bool {
+        self.banks.iter().any(SoundBankState::is_fading)
+    }
+
+    fn is_playing(&self) -> bool {
+        self.banks.iter().any(SoundBankState::is_playing)
+    }
+
+    fn update_fades(&mut self) {
+        let now = self.now();
+        for bank in self.banks.iter_mut() {
+            bank.update_fades(now);
+        }
+    }
+
+    /// Update the volume of samples that are fading out, and handle gestures, input filters and
+    /// MIDI notes which depend on the passing of time
+    fn handle_timers(&mut self) {
+        self.update_fades();
+        if let Err(err) = self.handle_expired_input_filters() {
+            eprintln!("SoundThread: Failed to handle switch press: {:?}", err);
+        }
+        if let Err(err) = self.handle_expired_gestures() {
+            eprintln!("SoundThread: Failed to handle gesture: {:?}", err);
+        }
+        if let Err(err) = self.midi_output.expire(self.now()) {
+            eprintln!("SoundThread: Failed to stop MIDI notes: {}", err);
+        }
+    }
+
+    /// Returns when handle_timers() should be called again, or None if nothing depends on the
+    /// passing of time
+    fn next_deadline(&self) -> Option<Instant> {
+        let deadline = [
+            self.gestures.next_deadline(),
+            self.input_filter.next_deadline(),
+            self.midi_output.next_deadline(),
+        ].into_iter().flatten().min();
+
+        if self.is_fading() {
+            let next_fade = self.now() + FADE_INTERVAL;
+            Some(deadline.map_or(next_fade, |deadline| deadline.min(next_fade)))
+        }
+        else {
+            deadline
+        }
+    }
+
+    /// Play a new mix if the AudioOutput has stopped playing (for example because the sound device
+    /// has been disconnected), or if it can play on its preferred device again. The banks are moved
+    /// to the new mix, without loading their samples again. The sounds that were playing are
+    /// stopped.
+    fn check_audio_output(&mut self) -> Result<(), SoundThreadError> {
+        let now = Instant::now();
+        if now < self.next_audio_output_check {
+            return Ok(());
+        }
+        self.next_audio_output_check = now + AUDIO_OUTPUT_CHECK_INTERVAL;
+
+        match self.output.audio_output.check() {
+            AudioOutputStatus::Playing => return Ok(()),
+            AudioOutputStatus::Lost => {
+                if !self.audio_output_lost {
+                    eprintln!("SoundThread: The audio output has stopped playing, was the sound device disconnected?");
+                    self.audio_output_lost = true;
+                    self.events.send(SoundThreadEvent::AudioOutputLost)?;
+                }
+            },
+            AudioOutputStatus::PreferredDeviceAvailable => {
+                println!("The preferred audio output device is available again");
+            },
+        }
+
+        if let Err(err) = self.output.replace_mix() {
+            // try again at the next check
+            eprintln!("SoundThread: Failed to open the audio output: {}", err);
+            return Ok(());
+        }
+
+        for bank in self.banks.iter_mut() {
+            bank.move_to_mixer(&self.output.mixer);
+        }
+        self.audio_output_lost = false;
+        let device_name = self.output.audio_output.device_name().map(String::from);
+        self.events.send(SoundThreadEvent::AudioOutputRestored(device_name))?;
+        Ok(())
+    }
+
+    /// Wait for the next operation. In the meantime the volume of samples that are fading out is
+    /// updated, gestures, input filters and MIDI notes which depend on the passing of time are
+    /// handled, and the AudioOutput is checked.
+    fn receive_operation(&mut self) -> Result<Operation, SoundThreadError> {
+        loop {
+            self.handle_timers();
+            if let Err(err) = self.check_audio_output() {
+                eprintln!("SoundThread: Failed to check the audio output: {:?}", err);
+            }
+
+            let timeout = match self.next_deadline() {
+                Some(deadline) => deadline.min(self.next_audio_output_check).saturating_duration_since(self.now()),
+                None => self.next_audio_output_check.saturating_duration_since(self.now()),
+            };
+
+            match self.operation_receiver.recv_timeout(timeout) {
+                Ok(received) => return Ok(received),
+                Err(RecvTimeoutError::Timeout) => {},
+                Err(err @ RecvTimeoutError::Disconnected) => return Err(SoundThreadError::from(err)),
+            }
+        }
+    }
+
+    /// Perform a switch input at the current time of the clock, while rendering offline
+    fn handle_replay_input(&mut self, input: &ReplayInput) -> Result<(), SoundThreadError> {
+        match input.command {
+            SwitchCommand::Press => self.handle_operation_switch_pressed(input.switch_ref, InputSource::Replay),
+            SwitchCommand::Release => self.handle_operation_switch_released(input.switch_ref, InputSource::Replay),
+            SwitchCommand::Click => self.handle_operation_switch_clicked(input.switch_ref, InputSource::Replay),
+        }
+    }
+
     fn thread_body(mut self) -> Result<(), SoundThreadError> {
         loop {
-            let received: Operation = self.operation_receiver.recv()?;
+            let received: Operation = self.receive_operation()?;
 
             // Should not return Err() from this point on, otherwise the whole thread stops
             // because of a single bad message (todo: consider making the communication duplex
             // so that we can return errors for individual operations)
             match received {
                 Operation::Stop => {
+                    if let Err(err) = self.midi_output.stop_all() {
+                        eprintln!("SoundThread: Failed to stop MIDI notes: {}", err);
+                    }
+                    if let Err(err) = self.stop_recording() {
+                        eprintln!("SoundThread: Failed to stop recording: {:?}", err);
+                    }
                     return Ok(());
                 }
-                Operation::SwitchPressed { switch_ref } => {
-                    if let Err(err) =  self.handle_operation_switch_pressed(switch_ref) {
+                Operation::SwitchPressed { switch_ref, source } => {
+                    if let Err(err) = self.handle_operation_switch_pressed(switch_ref, source) {
                         eprintln!("SoundThread: Failed to handle switch press: {:?}", err);
                     }
                 }
+                Operation::SwitchReleased { switch_ref, source } => {
+                    if let Err(err) = self.handle_operation_switch_released(switch_ref, source) {
+                        eprintln!("SoundThread: Failed to handle switch release: {:?}", err);
+                    }
+                }
+                Operation::SwitchClicked { switch_ref, source } => {
+                    if let Err(err) = self.handle_operation_switch_clicked(switch_ref, source) {
+                        eprintln!("SoundThread: Failed to handle switch click: {:?}", err);
+                    }
+                }
+                Operation::ReloadConfig { config } => {
+                    if let Err(err) = self.handle_operation_reload_config(*config) {
+                        eprintln!("SoundThread: Failed to reload config, keeping the current config: {}", err);
+                    }
+                }
+                Operation::Subscribe { event_sender } => {
+                    self.events.subscribers.push(event_sender);
+                }
+                Operation::StartRecording { path } => {
+                    if let Err(err) = self.handle_operation_start_recording(path) {
+                        eprintln!("SoundThread: Failed to start recording: {}", err);
+                    }
+                }
+                Operation::StopRecording => {
+                    if let Err(err) = self.stop_recording() {
+                        eprintln!("SoundThread: Failed to stop recording: {}", err);
+                    }
+                }
             };
         }
     }
 }
 
-/// The SoundThread takes care of loading audio samples from disk, playing them using rodio and
-/// tracking the play state of banks/samples.
+/// The SoundThread takes care of loading audio samples from disk, playing them on an AudioOutput
+/// and tracking the play state of banks/samples.
 /// A SoundThread instance is created by the parent process in order to spawn the actual thread and
 /// has methods for communicating with the thread.
 pub struct SoundThread {
@@ -119,14 +694,19 @@ pub struct SoundThread {
 }
 
 impl SoundThread {
-    pub fn new(config: &Config, sample_loader: Box<dyn SampleLoader + Send>) -> Result<(Self, Receiver<SoundThreadEvent>), SoundThreadError> {
+    /// `audio_output` is called on the new thread, and decides where the mix of all banks is
+    /// played
+    pub fn new<F>(config: &Config, sample_loader: Box<dyn SampleLoader + Send>, audio_output: F) -> Result<(Self, Receiver<SoundThreadEvent>), SoundThreadError>
+    where
+        F: FnOnce() -> Box<dyn AudioOutput> + Send + 'static,
+    {
         let config = config.clone();
         let (operation_sender, operation_receiver) = mpsc::channel();
         let (startup_sender, startup_receiver) = mpsc::channel();
         let (event_sender, event_receiver) = mpsc::channel();
 
         let handle = thread::spawn(move || {
-            let result = SoundThreadBody::new(config, sample_loader, operation_receiver, event_sender);
+            let result = SoundThreadBody::new(config, sample_loader, operation_receiver, event_sender, audio_output());
             match result {
                 Ok(body) => {
                     startup_sender.send(Ok(()))
@@ -167,6 +747,109 @@ impl SoundThread {
     }
 }
 
+/// The result of render_offline()
+pub struct OfflineRender {
+    /// The mix of everything that has been played, with MIX_CHANNELS interleaved channels at
+    /// MIX_SAMPLE_RATE
+    pub samples: Vec<f32>,
+    /// Every SoundThreadEvent, along with the time since the start of the rendering
+    pub events: Vec<(Duration, SoundThreadEvent)>,
+}
+
+impl OfflineRender {
+    pub fn channels(&self) -> u16 {
+        MIX_CHANNELS
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        MIX_SAMPLE_RATE
+    }
+
+    pub fn duration(&self) -> Duration {
+        let frames = self.samples.len() / usize::from(MIX_CHANNELS);
+        Duration::from_secs_f64(frames as f64 / f64::from(MIX_SAMPLE_RATE))
+    }
+}
+
+/// The amount of samples (of all channels) in the mix, from the start until the given time
+fn mix_sample_count(time: Duration) -> usize {
+    let frames = (time.as_secs_f64() * f64::from(MIX_SAMPLE_RATE)).round() as usize;
+    frames * usize::from(MIX_CHANNELS)
+}
+
+/// Perform the given switch inputs at their time (which must be in order) and mix everything that
+/// is played, without a sound output device. Instead of the system clock, time passes according to
+/// the amount of samples that have been mixed, so the result is the same every time. Rendering
+/// continues until every sample has finished playing after the last input. MIDI messages are not
+/// sent.
+pub fn render_offline(config: &Config, sample_loader: Box<dyn SampleLoader>, inputs: &[ReplayInput]) -> Result<OfflineRender, SoundThreadError> {
+    let start = Instant::now();
+    let (event_sender, event_receiver) = mpsc::channel();
+    // the inputs are performed directly, instead of being sent as an Operation
+    let (_, operation_receiver) = mpsc::channel();
+    let audio_output = OfflineAudioOutput::new();
+    let output = MixOutput::new(Box::new(audio_output.clone()))?;
+    let midi_output = MidiOutput::new(Box::new(NullMidiOutput));
+    let mut body = SoundThreadBody::with_output(config.clone(), sample_loader, operation_receiver, event_sender, output, midi_output, Clock::Manual(start))?;
+
+    let mut render = OfflineRender { samples: Vec::new(), events: Vec::new() };
+    let mut time = Duration::ZERO;
+    let mut inputs = inputs.iter().peekable();
+
+    loop {
+        body.clock = Clock::Manual(start + time);
+        while let Some(input) = inputs.next_if(|input| input.time <= time) {
+            body.handle_replay_input(input)?;
+        }
+        body.handle_timers();
+        render.events.extend(event_receiver.try_iter().map(|event| (time, event)));
+
+        let next_input = inputs.peek().map(|input| input.time);
+        let next_deadline = body.next_deadline().map(|deadline| deadline.saturating_duration_since(start));
+        time = match next_input.into_iter().chain(next_deadline).min() {
+            Some(next) => next.max(time),
+            None if body.is_playing() => time + RENDER_TAIL_INTERVAL,
+            None => break,
+        };
+
+        let sample_count = mix_sample_count(time);
+        if sample_count > render.samples.len() {
+            let missing = sample_count - render.samples.len();
+            render.samples.extend(audio_output.render(missing));
+        }
+    }
+
+    Ok(render)
+}
+
+/// What to do with a switch, for controls that send commands instead of holding down a switch,
+/// such as OSC and HTTP
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SwitchCommand {
+    Press,
+    Release,
+    Click,
+}
+
+/// The kind of input that has pressed or released a switch
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum InputSource {
+    Keyboard,
+    Mouse,
+    /// A click on a button in the window
+    Gui,
+    /// The scanning highlight has been selected
+    Scan,
+    Gamepad,
+    Midi,
+    Osc,
+    Http,
+    /// A recorded session that is being replayed
+    Replay,
+}
+
 pub struct SoundThreadRpc {
     operation_sender: Sender<Operation>,
 }
@@ -176,7 +859,147 @@ impl SoundThreadRpc {
         SoundThreadRpc { operation_sender: thread.operation_sender.clone() }
     }
 
-    pub fn switch_pressed(&self, switch_ref: SwitchRef) -> Result<(), SendError<Operation>> {
-        self.operation_sender.send(Operation::SwitchPressed { switch_ref })
+    /// Lets tests receive the operations without starting a SoundThread
+    #[cfg(test)]
+    pub fn from_sender(operation_sender: Sender<Operation>) -> Self {
+        SoundThreadRpc { operation_sender }
+    }
+
+    pub fn switch_pressed(&self, switch_ref: SwitchRef, source: InputSource) -> Result<(), SendError<Operation>> {
+        self.operation_sender.send(Operation::SwitchPressed { switch_ref, source })
+    }
+
+    pub fn switch_released(&self, switch_ref: SwitchRef, source: InputSource) -> Result<(), SendError<Operation>> {
+        self.operation_sender.send(Operation::SwitchReleased { switch_ref, source })
+    }
+
+    pub fn switch_clicked(&self, switch_ref: SwitchRef, source: InputSource) -> Result<(), SendError<Operation>> {
+        self.operation_sender.send(Operation::SwitchClicked { switch_ref, source })
+    }
+
+    pub fn switch_command(&self, command: SwitchCommand, switch_ref: SwitchRef, source: InputSource) -> Result<(), SendError<Operation>> {
+        match command {
+            SwitchCommand::Press => self.switch_pressed(switch_ref, source),
+            SwitchCommand::Release => self.switch_released(switch_ref, source),
+            SwitchCommand::Click => self.switch_clicked(switch_ref, source),
+        }
+    }
+
+    pub fn reload_config(&self, config: Config) -> Result<(), SendError<Operation>> {
+        self.operation_sender.send(Operation::ReloadConfig { config: Box::new(config) })
+    }
+
+    pub fn start_recording(&self, path: PathBuf) -> Result<(), SendError<Operation>> {
+        self.operation_sender.send(Operation::StartRecording { path })
+    }
+
+    pub fn stop_recording(&self) -> Result<(), SendError<Operation>> {
+        self.operation_sender.send(Operation::StopRecording)
+    }
+
+    /// Returns a channel which receives a copy of every SoundThreadEvent, from now on
+    pub fn subscribe(&self) -> Result<Receiver<SoundThreadEvent>, SendError<Operation>> {
+        let (event_sender, event_receiver) = mpsc::channel();
+        self.operation_sender.send(Operation::Subscribe { event_sender })?;
+        Ok(event_receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use crate::audio_output::{AudioOutput, AudioOutputStatus, CaptureAudioOutput};
+    use crate::config::{Config, SwitchRef};
+    use crate::error::SoundThreadError;
+    use crate::mixer::MixSource;
+    use crate::sample_loader::EmbeddedSampleLoader;
+    use crate::sound_thread::{InputSource, SoundThread, SoundThreadEvent, SoundThreadRpc, SwitchCommand};
+    use crate::test_util::test_sample;
+    use pretty_assertions::{assert_eq};
+
+    #[test]
+    fn a_clicked_switch_is_played_on_the_audio_output() {
+        let config_source = r###"
+banks:
+  - id: beeps
+    samples:
+      - id: beep
+        file: beep.wav
+switches:
+  - title: Beep
+    play:
+      bank: beeps
+      sample: beep
+"###;
+        let config = Config::from_string(config_source, None).unwrap();
+        let loader = EmbeddedSampleLoader::new(HashMap::from([("beep.wav", test_sample())])).unwrap();
+        let capture = CaptureAudioOutput::new();
+        let thread_capture = capture.clone();
+        let (sound_thread, event_receiver) = SoundThread::new(&config, Box::new(loader), move || -> Box<dyn AudioOutput> {
+            Box::new(thread_capture)
+        }).unwrap();
+
+        SoundThreadRpc::new(&sound_thread).switch_command(SwitchCommand::Click, SwitchRef { switch_index: 0 }, InputSource::Gui).unwrap();
+        let receive_event = || event_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        while !matches!(receive_event(), SoundThreadEvent::PlayedSample(_)) {}
+
+        // both channels of the mix play the mono sample, followed by silence
+        let beep_samples = |samples: &[f32]| samples.iter().filter(|sample| **sample == 0.5).count();
+        let has_played = |samples: &[f32]| beep_samples(samples) == 4410 * 2 && samples.ends_with(&[0.0, 0.0]);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !has_played(&capture.samples()) {
+            assert!(Instant::now() < deadline, "The sample has not been played within 5 seconds");
+            thread::sleep(Duration::from_millis(10));
+        }
+        sound_thread.stop().unwrap();
+
+        let samples = capture.samples();
+        assert_eq!(beep_samples(&samples), 4410 * 2);
+        assert!(samples.ends_with(&[0.0, 0.0]));
+    }
+
+    /// Stops playing the first mix, like a sound device that has been disconnected
+    struct DisconnectingAudioOutput {
+        plays: Arc<AtomicUsize>,
+    }
+
+    impl AudioOutput for DisconnectingAudioOutput {
+        fn play(&mut self, _source: MixSource) -> Result<(), SoundThreadError> {
+            self.plays.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+
+        fn check(&mut self) -> AudioOutputStatus {
+            match self.plays.load(Ordering::Relaxed) {
+                1 => AudioOutputStatus::Lost,
+                _ => AudioOutputStatus::Playing,
+            }
+        }
+
+        fn device_name(&self) -> Option<&str> {
+            Some("Speakers")
+        }
+    }
+
+    #[test]
+    fn a_lost_audio_output_plays_a_new_mix() {
+        let config = Config::from_string("banks: []\nswitches: []\n", None).unwrap();
+        let loader = EmbeddedSampleLoader::new(HashMap::new()).unwrap();
+        let plays = Arc::new(AtomicUsize::new(0));
+        let thread_plays = Arc::clone(&plays);
+        let (sound_thread, event_receiver) = SoundThread::new(&config, Box::new(loader), move || -> Box<dyn AudioOutput> {
+            Box::new(DisconnectingAudioOutput { plays: thread_plays })
+        }).unwrap();
+
+        let receive_event = || event_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(matches!(receive_event(), SoundThreadEvent::AudioOutputLost));
+        assert!(matches!(receive_event(), SoundThreadEvent::AudioOutputRestored(Some(name)) if name == "Speakers"));
+        assert_eq!(plays.load(Ordering::Relaxed), 2);
+
+        sound_thread.stop().unwrap();
     }
 }
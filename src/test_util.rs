@@ -0,0 +1,35 @@
+use crate::config::Config;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::io::Cursor;
+
+/// 100ms of a constant signal, in mono
+pub fn test_sample() -> &'static [u8] {
+    let mut cursor = Cursor::new(Vec::new());
+    let spec = WavSpec { channels: 1, sample_rate: 44100, bits_per_sample: 16, sample_format: SampleFormat::Int };
+    let mut writer = WavWriter::new(&mut cursor, spec).unwrap();
+    for _ in 0..4410 {
+        writer.write_sample(i16::MAX / 2 + 1).unwrap();
+    }
+    writer.finalize().unwrap();
+    Box::leak(cursor.into_inner().into_boxed_slice())
+}
+
+/// A config with a "Kick" switch which plays drums/kick, and a "Stop" switch which stops all
+/// sounds. `sections` is appended to it, for example to configure OSC.
+pub fn drums_config(sections: &str) -> Config {
+    let config_source = format!(r###"
+banks:
+  - id: drums
+    samples:
+      - id: kick
+        file: kick.wav
+switches:
+  - title: Kick
+    play:
+      bank: drums
+      sample: kick
+  - title: Stop
+    stopSounds: true
+{}"###, sections);
+    Config::from_string(&config_source, None).unwrap()
+}
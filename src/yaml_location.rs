@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::fmt;
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::Marker;
+
+/// A position in a YAML document. Both the line and the column start at 1.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl From<Marker> for Location {
+    fn from(marker: Marker) -> Self {
+        // yaml-rust2 lines already start at 1, but columns start at 0
+        Location { line: marker.line(), column: marker.col() + 1 }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+enum Frame {
+    Mapping {
+        path: String,
+        /// The key of the value that is about to be parsed, or None if a key is expected next
+        key: Option<String>,
+    },
+    Sequence {
+        path: String,
+        next_index: usize,
+    },
+}
+
+fn join_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    }
+    else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+/// Maps the path of every node in a YAML document (e.g. `switches[4].play.sample`) to its
+/// location. For values in a mapping, the location of the key is used.
+#[derive(Default)]
+pub struct YamlLocations {
+    locations: HashMap<String, Location>,
+    stack: Vec<Frame>,
+}
+
+impl YamlLocations {
+    /// Parse the given YAML string. Any syntax error is ignored, in that case the locations of the
+    /// nodes before the error are still available.
+    pub fn parse(yaml_string: &str) -> Self {
+        let mut locations = YamlLocations::default();
+        let mut parser = Parser::new(yaml_string.chars());
+        let _ = parser.load(&mut locations, false);
+        locations
+    }
+
+    pub fn get(&self, path: &str) -> Option<Location> {
+        self.locations.get(path).copied()
+    }
+
+    /// Determine the path of a node that is about to start. Returns None if the node is a mapping
+    /// key, which has been handled completely.
+    fn node_path(&mut self, event: &Event, marker: Marker) -> Option<String> {
+        match self.stack.last_mut() {
+            None => Some(String::new()),
+            Some(Frame::Mapping { path, key }) => {
+                match key.take() {
+                    None => {
+                        // this node is a key. Complex keys (a mapping or sequence as a key)
+                        // are not supported by the config, so they get a path that never matches
+                        let key_string = match event {
+                            Event::Scalar(value, ..) => value.clone(),
+                            _ => String::from("?"),
+                        };
+                        let child_path = join_path(path, &key_string);
+                        self.locations.entry(child_path).or_insert_with(|| Location::from(marker));
+                        // The marker of a block mapping points past its first key, use the
+                        // location of the first key instead (see below)
+                        if !path.is_empty() {
+                            self.locations.entry(path.clone()).or_insert_with(|| Location::from(marker));
+                        }
+                        *key = Some(key_string);
+
+                        match event {
+                            Event::MappingStart(..) | Event::SequenceStart(..) => Some(String::from("?")),
+                            _ => None,
+                        }
+                    },
+                    Some(key) => Some(join_path(path, &key)),
+                }
+            },
+            Some(Frame::Sequence { path, next_index }) => {
+                let child_path = format!("{}[{}]", path, next_index);
+                *next_index += 1;
+                if !matches!(event, Event::MappingStart(..)) {
+                    self.locations.entry(child_path.clone()).or_insert_with(|| Location::from(marker));
+                }
+                Some(child_path)
+            },
+        }
+    }
+}
+
+impl MarkedEventReceiver for YamlLocations {
+    fn on_event(&mut self, event: Event, marker: Marker) {
+        match event {
+            Event::Scalar(..) | Event::Alias(_) => {
+                self.node_path(&event, marker);
+            },
+            Event::MappingStart(..) => {
+                if let Some(path) = self.node_path(&event, marker) {
+                    self.stack.push(Frame::Mapping { path, key: None });
+                }
+            },
+            Event::SequenceStart(..) => {
+                if let Some(path) = self.node_path(&event, marker) {
+                    self.stack.push(Frame::Sequence { path, next_index: 0 });
+                }
+            },
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+            },
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::yaml_location::{Location, YamlLocations};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn locations_of_nested_nodes() {
+        let yaml = r###"banks:
+  - id: bankA
+    samples:
+      - { id: foo1, file: foo1.mp3 }
+switches:
+  - title: first
+  - title: second
+    play:
+      bank: bankA
+      sample: foo1
+"###;
+        let locations = YamlLocations::parse(yaml);
+        let l = |line, column| Some(Location { line, column });
+
+        assert_eq!(locations.get("banks"), l(1, 1));
+        assert_eq!(locations.get("banks[0]"), l(2, 5));
+        assert_eq!(locations.get("banks[0].id"), l(2, 5));
+        assert_eq!(locations.get("banks[0].samples[0].file"), l(4, 21));
+        assert_eq!(locations.get("switches[0].title"), l(6, 5));
+        assert_eq!(locations.get("switches[1].play"), l(8, 5));
+        assert_eq!(locations.get("switches[1].play.sample"), l(10, 7));
+        assert_eq!(locations.get("switches[2]"), None);
+    }
+}